@@ -0,0 +1,155 @@
+//! Times the diff engine, the grouping of the diffs into snippets and the
+//! writing of the reports over synthetic metric files of several sizes.
+//!
+//! Run with `cargo bench`. `cargo bench -- --save DIR` also writes the
+//! generated metric files and sources in `DIR`, to be compared again.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use json_minimal_tests::{json_diffs, Comparator};
+use serde_json::{json, Value};
+
+// Number of functions of the generated source files
+const SIZES: &[usize] = &[100, 1_000, 10_000];
+// Runs of each case, the median is printed
+const RUNS: usize = 7;
+
+/// Generates the metrics of a source file with `functions` functions in
+/// impl blocks of ten, the new version changing one function out of
+/// three, and the source file itself.
+fn generate(functions: usize) -> (Value, Value, String) {
+    let mut source = String::new();
+    let mut blocks = (Vec::new(), Vec::new());
+    for block in 0..functions.div_ceil(10) {
+        let start_line = source.lines().count() + 1;
+        source.push_str(&format!("impl S{block} {{\n"));
+        let mut spaces = (Vec::new(), Vec::new());
+        for i in block * 10..(block * 10 + 10).min(functions) {
+            let line = source.lines().count() + 1;
+            source.push_str(&format!(
+                "    fn f{i}(a: i32) -> i32 {{\n        a + {i}\n    }}\n"
+            ));
+            for (version, spaces) in [(0, &mut spaces.0), (1, &mut spaces.1)] {
+                let changed = version == 1 && i % 3 == 0;
+                spaces.push(space(
+                    &format!("f{i}"),
+                    "function",
+                    (line, line + 2),
+                    if changed { 2.0 } else { 1.0 },
+                    Vec::new(),
+                ));
+            }
+        }
+        source.push_str("}\n");
+        let lines = (start_line, source.lines().count());
+        blocks
+            .0
+            .push(space(&format!("S{block}"), "impl", lines, 1.0, spaces.0));
+        blocks
+            .1
+            .push(space(&format!("S{block}"), "impl", lines, 1.0, spaces.1));
+    }
+    let lines = (1, source.lines().count());
+    (
+        space("bench.rs", "unit", lines, 1.0, blocks.0),
+        space("bench.rs", "unit", lines, 1.0, blocks.1),
+        source,
+    )
+}
+
+fn space(name: &str, kind: &str, lines: (usize, usize), sum: f64, spaces: Vec<Value>) -> Value {
+    json!({
+        "name": name,
+        "kind": kind,
+        "start_line": lines.0,
+        "end_line": lines.1,
+        "metrics": {
+            "cyclomatic": { "sum": sum, "average": sum },
+            "nargs": { "total_functions": 1.0 },
+            "loc": { "sloc": (lines.1 - lines.0 + 1) as f64 },
+        },
+        "spaces": spaces,
+    })
+}
+
+/// Runs a case `RUNS` times and prints its median time.
+fn bench(name: &str, mut case: impl FnMut()) {
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            case();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    println!("{name:<40} {:>12.3?}", times[RUNS / 2]);
+}
+
+/// Writes the metric files of a size and their source file in a
+/// directory laid out as the command expects them.
+fn save(dir: &Path, functions: usize, (old, new, source): &(Value, Value, String)) -> PathBuf {
+    let root = dir.join(functions.to_string());
+    for (side, json) in [("old", old), ("new", new)] {
+        let mut json = json.clone();
+        let source_path = root.join("src").join("bench.rs");
+        json["name"] = json!(source_path.display().to_string());
+        fs::create_dir_all(root.join(side)).unwrap();
+        fs::write(
+            root.join(side).join("bench.rs.json"),
+            serde_json::to_vec(&json).unwrap(),
+        )
+        .unwrap();
+    }
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src").join("bench.rs"), source).unwrap();
+    root
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let saved = args
+        .iter()
+        .position(|arg| arg == "--save")
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from);
+    let dir = saved
+        .clone()
+        .unwrap_or_else(|| std::env::temp_dir().join("json-minimal-tests-bench"));
+
+    for &functions in SIZES {
+        let files = generate(functions);
+        let (old, new, _) = &files;
+        bench(&format!("json_diffs/{functions}"), || {
+            json_diffs(old, new, false);
+        });
+        let comparator = Comparator::new().ignore_metric("loc.*").tolerance(0.5);
+        bench(&format!("comparator/{functions}"), || {
+            comparator.compare_values(old, new);
+        });
+
+        // The snippets and the reports are only reachable through the command
+        let root = save(&dir, functions, &files);
+        for format in ["html", "json"] {
+            let output = root.join(format!("reports-{format}"));
+            bench(&format!("reports/{format}/{functions}"), || {
+                let _ = fs::remove_dir_all(&output);
+                fs::create_dir_all(&output).unwrap();
+                let status = Command::new(env!("CARGO_BIN_EXE_json-minimal-tests"))
+                    .arg(root.join("old"))
+                    .arg(root.join("new"))
+                    .args(["-o".as_ref(), output.as_os_str()])
+                    .args(["--format", format, "-q"])
+                    .status()
+                    .unwrap();
+                // The command fails when it finds diffs
+                assert!(status.code().is_some());
+            });
+        }
+    }
+    if saved.is_none() {
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde_json::Value;
+
+use crate::diff::{has_space_kind, ABSENT};
+use crate::schema::Schema;
+use crate::space::Space;
+use crate::{Options, SnippetDiff};
+
+/// How the children of two spaces are matched before being compared.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum SpaceMatching {
+    // By index, or by name and kind when their number differs
+    #[default]
+    Index,
+    // By name, kind and start line, wherever they are
+    Key,
+}
+
+impl SpaceMatching {
+    pub(crate) const NAMES: &'static [&'static str] = &["index", "key"];
+
+    pub(crate) fn parse(name: &str) -> Self {
+        match name {
+            "key" => SpaceMatching::Key,
+            _ => SpaceMatching::Index,
+        }
+    }
+}
+
+// The name and the kind of a space, which identify it among its siblings
+fn space_key(space: Space<'_>) -> (Option<&str>, Option<&str>) {
+    (space.name(), space.kind())
+}
+
+fn as_spaces<'a>(values: &'a [Value], schema: &'a Schema) -> Vec<Space<'a>> {
+    values
+        .iter()
+        .map(|value| Space::new(value, schema))
+        .collect()
+}
+
+/// Describes a space added or removed, e.g. `function foo (3, 10)`.
+fn describe(space: Space) -> String {
+    format!(
+        "{} {} ({}, {})",
+        space.kind().unwrap_or("space"),
+        space.name().unwrap_or("<unnamed>"),
+        space.start_line().unwrap_or(0),
+        space.end_line().unwrap_or(0)
+    )
+}
+
+/// Pairs the spaces of two lists with the same name and kind, keeping
+/// their order, as the longest common subsequence of their keys.
+fn match_spaces(old: &[Space], new: &[Space]) -> Vec<(usize, usize)> {
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if space_key(old[i]) == space_key(new[j]) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if space_key(old[i]) == space_key(new[j]) {
+            matches.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+/// Pairs the spaces of two lists with the same name, kind and start line,
+/// once the old one is shifted by `line_shift`, whatever their order,
+/// in the order of the old list.
+fn match_spaces_by_key(old: &[Space], new: &[Space], line_shift: i64) -> Vec<(usize, usize)> {
+    let mut indices: HashMap<_, VecDeque<usize>> = HashMap::new();
+    for (j, space) in new.iter().enumerate() {
+        indices
+            .entry((
+                space_key(*space),
+                space.start_line().map(|line| line as i64),
+            ))
+            .or_default()
+            .push_back(j);
+    }
+    old.iter()
+        .enumerate()
+        .filter_map(|(i, space)| {
+            let j = indices
+                .get_mut(&(
+                    space_key(*space),
+                    space.start_line().map(|line| line as i64 + line_shift),
+                ))?
+                .pop_front()?;
+            Some((i, j))
+        })
+        .collect()
+}
+
+/// Removes the spaces found in only one of two metric files, so that
+/// the remaining ones are compared with their counterparts rather than
+/// with the spaces at the same index.
+///
+/// Returns a diff for each removed space, with its path in the file
+/// where it is found and [`ABSENT`] on the other side. The children of
+/// two spaces are only aligned when their number differs, unless they
+/// are matched by key, in which case the new ones are also put in the
+/// order of the old ones, with the lines of the old ones shifted by
+/// `line_shift`.
+pub(crate) fn align_spaces(
+    old: &mut Value,
+    new: &mut Value,
+    line_shift: i64,
+    options: &Options,
+) -> Vec<SnippetDiff> {
+    let mut changes = Vec::new();
+    align_children(old, new, "", "", line_shift, options, &mut changes);
+    changes
+}
+
+fn align_children(
+    old: &mut Value,
+    new: &mut Value,
+    old_path: &str,
+    new_path: &str,
+    line_shift: i64,
+    options: &Options,
+    changes: &mut Vec<SnippetDiff>,
+) {
+    let spaces = &options.schema.spaces;
+    let (Some(Value::Array(old_spaces)), Some(Value::Array(new_spaces))) =
+        (old.get_mut(spaces), new.get_mut(spaces))
+    else {
+        return;
+    };
+
+    if old_spaces.len() == new_spaces.len() && options.space_matching == SpaceMatching::Index {
+        for (i, (old, new)) in old_spaces.iter_mut().zip(new_spaces).enumerate() {
+            let old_path = format!("{old_path}.{spaces}[{i}]");
+            let new_path = format!("{new_path}.{spaces}[{i}]");
+            align_children(old, new, &old_path, &new_path, line_shift, options, changes);
+        }
+        return;
+    }
+
+    let (old_list, new_list) = (
+        as_spaces(old_spaces, &options.schema),
+        as_spaces(new_spaces, &options.schema),
+    );
+    let matches = match options.space_matching {
+        SpaceMatching::Index => match_spaces(&old_list, &new_list),
+        SpaceMatching::Key => match_spaces_by_key(&old_list, &new_list, line_shift),
+    };
+    let mut changed = |values: &[Value], is_old: bool, path: &str, matched: &[usize]| {
+        for (i, value) in values.iter().enumerate() {
+            let space = Space::new(value, &options.schema);
+            if matched.contains(&i) || !has_space_kind(space, options) {
+                continue;
+            }
+            let (old, new) = if is_old {
+                (describe(space), ABSENT.to_owned())
+            } else {
+                (ABSENT.to_owned(), describe(space))
+            };
+            changes.push(SnippetDiff {
+                path: format!("{path}.{spaces}[{i}]"),
+                old,
+                new,
+            });
+        }
+    };
+    let old_matched: Vec<usize> = matches.iter().map(|(i, _)| *i).collect();
+    let new_matched: Vec<usize> = matches.iter().map(|(_, j)| *j).collect();
+    changed(old_spaces, true, old_path, &old_matched);
+    changed(new_spaces, false, new_path, &new_matched);
+
+    for &(i, j) in &matches {
+        let old_path = format!("{old_path}.{spaces}[{i}]");
+        let new_path = format!("{new_path}.{spaces}[{j}]");
+        align_children(
+            &mut old_spaces[i],
+            &mut new_spaces[j],
+            &old_path,
+            &new_path,
+            line_shift,
+            options,
+            changes,
+        );
+    }
+
+    // The matched spaces in the order of their matches
+    let keep = |values: &mut Vec<Value>, matched: &[usize]| {
+        let mut values: Vec<Option<Value>> = std::mem::take(values).into_iter().map(Some).collect();
+        matched
+            .iter()
+            .filter_map(|&i| values[i].take())
+            .collect::<Vec<Value>>()
+    };
+    *old_spaces = keep(old_spaces, &old_matched);
+    *new_spaces = keep(new_spaces, &new_matched);
+}
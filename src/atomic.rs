@@ -0,0 +1,40 @@
+use std::fs::File;
+use std::io::{BufWriter, Error, ErrorKind, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Tells apart the temporary files of the threads of a run
+static NEXT_TEMP: AtomicUsize = AtomicUsize::new(0);
+
+/// Prefix of the temporary files, which only a killed run leaves behind.
+pub(crate) const TEMP_PREFIX: &str = ".json-minimal-tests-";
+
+/// Writes a file through a temporary file of its directory, renamed over
+/// it once complete. A reader never sees a partial file, even when the run
+/// is killed while writing it, and two runs writing the same file leave
+/// the whole version of one of them.
+pub(crate) fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "not a file path"))?;
+    // The process id tells apart the files of concurrent runs
+    let temp_path = path.with_file_name(format!(
+        "{TEMP_PREFIX}{}-{}-{}",
+        std::process::id(),
+        NEXT_TEMP.fetch_add(1, Ordering::Relaxed),
+        file_name.to_string_lossy()
+    ));
+    let result = File::create(&temp_path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()?;
+        std::fs::rename(&temp_path, path)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
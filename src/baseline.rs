@@ -0,0 +1,86 @@
+use std::collections::BTreeSet;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::SnippetDiff;
+
+// Source file name, diff path, old and new value
+type Entry = (String, String, String, String);
+
+fn entry(source_filename: &str, diff: &SnippetDiff) -> Entry {
+    (
+        source_filename.to_owned(),
+        diff.path.clone(),
+        diff.old.clone(),
+        diff.new.clone(),
+    )
+}
+
+/// The diffs accepted as intentional metric changes, which are no longer
+/// reported.
+///
+/// A diff is accepted for a source file, a path and both its values,
+/// so a further change of the same metric is reported again.
+#[derive(Default)]
+pub(crate) struct Baseline {
+    entries: BTreeSet<Entry>,
+}
+
+impl Baseline {
+    pub(crate) fn read(path: &Path) -> std::io::Result<Self> {
+        let json: Value = serde_json::from_slice(&std::fs::read(path)?)?;
+        let invalid = || Error::new(ErrorKind::InvalidData, "not a baseline file");
+        let mut baseline = Self::default();
+        for diff in json
+            .get("diffs")
+            .and_then(Value::as_array)
+            .ok_or_else(invalid)?
+        {
+            let field = |name| {
+                diff.get(name)
+                    .and_then(Value::as_str)
+                    .map(str::to_owned)
+                    .ok_or_else(invalid)
+            };
+            baseline.entries.insert((
+                field("source")?,
+                field("path")?,
+                field("old")?,
+                field("new")?,
+            ));
+        }
+        Ok(baseline)
+    }
+
+    pub(crate) fn accept(&mut self, source_filename: &str, diff: &SnippetDiff) {
+        self.entries.insert(entry(source_filename, diff));
+    }
+
+    pub(crate) fn remove(&mut self, source_filename: &str, diff: &SnippetDiff) {
+        self.entries.remove(&entry(source_filename, diff));
+    }
+
+    pub(crate) fn is_accepted(&self, source_filename: &str, diff: &SnippetDiff) -> bool {
+        self.entries.contains(&entry(source_filename, diff))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Writes the accepted diffs, sorted so that the file can be reviewed
+    /// and versioned.
+    pub(crate) fn write(&self, path: &Path) -> std::io::Result<()> {
+        let diffs: Vec<Value> = self
+            .entries
+            .iter()
+            .map(|(source, path, old, new)| {
+                json!({ "source": source, "path": path, "old": old, "new": new })
+            })
+            .collect();
+        let json = serde_json::to_string_pretty(&json!({ "diffs": diffs }))?;
+        std::fs::write(path, json)
+    }
+}
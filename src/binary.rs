@@ -0,0 +1,84 @@
+use serde_json::{Number, Value};
+
+// As deep as serde_json nests, to fail rather than overflow the stack
+const MAX_DEPTH: usize = 128;
+
+/// The position in a binary document, CBOR or MessagePack, read by their
+/// decoders. The errors tell the offset of the byte where they happened.
+pub(crate) struct Cursor<'a> {
+    bytes: &'a [u8],
+    pub(crate) pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub(crate) fn error(&self, message: &str) -> String {
+        format!("byte {}: {}", self.pos, message)
+    }
+
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    // The bytes left, each item taking at least one, so that a length read
+    // from the document is not trusted to reserve memory
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| self.error("unexpected end of the file"))?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    // A big-endian unsigned integer of `len` bytes
+    pub(crate) fn uint(&mut self, len: usize) -> Result<u64, String> {
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0, |n, &byte| n << 8 | u64::from(byte)))
+    }
+
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            Err(self.error("too deeply nested"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the decoded document, when no data follows it.
+    pub(crate) fn end(&self, value: Value) -> Result<Value, String> {
+        if self.pos < self.bytes.len() {
+            return Err(self.error("unexpected data after the document"));
+        }
+        Ok(value)
+    }
+}
+
+// Decodes a string whose first byte is at `start`
+pub(crate) fn utf8(start: usize, bytes: Vec<u8>) -> Result<String, String> {
+    String::from_utf8(bytes).map_err(|_| format!("byte {start}: invalid UTF-8 string"))
+}
+
+pub(crate) fn float(n: f64) -> Value {
+    // NaN and infinities have no JSON number, as in serde_json
+    Number::from_f64(n).map_or(Value::Null, Value::Number)
+}
+
+// The keys of the maps which are not strings are written as JSON, e.g. `1`
+pub(crate) fn map_key(key: Value) -> String {
+    match key {
+        Value::String(key) => key,
+        key => key.to_string(),
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashSet;
+use std::io::Write;
+
+use crate::filter::{FilterRule, FilteredDiff};
+use crate::top::{score, DeferredReport};
+use crate::{LinesRange, SortBy};
+
+/// Counts the bytes of a report without keeping them.
+#[derive(Default)]
+pub(crate) struct ByteCounter(pub(crate) u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Leaves out the snippets with the smallest change of the whole run until
+/// the reports, whose sizes are measured by `size`, fit in `budget` bytes.
+/// Their diffs are moved to the filtered diffs. Returns the source file and
+/// the lines of the snippets left out.
+///
+/// Each round leaves out the snippets expected to save the bytes over
+/// the budget, from the average size of a snippet of their report, and
+/// measures again the reports changed.
+pub(crate) fn fit_budget(
+    reports: &mut [DeferredReport],
+    budget: u64,
+    size: impl Fn(&DeferredReport) -> u64,
+) -> Vec<(String, LinesRange)> {
+    let mut sizes: Vec<u64> = reports.iter().map(&size).collect();
+    let mut omitted = Vec::new();
+    loop {
+        let total: u64 = sizes.iter().sum();
+        if total <= budget {
+            break;
+        }
+        let mut candidates: Vec<(f64, usize, LinesRange)> = reports
+            .iter()
+            .enumerate()
+            .flat_map(|(i, report)| {
+                report
+                    .snippets
+                    .snippets_data
+                    .iter()
+                    .map(move |(lines_range, diffs)| {
+                        (score(diffs, SortBy::Delta), i, lines_range.clone())
+                    })
+            })
+            .collect();
+        // Nothing left to leave out, the global metrics alone are too large
+        if candidates.is_empty() {
+            break;
+        }
+        // The smallest changes first, then the last snippets of the run
+        candidates.sort_by(|a, b| {
+            a.0.total_cmp(&b.0)
+                .then(b.1.cmp(&a.1))
+                .then(b.2.start_line.cmp(&a.2.start_line))
+        });
+        let average_sizes: Vec<u64> = reports
+            .iter()
+            .zip(&sizes)
+            .map(|(report, size)| size / report.snippets.snippets_data.len().max(1) as u64)
+            .collect();
+        let mut over = total - budget;
+        let mut changed = HashSet::new();
+        for (_, i, lines_range) in candidates {
+            let snippets = &mut reports[i].snippets;
+            let diffs = snippets
+                .snippets_data
+                .remove(&lines_range)
+                .unwrap_or_default();
+            snippets
+                .filtered
+                .extend(diffs.into_iter().map(|diff| FilteredDiff {
+                    diff,
+                    rule: FilterRule::Budget,
+                }));
+            snippets.omitted_snippets += 1;
+            omitted.push((snippets.source_filename.clone(), lines_range));
+            changed.insert(i);
+            if average_sizes[i] >= over {
+                break;
+            }
+            over -= average_sizes[i];
+        }
+        for i in changed {
+            reports[i]
+                .snippets
+                .filtered
+                .sort_by(|a, b| a.diff.path.cmp(&b.diff.path));
+            sizes[i] = size(&reports[i]);
+        }
+    }
+    omitted
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use serde_json::{json, Map, Value};
+
+use crate::csv::escape_field;
+use crate::fingerprint::fingerprint;
+use crate::html::{space_path, write_by_metric_page};
+use crate::pr_comment::escape_cell;
+use crate::summary::{format_change, metric_name};
+use crate::toml_report::write_table;
+use crate::{CodeSnippets, Format, Options, SnippetDiff};
+
+/// Name of the report grouped by metric, without its extension.
+pub(crate) const BY_METRIC_NAME: &str = "by-metric";
+
+/// A diff of a source file, in the section of its metric.
+pub(crate) struct MetricDiff {
+    pub(crate) source_filename: String,
+    pub(crate) diff: SnippetDiff,
+}
+
+/// The diffs of all the source files, by metric name, e.g.
+/// `cyclomatic.sum`. The diffs of a metric are in the order of their
+/// source files, then of their paths.
+#[derive(Default)]
+pub(crate) struct MetricGroups {
+    pub(crate) groups: BTreeMap<String, Vec<MetricDiff>>,
+}
+
+impl MetricGroups {
+    /// Adds the reported diffs of a source file to the sections of their
+    /// metrics.
+    pub(crate) fn add(&mut self, snippets: &CodeSnippets, options: &Options) {
+        let diffs = snippets
+            .global_metrics
+            .iter()
+            .chain(snippets.snippets_data.values().flatten())
+            .chain(snippets.positions.values().flatten())
+            .chain(&snippets.space_changes);
+        for diff in diffs {
+            self.groups
+                .entry(metric_name(&diff.path, &options.schema).to_owned())
+                .or_default()
+                .push(MetricDiff {
+                    source_filename: snippets.source_filename.clone(),
+                    diff: diff.clone(),
+                });
+        }
+    }
+
+    fn sort(&mut self) {
+        for diffs in self.groups.values_mut() {
+            diffs.sort_by(|a, b| {
+                (&a.source_filename, &a.diff.path).cmp(&(&b.source_filename, &b.diff.path))
+            });
+        }
+    }
+
+    fn to_map(&self, options: &Options) -> Map<String, Value> {
+        let metrics: Vec<Value> =
+            self.groups
+                .iter()
+                .map(|(metric, diffs)| {
+                    let diffs: Vec<Value> = diffs
+                    .iter()
+                    .map(|MetricDiff { source_filename, diff }| {
+                        json!({
+                            "source": source_filename,
+                            "space": space_path(&diff.path),
+                            "path": diff.path,
+                            "old": diff.old,
+                            "new": diff.new,
+                            "change": diff.delta(),
+                            "fingerprint": fingerprint(source_filename, diff, &options.schema),
+                        })
+                    })
+                    .collect();
+                    json!({ "metric": metric, "diffs": diffs })
+                })
+                .collect();
+        let mut map = Map::new();
+        map.insert("metrics".to_owned(), Value::Array(metrics));
+        map
+    }
+}
+
+fn write_markdown(
+    writer: &mut dyn Write,
+    groups: &MetricGroups,
+    options: &Options,
+) -> std::io::Result<()> {
+    writeln!(writer, "# Metric diffs by metric\n")?;
+    for (metric, diffs) in &groups.groups {
+        writeln!(
+            writer,
+            "## `{}`\n\n| Source | Path | Old | New | Change | Fingerprint |\n|---|---|---|---|---|---|",
+            escape_cell(metric)
+        )?;
+        for MetricDiff {
+            source_filename,
+            diff,
+        } in diffs
+        {
+            writeln!(
+                writer,
+                "| {} | `{}` | {} | {} | {} | `{}` |",
+                escape_cell(source_filename),
+                escape_cell(&diff.path),
+                escape_cell(&diff.old),
+                escape_cell(&diff.new),
+                format_change(diff, options.precision).unwrap_or_default(),
+                fingerprint(source_filename, diff, &options.schema)
+            )?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+fn write_csv(
+    writer: &mut dyn Write,
+    groups: &MetricGroups,
+    options: &Options,
+) -> std::io::Result<()> {
+    writeln!(writer, "metric,source,path,old,new,change,fingerprint")?;
+    for (metric, diffs) in &groups.groups {
+        for MetricDiff {
+            source_filename,
+            diff,
+        } in diffs
+        {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{}",
+                escape_field(metric),
+                escape_field(source_filename),
+                escape_field(&diff.path),
+                escape_field(&diff.old),
+                escape_field(&diff.new),
+                escape_field(&format_change(diff, options.precision).unwrap_or_default()),
+                fingerprint(source_filename, diff, &options.schema)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a report with a section for each metric, listing the source
+/// files and the spaces where it changed. The JUnit format has no
+/// sections, so it is rejected with `--group-by metric`.
+pub(crate) fn write_by_metric(
+    writer: &mut dyn Write,
+    groups: &mut MetricGroups,
+    options: &Options,
+) -> std::io::Result<()> {
+    groups.sort();
+    match options.format {
+        Format::Html | Format::HtmlLegacy => write_by_metric_page(writer, groups, options),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *writer, &groups.to_map(options))?;
+            writeln!(writer)
+        }
+        Format::Toml => write_table(writer, &[], &groups.to_map(options)),
+        Format::Markdown => write_markdown(writer, groups, options),
+        Format::Csv => write_csv(writer, groups, options),
+        Format::Junit => Ok(()),
+    }
+}
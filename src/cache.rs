@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::sha256::{sha256_hex, Sha256};
+use crate::summary::PairStatus;
+
+/// An on-disk cache of the outcome of the comparisons, so that a pair
+/// whose files have not changed since the previous run is skipped.
+///
+/// Each pair has an entry named after the hash of its paths, storing the
+/// hash of the files and the outcome of their comparison. Only the
+/// outcomes which do not produce a report are reused.
+pub(crate) struct Cache {
+    dir: PathBuf,
+    // Identifies the options which change the outcome of a comparison
+    options_key: String,
+}
+
+impl Cache {
+    pub(crate) fn new(dir: PathBuf, options_key: String) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir, options_key })
+    }
+
+    fn entry_path(&self, path1: &Path, path2: &Path) -> PathBuf {
+        let paths = format!("{}\0{}", path1.display(), path2.display());
+        self.dir.join(sha256_hex(paths.as_bytes()))
+    }
+
+    /// Returns the hash of the content of a pair and of the options.
+    pub(crate) fn pair_key(&self, path1: &Path, path2: &Path) -> std::io::Result<String> {
+        let mut hasher = Sha256::default();
+        hasher.update(self.options_key.as_bytes());
+        for path in [path1, path2] {
+            let mut file = File::open(path)?;
+            let mut buffer = vec![0; 64 * 1024];
+            let mut len = 0u64;
+            loop {
+                let read = file.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+                len += read as u64;
+            }
+            // Keep the boundary between the two files
+            hasher.update(&len.to_le_bytes());
+        }
+        Ok(hasher.finish_hex())
+    }
+
+    /// Returns the outcome of the previous comparison of a pair,
+    /// when its files are the same and it produced no report.
+    pub(crate) fn lookup(&self, path1: &Path, path2: &Path, key: &str) -> Option<PairStatus> {
+        let entry: Value =
+            serde_json::from_slice(&std::fs::read(self.entry_path(path1, path2)).ok()?).ok()?;
+        if entry.get("key").and_then(Value::as_str) != Some(key) {
+            return None;
+        }
+        match entry.get("status").and_then(Value::as_str)? {
+            "identical" => Some(PairStatus::Identical),
+            "filtered" => Some(PairStatus::Filtered),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn store(
+        &self,
+        path1: &Path,
+        path2: &Path,
+        key: &str,
+        status: PairStatus,
+    ) -> std::io::Result<()> {
+        let entry = json!({ "key": key, "status": status.name() });
+        std::fs::write(self.entry_path(path1, path2), entry.to_string())
+    }
+}
@@ -0,0 +1,247 @@
+use serde_json::{Map, Value};
+
+use crate::binary::{float, map_key, utf8, Cursor};
+
+// The additional information of an indefinite length, and of the break
+// ending the items of an indefinite length
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = 0xff;
+
+struct Decoder<'a> {
+    cursor: Cursor<'a>,
+}
+
+// Converts a half-precision float, which has no Rust type
+fn half_to_f64(half: u16) -> f64 {
+    let exponent = i32::from(half >> 10 & 0x1f);
+    let mantissa = f64::from(half & 0x3ff);
+    let magnitude = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        0x1f if mantissa == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent - 25),
+    };
+    if half & 0x8000 == 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+impl Decoder<'_> {
+    // The argument following the initial byte, none for an indefinite
+    // length
+    fn argument(&mut self, info: u8) -> Result<Option<u64>, String> {
+        match info {
+            0..=23 => Ok(Some(u64::from(info))),
+            24..=27 => self.cursor.uint(1 << (info - 24)).map(Some),
+            INDEFINITE => Ok(None),
+            _ => Err(self.cursor.error("invalid additional information")),
+        }
+    }
+
+    fn is_break(&mut self) -> Result<bool, String> {
+        match self.cursor.peek() {
+            Some(BREAK) => {
+                self.cursor.pos += 1;
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Err(self.cursor.error("unexpected end of the file")),
+        }
+    }
+
+    // Whether an array or a map has an item after `count` ones, the break
+    // being read only when its length is indefinite
+    fn has_item(&mut self, len: Option<u64>, count: u64) -> Result<bool, String> {
+        match len {
+            Some(len) => Ok(count < len),
+            None => Ok(!self.is_break()?),
+        }
+    }
+
+    // The content of a byte or a text string, made of chunks of the same
+    // major type if its length is indefinite
+    fn chunks(&mut self, major: u8, len: Option<u64>) -> Result<Vec<u8>, String> {
+        let Some(len) = len else {
+            let mut bytes = Vec::new();
+            while !self.is_break()? {
+                let initial = self.cursor.take(1)?[0];
+                if initial >> 5 != major || initial & 0x1f == INDEFINITE {
+                    return Err(self.cursor.error("invalid chunk of an indefinite string"));
+                }
+                let len = self.argument(initial & 0x1f)?;
+                bytes.extend(self.chunks(major, len)?);
+            }
+            return Ok(bytes);
+        };
+        let len = usize::try_from(len).map_err(|_| self.cursor.error("string too long"))?;
+        Ok(self.cursor.take(len)?.to_vec())
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, String> {
+        self.cursor.check_depth(depth)?;
+        let initial = self.cursor.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        if major == 7 {
+            return match info {
+                20 => Ok(Value::Bool(false)),
+                21 => Ok(Value::Bool(true)),
+                22 | 23 => Ok(Value::Null),
+                25 => Ok(float(half_to_f64(self.cursor.uint(2)? as u16))),
+                26 => Ok(float(f64::from(
+                    f32::from_bits(self.cursor.uint(4)? as u32),
+                ))),
+                27 => Ok(float(f64::from_bits(self.cursor.uint(8)?))),
+                _ => Err(self.cursor.error("unsupported simple value")),
+            };
+        }
+        let argument = self.argument(info)?;
+        match (major, argument) {
+            (0, Some(n)) => Ok(Value::from(n)),
+            (1, Some(n)) => Ok(match i64::try_from(n) {
+                Ok(n) => Value::from(-1 - n),
+                Err(_) => float(-1.0 - n as f64),
+            }),
+            // Byte strings, as serde_json writes bytes
+            (2, len) => Ok(Value::Array(
+                self.chunks(2, len)?.into_iter().map(Value::from).collect(),
+            )),
+            (3, len) => {
+                let start = self.cursor.pos;
+                let bytes = self.chunks(3, len)?;
+                utf8(start, bytes).map(Value::String)
+            }
+            (4, len) => {
+                let mut items = Vec::new();
+                let mut count = 0;
+                while self.has_item(len, count)? {
+                    items.push(self.value(depth + 1)?);
+                    count += 1;
+                }
+                Ok(Value::Array(items))
+            }
+            (5, len) => {
+                let mut map = Map::new();
+                let mut count = 0;
+                while self.has_item(len, count)? {
+                    let key = map_key(self.value(depth + 1)?);
+                    map.insert(key, self.value(depth + 1)?);
+                    count += 1;
+                }
+                Ok(Value::Object(map))
+            }
+            // The tags, e.g. of a date, are left out for the tagged value
+            (6, Some(_)) => self.value(depth + 1),
+            _ => Err(self.cursor.error("invalid indefinite length")),
+        }
+    }
+}
+
+/// Decodes a CBOR document into the JSON value with the same structure.
+/// The keys of the maps which are not strings are written as JSON, e.g.
+/// `1`, and the tags are left out.
+pub(crate) fn parse_cbor(bytes: &[u8]) -> Result<Value, String> {
+    let mut decoder = Decoder {
+        cursor: Cursor::new(bytes),
+    };
+    let value = decoder.value(0)?;
+    decoder.cursor.end(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn values() {
+        assert_eq!(
+            parse_cbor(&[0x83, 0x01, 0x20, 0x63, b'a', b'b', b'c']).unwrap(),
+            json!([1, -1, "abc"])
+        );
+        assert_eq!(parse_cbor(&[0xf9, 0x3c, 0x00]).unwrap(), json!(1.0));
+        // A tag, here of an epoch date, is left out
+        assert_eq!(parse_cbor(&[0xc1, 0x18, 0x64]).unwrap(), json!(100));
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(
+            parse_cbor(&[0x82, 0x01]).unwrap_err(),
+            "byte 2: unexpected end of the file"
+        );
+        assert_eq!(
+            parse_cbor(&[0x63, b'a']).unwrap_err(),
+            "byte 1: unexpected end of the file"
+        );
+        assert_eq!(
+            parse_cbor(&[0x9f, 0x01]).unwrap_err(),
+            "byte 2: unexpected end of the file"
+        );
+        assert_eq!(
+            parse_cbor(&[0x01, 0x02]).unwrap_err(),
+            "byte 1: unexpected data after the document"
+        );
+    }
+
+    #[test]
+    fn too_deep() {
+        let mut bytes = vec![0x81; 200];
+        bytes.push(0x00);
+        assert_eq!(
+            parse_cbor(&bytes).unwrap_err(),
+            "byte 129: too deeply nested"
+        );
+    }
+
+    #[test]
+    fn not_a_number() {
+        // Half, single and double precision NaN and infinities
+        for bytes in [
+            &[0xf9, 0x7e, 0x00][..],
+            &[0xf9, 0x7c, 0x00],
+            &[0xfa, 0x7f, 0xc0, 0x00, 0x00],
+            &[0xfb, 0xff, 0xf0, 0, 0, 0, 0, 0, 0],
+        ] {
+            assert_eq!(parse_cbor(bytes).unwrap(), Value::Null);
+        }
+    }
+
+    #[test]
+    fn non_string_keys() {
+        assert_eq!(
+            parse_cbor(&[0xa2, 0x01, 0x02, 0x81, 0xf5, 0x03]).unwrap(),
+            json!({ "1": 2, "[true]": 3 })
+        );
+    }
+
+    #[test]
+    fn indefinite_lengths() {
+        assert_eq!(
+            parse_cbor(&[0x9f, 0x01, 0x9f, 0xff, 0xff]).unwrap(),
+            json!([1, []])
+        );
+        assert_eq!(
+            parse_cbor(&[0xbf, 0x61, b'a', 0x01, 0xff]).unwrap(),
+            json!({ "a": 1 })
+        );
+        assert_eq!(
+            parse_cbor(&[0x7f, 0x61, b'a', 0x62, b'b', b'c', 0xff]).unwrap(),
+            json!("abc")
+        );
+        assert_eq!(
+            parse_cbor(&[0x5f, 0x41, 0x01, 0x42, 0x02, 0x03, 0xff]).unwrap(),
+            json!([1, 2, 3])
+        );
+        // A chunk of bytes in a text string
+        assert_eq!(
+            parse_cbor(&[0x7f, 0x41, 0x01, 0xff]).unwrap_err(),
+            "byte 2: invalid chunk of an indefinite string"
+        );
+        assert_eq!(
+            parse_cbor(&[0x1f]).unwrap_err(),
+            "byte 1: invalid indefinite length"
+        );
+    }
+}
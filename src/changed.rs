@@ -0,0 +1,45 @@
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Returns the paths, relative to the repository root, of the source files
+/// changed since the given revision, including uncommitted changes.
+pub(crate) fn changed_files(repo: &Path, since: &str) -> std::io::Result<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["diff", "--name-only", since, "--"])
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Checks whether a metric file corresponds to one of the changed sources.
+///
+/// rust-code-analysis names each metric file after its source file plus
+/// the `.json` extension, mirroring the source tree layout. The metric
+/// files may have one of the other `extensions` instead.
+pub(crate) fn is_changed(metric_path: &Path, extensions: &[String], changed: &[PathBuf]) -> bool {
+    let source_path = metric_path
+        .to_str()
+        .and_then(|path| {
+            extensions
+                .iter()
+                .find_map(|extension| path.strip_suffix(extension.as_str())?.strip_suffix('.'))
+        })
+        .map_or_else(|| metric_path.with_extension(""), PathBuf::from);
+    is_source_changed(&source_path, changed)
+}
+
+pub(crate) fn is_source_changed(source_path: &Path, changed: &[PathBuf]) -> bool {
+    changed.iter().any(|path| source_path.ends_with(path))
+}
@@ -0,0 +1,84 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Replaced by the path of the snippet file in a check command
+const FILE_PLACEHOLDER: &str = "{file}";
+
+// Numbers the directories of the snippets checked in parallel
+static NEXT_CHECK: AtomicUsize = AtomicUsize::new(0);
+
+/// A command checking that a snippet compiles on its own,
+/// e.g. `rustc --edition 2021 --emit=metadata {file}`.
+pub(crate) struct CheckCommand {
+    // Only check the snippets of the sources with this extension, all if none
+    extension: Option<String>,
+    program: String,
+    args: Vec<String>,
+}
+
+impl CheckCommand {
+    /// Parses a command, optionally prefixed by the extension of the
+    /// sources it checks, e.g. `rs=rustc {file}`. The snippet file is
+    /// appended to the command when it has no `{file}`.
+    pub(crate) fn parse(command: &str) -> Option<Self> {
+        let mut words = command.split_whitespace();
+        let first = words.next()?;
+        let (extension, program) = match first.split_once('=') {
+            Some((extension, program)) => (Some(extension.to_owned()), program),
+            None => (None, first),
+        };
+        if program.is_empty() {
+            return None;
+        }
+        let mut args: Vec<String> = words.map(str::to_owned).collect();
+        if !args.iter().any(|arg| arg.contains(FILE_PLACEHOLDER)) {
+            args.push(FILE_PLACEHOLDER.to_owned());
+        }
+        Some(Self {
+            extension,
+            program: program.to_owned(),
+            args,
+        })
+    }
+
+    pub(crate) fn applies_to(&self, source_filename: &str) -> bool {
+        self.extension.as_ref().is_none_or(|extension| {
+            Path::new(source_filename).extension() == Some(extension.as_ref())
+        })
+    }
+
+    /// Returns whether a snippet of a source file compiles on its own.
+    ///
+    /// The snippet is written in a temporary directory, with the extension
+    /// of its source file, and the command runs from there, so that the
+    /// files it emits are removed with it.
+    pub(crate) fn check(&self, snippet: &str, source_filename: &str) -> std::io::Result<bool> {
+        let dir = std::env::temp_dir().join(format!(
+            "json-minimal-tests-check-{}-{}",
+            std::process::id(),
+            NEXT_CHECK.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir)?;
+        let mut file = dir.join("snippet");
+        if let Some(extension) = Path::new(source_filename).extension() {
+            file.set_extension(extension);
+        }
+        let result = std::fs::write(&file, snippet).and_then(|_| {
+            let file = file.to_string_lossy();
+            Command::new(&self.program)
+                .args(
+                    self.args
+                        .iter()
+                        .map(|arg| arg.replace(FILE_PLACEHOLDER, &file)),
+                )
+                .current_dir(&dir)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(result?.success())
+    }
+}
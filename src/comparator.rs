@@ -0,0 +1,107 @@
+use serde_json::Value;
+
+use crate::glob::Glob;
+use crate::json_diff::json_diffs;
+use crate::schema::Schema;
+use crate::SnippetDiff;
+
+/// Compares two metric files, configured with its builder methods.
+///
+/// ```
+/// use json_minimal_tests::Comparator;
+/// use serde_json::json;
+///
+/// let old = json!({ "metrics": { "cyclomatic": { "sum": 2.0 }, "halstead": { "volume": 20.5 } } });
+/// let new = json!({ "metrics": { "cyclomatic": { "sum": 3.0 }, "halstead": { "volume": 99.0 } } });
+/// let diffs = Comparator::new()
+///     .ignore_metric("halstead.*")
+///     .tolerance(0.01)
+///     .compare_values(&old, &new);
+/// assert_eq!(diffs.len(), 1);
+/// assert_eq!(diffs[0].metric_name(), "cyclomatic.sum");
+/// assert_eq!(diffs[0].delta(), Some(1.0));
+/// ```
+#[derive(Debug, Default)]
+pub struct Comparator {
+    // Globs over the names of the metrics left out
+    ignored_metrics: Vec<Glob>,
+    // The changes of a number up to this are not diffs
+    tolerance: f64,
+    report_missing: bool,
+    // The layout of the metric files, to find the names of their metrics
+    schema: Schema,
+}
+
+impl Comparator {
+    /// A comparator reporting any change, as assert_json_diff does.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves out the metrics whose name matches a glob, e.g. `halstead.*`.
+    ///
+    /// # Panics
+    ///
+    /// When the glob is not valid, e.g. an unclosed character class.
+    pub fn ignore_metric(mut self, pattern: &str) -> Self {
+        let glob = Glob::new(pattern)
+            .unwrap_or_else(|err| panic!("`{pattern}` is not a valid glob: {err}"));
+        self.ignored_metrics.push(glob);
+        self
+    }
+
+    /// Leaves out the changes of a number up to `tolerance`.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Also reports the values found on one side only, with [`ABSENT`]
+    /// on the other one.
+    ///
+    /// [`ABSENT`]: crate::ABSENT
+    pub fn report_missing(mut self, report_missing: bool) -> Self {
+        self.report_missing = report_missing;
+        self
+    }
+
+    /// Compares metric files with another layout than the one of
+    /// rust-code-analysis, e.g. read with [`Schema::read`].
+    ///
+    /// ```
+    /// use json_minimal_tests::{Comparator, Schema};
+    /// use serde_json::json;
+    ///
+    /// let schema = Schema {
+    ///     metrics: "measures".to_owned(),
+    ///     ..Schema::default()
+    /// };
+    /// let old = json!({ "measures": { "loc": 10.0, "halstead": { "volume": 20.5 } } });
+    /// let new = json!({ "measures": { "loc": 12.0, "halstead": { "volume": 99.0 } } });
+    /// let diffs = Comparator::new()
+    ///     .schema(schema)
+    ///     .ignore_metric("halstead.*")
+    ///     .compare_values(&old, &new);
+    /// assert_eq!(diffs.len(), 1);
+    /// assert_eq!(diffs[0].path, ".measures.loc");
+    /// ```
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Returns the diffs between an old and a new metric file.
+    pub fn compare_values(&self, old: &Value, new: &Value) -> Vec<SnippetDiff> {
+        let mut diffs = json_diffs(old, new, self.report_missing);
+        diffs.retain(|diff| {
+            !self
+                .ignored_metrics
+                .iter()
+                .any(|glob| glob.is_match(diff.metric_name_in(&self.schema)))
+                && diff
+                    .delta()
+                    .is_none_or(|delta| delta.abs() > self.tolerance)
+        });
+        diffs
+    }
+}
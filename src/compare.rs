@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use crate::glob::{read_glob_rules, Glob};
+use crate::SnippetDiff;
+
+/// How the old and the new values of a metric are compared.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Comparison {
+    // Any change is reported
+    Exact,
+    // The changes of a number larger than this are reported
+    Absolute(f64),
+    // The changes of a number larger than this fraction of its old value
+    // are reported
+    Relative(f64),
+    // No change is reported
+    Ignore,
+    // The changes of the integer part of a number are reported
+    Integer,
+}
+
+impl Comparison {
+    /// Parses a comparison: `exact`, `abs 0.5`, `rel 0.05` or `rel 5%`,
+    /// `ignore` or `integer`.
+    pub(crate) fn parse(value: &str) -> Result<Self, String> {
+        let tolerance = |tolerance: &str| {
+            tolerance
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|tolerance| *tolerance >= 0.)
+                .ok_or_else(|| format!("`{tolerance}` is not a valid tolerance"))
+        };
+        match value.split_once(char::is_whitespace) {
+            Some(("abs", tolerance_value)) => Ok(Comparison::Absolute(tolerance(tolerance_value)?)),
+            Some(("rel", tolerance_value)) => Ok(Comparison::Relative(
+                match tolerance_value.trim().strip_suffix('%') {
+                    Some(percentage) => tolerance(percentage)? / 100.,
+                    None => tolerance(tolerance_value)?,
+                },
+            )),
+            _ => match value {
+                "exact" => Ok(Comparison::Exact),
+                "ignore" => Ok(Comparison::Ignore),
+                "integer" => Ok(Comparison::Integer),
+                _ => Err(
+                    "the comparison should be exact, abs N, rel N, rel N%, ignore or integer"
+                        .to_owned(),
+                ),
+            },
+        }
+    }
+
+    /// Whether the change of a diff is reported. The values which are
+    /// not numbers are compared exactly unless ignored.
+    pub(crate) fn is_reported(self, diff: &SnippetDiff) -> bool {
+        let numbers = diff
+            .old
+            .parse::<f64>()
+            .ok()
+            .zip(diff.new.parse::<f64>().ok());
+        match (self, numbers) {
+            (Comparison::Ignore, _) => false,
+            (Comparison::Absolute(tolerance), Some((old, new))) => (new - old).abs() > tolerance,
+            (Comparison::Relative(tolerance), Some((old, new))) => {
+                (new - old).abs() > tolerance * old.abs()
+            }
+            (Comparison::Integer, Some((old, new))) => old.trunc() != new.trunc(),
+            _ => true,
+        }
+    }
+}
+
+/// The comparison of the metrics, given as globs over their names.
+#[derive(Debug)]
+pub(crate) struct CompareRules {
+    rules: Vec<(Glob, Comparison)>,
+}
+
+impl CompareRules {
+    /// Reads the rules from a file with a `glob = comparison` rule per
+    /// line, e.g. `halstead.* = rel 1%`.
+    pub(crate) fn read(path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            rules: read_glob_rules(path, Comparison::parse)?,
+        })
+    }
+
+    /// Returns the comparison of the first rule matching a metric name,
+    /// exact when none does.
+    pub(crate) fn comparison(&self, metric_name: &str) -> Comparison {
+        self.rules
+            .iter()
+            .find(|(glob, _)| glob.is_match(metric_name))
+            .map_or(Comparison::Exact, |(_, comparison)| *comparison)
+    }
+}
@@ -0,0 +1,335 @@
+use std::ffi::OsString;
+use std::fmt;
+use std::path::Path;
+
+/// Name of the configuration file looked for in the current directory.
+pub(crate) const CONFIG_NAME: &str = "json-minimal-tests.toml";
+
+/// Returns whether a long option is on a command line, as `--name` or
+/// `--name=value`.
+pub(crate) fn is_long_option_given(args: &[OsString], name: &str) -> bool {
+    let (option, attached) = (format!("--{name}"), format!("--{name}="));
+    args.iter()
+        .filter_map(|arg| arg.to_str())
+        .any(|arg| arg == option || arg.starts_with(&attached))
+}
+
+/// Returns the value of a long option on a command line, given as
+/// `--name value` or `--name=value`.
+pub(crate) fn long_option_value(args: &[OsString], name: &str) -> Option<OsString> {
+    let (option, attached) = (format!("--{name}"), format!("--{name}="));
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some(arg) if arg == option => return args.next().cloned(),
+            Some(arg) if arg.starts_with(&attached) => {
+                return Some(OsString::from(&arg[attached.len()..]))
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A preset of options for a common workflow, given before the ones of
+/// the configuration file and of the command line, which take precedence.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Profile {
+    // Every metric is compared, a corrupt or missing input is an error
+    Strict,
+    // The spaces moved or renested by a new grammar are paired by name,
+    // with their positions and averages ignored as by default
+    GrammarBump,
+    // Nothing is ignored, to check the metrics being developed
+    MetricsDev,
+}
+
+impl Profile {
+    pub(crate) const NAMES: &'static [&'static str] = &["strict", "grammar-bump", "metrics-dev"];
+
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        match name {
+            "strict" => Some(Profile::Strict),
+            "grammar-bump" => Some(Profile::GrammarBump),
+            "metrics-dev" => Some(Profile::MetricsDev),
+            _ => None,
+        }
+    }
+
+    /// Returns the command line arguments of the profile, but the ones of
+    /// the options for which `is_set` is true.
+    pub(crate) fn args(self, is_set: impl Fn(&str) -> bool) -> Vec<OsString> {
+        let args: &[&str] = match self {
+            Profile::Strict => &[
+                "--strict",
+                "--fail-fast",
+                "--report-missing",
+                "--include-halstead",
+                "--include-mi",
+            ],
+            Profile::GrammarBump => &["--match-spaces=key", "--minimize"],
+            Profile::MetricsDev => &[
+                "--no-default-ignores",
+                "--include-halstead",
+                "--include-mi",
+                "--report-positions",
+                "--report-missing",
+            ],
+        };
+        args.iter()
+            .filter(|arg| {
+                let name = arg[2..].split('=').next().unwrap();
+                // An option given with its opposite one is left to it
+                let opposite = match name {
+                    "fail-fast" => "keep-going",
+                    _ => name,
+                };
+                !is_set(name) && !is_set(opposite)
+            })
+            .map(OsString::from)
+            .collect()
+    }
+}
+
+/// A value of the configuration file.
+#[derive(Debug, PartialEq)]
+enum ConfigValue {
+    Bool(bool),
+    // A string or a number, passed as it is written
+    Scalar(String),
+    Array(Vec<String>),
+}
+
+#[derive(Debug)]
+pub(crate) struct ConfigError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// The options of a `json-minimal-tests.toml` file, each one named as
+/// the long option of the command line, e.g. `format = "json"` or
+/// `ignore-path = [".spaces[0].start_line"]`. Only the top-level keys
+/// of TOML are read, with strings, numbers, booleans and arrays of them.
+#[derive(Debug, Default)]
+pub(crate) struct Config {
+    options: Vec<(String, ConfigValue)>,
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> ConfigError {
+        ConfigError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    // Skips the spaces and the comments, and the line breaks too in arrays
+    fn skip_blank(&mut self, newlines: bool) {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                ' ' | '\t' | '\r' => {}
+                '\n' if newlines => {}
+                '#' => {
+                    while self.chars.peek().is_some_and(|&c| c != '\n') {
+                        self.next();
+                    }
+                    continue;
+                }
+                _ => return,
+            }
+            self.next();
+        }
+    }
+
+    fn expect_line_end(&mut self) -> Result<(), ConfigError> {
+        self.skip_blank(false);
+        match self.next() {
+            None | Some('\n') => Ok(()),
+            Some(c) => Err(self.error(format!("unexpected `{c}` after the value"))),
+        }
+    }
+
+    fn string(&mut self, quote: char) -> Result<String, ConfigError> {
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some(c) if c == quote => return Ok(text),
+                // Literal strings have no escapes
+                Some('\\') if quote == '"' => match self.next() {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some('r') => text.push('\r'),
+                    Some('"') => text.push('"'),
+                    Some('\\') => text.push('\\'),
+                    Some('u') => {
+                        let code: String = (0..4).filter_map(|_| self.next()).collect();
+                        let c = u32::from_str_radix(&code, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| self.error("invalid unicode escape"))?;
+                        text.push(c);
+                    }
+                    _ => return Err(self.error("invalid escape")),
+                },
+                Some(c) => text.push(c),
+            }
+        }
+    }
+
+    fn key(&mut self) -> Result<String, ConfigError> {
+        match self.chars.peek() {
+            Some(&quote @ ('"' | '\'')) => {
+                self.next();
+                self.string(quote)
+            }
+            _ => {
+                let mut key = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                        break;
+                    }
+                    key.push(c);
+                    self.next();
+                }
+                if key.is_empty() {
+                    Err(self.error("expected a key"))
+                } else {
+                    Ok(key)
+                }
+            }
+        }
+    }
+
+    // A string, a number or a boolean
+    fn scalar(&mut self) -> Result<ConfigValue, ConfigError> {
+        match self.chars.peek() {
+            Some(&quote @ ('"' | '\'')) => {
+                self.next();
+                Ok(ConfigValue::Scalar(self.string(quote)?))
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || "+-._".contains(c)) {
+                        break;
+                    }
+                    word.push(c);
+                    self.next();
+                }
+                match word.as_str() {
+                    "true" => Ok(ConfigValue::Bool(true)),
+                    "false" => Ok(ConfigValue::Bool(false)),
+                    _ if word.replace('_', "").parse::<f64>().is_ok() => {
+                        Ok(ConfigValue::Scalar(word.replace('_', "")))
+                    }
+                    "" => Err(self.error("expected a value")),
+                    _ => Err(self.error(format!("invalid value `{word}`"))),
+                }
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<ConfigValue, ConfigError> {
+        if self.chars.peek() != Some(&'[') {
+            return self.scalar();
+        }
+        self.next();
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank(true);
+            if self.chars.peek() == Some(&']') {
+                self.next();
+                return Ok(ConfigValue::Array(items));
+            }
+            match self.scalar()? {
+                ConfigValue::Bool(value) => items.push(value.to_string()),
+                ConfigValue::Scalar(value) => items.push(value),
+                ConfigValue::Array(_) => unreachable!(),
+            }
+            self.skip_blank(true);
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(ConfigValue::Array(items)),
+                _ => return Err(self.error("expected `,` or `]` in the array")),
+            }
+        }
+    }
+}
+
+impl Config {
+    pub(crate) fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+            line: 1,
+        };
+        let mut config = Self::default();
+        loop {
+            parser.skip_blank(true);
+            match parser.chars.peek() {
+                None => return Ok(config),
+                Some('[') => {
+                    return Err(parser.error("tables are not supported, set the options at the top"))
+                }
+                _ => {}
+            }
+            let key = parser.key()?;
+            parser.skip_blank(false);
+            if parser.next() != Some('=') {
+                return Err(parser.error(format!("expected `=` after `{key}`")));
+            }
+            parser.skip_blank(false);
+            let value = parser.value()?;
+            parser.expect_line_end()?;
+            if config.options.iter().any(|(name, _)| *name == key) {
+                return Err(parser.error(format!("`{key}` is set twice")));
+            }
+            config.options.push((key, value));
+        }
+    }
+
+    pub(crate) fn read(path: &Path) -> std::io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
+    }
+
+    /// Returns the command line arguments of the options, but the ones
+    /// for which `is_set` is true, which are given on the command line.
+    pub(crate) fn args(&self, is_set: impl Fn(&str) -> bool) -> Vec<OsString> {
+        let mut args = Vec::new();
+        for (name, value) in &self.options {
+            if is_set(name) {
+                continue;
+            }
+            match value {
+                ConfigValue::Bool(true) => args.push(format!("--{name}")),
+                ConfigValue::Bool(false) => {}
+                // The value is attached so that it is not read as a flag
+                ConfigValue::Scalar(value) => args.push(format!("--{name}={value}")),
+                ConfigValue::Array(items) => {
+                    args.extend(items.iter().map(|item| format!("--{name}={item}")))
+                }
+            }
+        }
+        args.into_iter().map(OsString::from).collect()
+    }
+}
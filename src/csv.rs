@@ -0,0 +1,96 @@
+use std::io::Write;
+
+use crate::fingerprint::fingerprint;
+use crate::report::{sorted_snippets, Report, ReportWriter};
+use crate::summary::format_change;
+use crate::{LinesRange, SnippetDiff};
+
+/// The report as CSV, a row for each diff, e.g. to be loaded in
+/// a spreadsheet.
+pub(crate) struct CsvReport;
+
+/// Quotes a field containing a separator, a quote or a line break.
+pub(crate) fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn write_rows(
+    writer: &mut dyn Write,
+    report: &Report,
+    section: &str,
+    lines_range: Option<&LinesRange>,
+    diffs: &[SnippetDiff],
+) -> std::io::Result<()> {
+    let (source_filename, options) = (&report.snippets.source_filename, report.options);
+    let (start_line, end_line) = match lines_range {
+        Some(lines_range) => (
+            (lines_range.start_line + 1).to_string(),
+            lines_range.end_line.to_string(),
+        ),
+        None => (String::new(), String::new()),
+    };
+    for diff in diffs {
+        writeln!(
+            writer,
+            "{section},{start_line},{end_line},{},{},{},{},{}",
+            escape_field(&diff.path),
+            escape_field(&diff.old),
+            escape_field(&diff.new),
+            escape_field(&format_change(diff, options.precision).unwrap_or_default()),
+            fingerprint(source_filename, diff, &options.schema)
+        )?;
+    }
+    Ok(())
+}
+
+impl ReportWriter for CsvReport {
+    fn write_header(&mut self, writer: &mut dyn Write, _report: &Report) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "section,start_line,end_line,path,old,new,change,fingerprint"
+        )
+    }
+
+    fn write_global_metrics(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+    ) -> std::io::Result<()> {
+        write_rows(
+            writer,
+            report,
+            "global",
+            None,
+            &report.snippets.global_metrics,
+        )
+    }
+
+    fn write_snippet(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+        lines_range: &LinesRange,
+        diffs: &[SnippetDiff],
+    ) -> std::io::Result<()> {
+        write_rows(writer, report, "snippet", Some(lines_range), diffs)
+    }
+
+    fn finish(&mut self, writer: &mut dyn Write, report: &Report) -> std::io::Result<()> {
+        let (snippets, options) = (report.snippets, report.options);
+        write_rows(
+            writer,
+            report,
+            "space_change",
+            None,
+            &snippets.space_changes,
+        )?;
+        for (lines_range, diffs) in sorted_snippets(&snippets.positions, options) {
+            write_rows(writer, report, "position", Some(lines_range), diffs)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,705 @@
+use std::collections::HashMap;
+use std::thread;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::align::align_spaces;
+use crate::error::ExtractionError;
+use crate::filter::{is_ignored_by_default, is_position, FilterRule, FilteredDiff};
+use crate::ignore::{prune_paths, prune_spaces};
+use crate::language::metric_language;
+use crate::line_shift::{apply_line_shift, detect_line_shift};
+use crate::merge::{merge_old_ranges, merge_ranges};
+use crate::metrics_file::{self, MetricsFile};
+use crate::narrow::narrow_space;
+use crate::space::Space;
+use crate::stale::space_hash;
+use crate::summary::metric_name;
+use crate::{CodeSnippets, LinesRange, Options, SnippetDiff};
+
+pub(crate) use json_minimal_tests::{json_diffs, ABSENT};
+
+pub(crate) fn has_space_kind(space: Space, options: &Options) -> bool {
+    options.space_kinds.is_empty()
+        || space
+            .kind()
+            .is_some_and(|kind| options.space_kinds.iter().any(|k| k == kind))
+}
+
+/// Compares an old and a new metric file, returning their diffs grouped
+/// by the lines of their spaces, or `None` when the files are equal.
+pub(crate) fn get_code_snippets(
+    mut json1: Value,
+    mut json2: Value,
+    options: &Options,
+) -> Result<Option<CodeSnippets>, ExtractionError> {
+    prune_spaces(&mut json1, &options.ignored_spaces, &options.schema);
+    let ignored_spaces = prune_spaces(&mut json2, &options.ignored_spaces, &options.schema);
+    prune_paths(&mut json1, &options.ignored_paths);
+    prune_paths(&mut json2, &options.ignored_paths);
+    // The spaces parsed by only one of the versions of a grammar are
+    // reported apart, so that the others are compared with each other
+    let line_shift = detect_line_shift(&json1, &json2, &options.schema);
+    let space_changes = align_spaces(&mut json1, &mut json2, line_shift.unwrap_or(0), options);
+    // The lines moved by the shift are not diffs, only the other moves
+    let line_shift = line_shift.map(|shift| {
+        (
+            shift,
+            apply_line_shift(&mut json1, &json2, shift, &options.schema),
+        )
+    });
+
+    // Two JSON values MUST be exactly equal
+    if json1 != json2 || !space_changes.is_empty() {
+        let diffs = json_diffs(&json1, &json2, options.report_missing);
+        let mut snippets =
+            snippets_from_diffs(Some(&json1), &json2, diffs, ignored_spaces, options)?;
+        snippets.space_changes = space_changes;
+        snippets.line_shift = line_shift;
+        Ok(Some(snippets))
+    } else {
+        Ok(None)
+    }
+}
+
+// Diffs from which the spaces are looked up on several threads
+const PARALLEL_DIFFS: usize = 4096;
+
+/// Where a diff goes in the report.
+enum Place {
+    // Inside a space pruned with --ignore-space
+    Pruned,
+    // Of a space kind not selected with --space-kind
+    SpaceKind,
+    Global,
+    // The start or end line of a space, with --report-positions
+    Position(LinesRange),
+    Snippet {
+        lines_range: LinesRange,
+        old_range: Option<LinesRange>,
+        source_hash: Option<(LinesRange, String)>,
+        // The indices of the new space in the tree of the spaces, for the
+        // expected metrics of the snippet
+        space_path: Vec<usize>,
+    },
+}
+
+/// Looks up the spaces of the diffs in the metric files.
+struct Locator<'a> {
+    json1: Option<&'a Value>,
+    json2: &'a Value,
+    ignored_spaces: &'a [String],
+    // Matches the indices of the spaces in a diff path
+    re: &'a Regex,
+    options: &'a Options,
+}
+
+impl Locator<'_> {
+    fn place(&self, diff: &SnippetDiff) -> Result<Place, ExtractionError> {
+        let options = self.options;
+        let mut space_path: Vec<usize> = self
+            .re
+            .captures_iter(&diff.path)
+            .filter_map(|captures| captures[1].parse().ok())
+            .collect();
+
+        // Skip the diffs inside the pruned spaces, which an imported
+        // diff log can still contain
+        let pointer: String = space_path
+            .iter()
+            .map(|index| format!("/{}/{index}", options.schema.spaces))
+            .collect();
+        if self
+            .ignored_spaces
+            .iter()
+            .any(|ignored| pointer == *ignored || pointer.starts_with(&format!("{ignored}/")))
+        {
+            return Ok(Place::Pruned);
+        }
+
+        let missing_line = |field| ExtractionError::MissingLine {
+            diff_path: diff.path.clone(),
+            field,
+        };
+        let space_lines_range = |space: Space| -> Result<LinesRange, ExtractionError> {
+            Ok(LinesRange {
+                // Subtracting one since the lines of a file start from 0
+                start_line: space
+                    .start_line()
+                    .ok_or_else(|| missing_line(options.schema.start_line.clone()))?
+                    - 1,
+                end_line: space
+                    .end_line()
+                    .ok_or_else(|| missing_line(options.schema.end_line.clone()))?,
+            })
+        };
+
+        // If empty, it is a global metric
+        if space_path.is_empty() {
+            let space = Space::new(self.json2, &options.schema);
+            return Ok(if !has_space_kind(space, options) {
+                Place::SpaceKind
+            } else if options.report_positions && is_position(&diff.path, &options.schema) {
+                Place::Position(space_lines_range(space)?)
+            } else {
+                Place::Global
+            });
+        }
+
+        let mut space = Space::new(self.json2, &options.schema);
+        // The same space in the old JSON, if it still exists
+        let mut space1 = self.json1.map(|json1| Space::new(json1, &options.schema));
+        for &index in &space_path {
+            space = space
+                .child(index)
+                .ok_or_else(|| ExtractionError::MissingSpace {
+                    diff_path: diff.path.clone(),
+                    key: index.to_string(),
+                })?;
+            space1 = space1.and_then(|space1| space1.child(index));
+        }
+        if !has_space_kind(space, options) {
+            return Ok(Place::SpaceKind);
+        }
+        // The lines of a moved space are the ones it spans now
+        if options.report_positions && is_position(&diff.path, &options.schema) {
+            return Ok(Place::Position(space_lines_range(space)?));
+        }
+        if let Some(old_space) = space1 {
+            // Metric path relative to the space, e.g. .metrics.nargs.sum
+            let metric_path = self
+                .re
+                .find_iter(&diff.path)
+                .last()
+                .map_or("", |mat| &diff.path[mat.end()..]);
+            let (old_space, new_space, narrowed_path) = if options.minimize {
+                narrow_space(old_space, space, metric_path, |_| true)
+            } else if let Some(max_lines) = options.max_snippet_lines {
+                narrow_space(old_space, space, metric_path, |space| {
+                    space.lines() > max_lines
+                })
+            } else {
+                (old_space, space, Vec::new())
+            };
+            space_path.extend(narrowed_path);
+            space1 = Some(old_space);
+            space = new_space;
+        }
+        Ok(Place::Snippet {
+            lines_range: space_lines_range(space)?,
+            old_range: space1.and_then(Space::lines_range),
+            source_hash: space_hash(space),
+            // The exported tests have their expected metrics too
+            space_path,
+        })
+    }
+}
+
+/// Groups the diffs between two metric files by the lines of their spaces.
+/// Without the old metric file, the snippets cannot be narrowed.
+pub(crate) fn snippets_from_diffs(
+    json1: Option<&Value>,
+    json2: &Value,
+    diffs: Vec<SnippetDiff>,
+    ignored_spaces: Vec<String>,
+    options: &Options,
+) -> Result<CodeSnippets, ExtractionError> {
+    let (filtered, spaces_diff): (Vec<SnippetDiff>, Vec<SnippetDiff>) =
+        diffs.into_iter().partition(|diff| {
+            is_ignored_by_default(&diff.path, &options.schema, options.default_ignores)
+                && !(options.report_positions && is_position(&diff.path, &options.schema))
+        });
+    let mut filtered: Vec<FilteredDiff> = filtered
+        .into_iter()
+        .map(|diff| FilteredDiff {
+            diff,
+            rule: FilterRule::Default,
+        })
+        .collect();
+    let spaces_diff: Vec<SnippetDiff> = match &options.compare_rules {
+        Some(rules) => {
+            let (spaces_diff, within): (Vec<SnippetDiff>, Vec<SnippetDiff>) =
+                spaces_diff.into_iter().partition(|diff| {
+                    rules
+                        .comparison(metric_name(&diff.path, &options.schema))
+                        .is_reported(diff)
+                });
+            filtered.extend(within.into_iter().map(|diff| FilteredDiff {
+                diff,
+                rule: FilterRule::Compare,
+            }));
+            spaces_diff
+        }
+        None => spaces_diff,
+    };
+    let mut source_hashes: HashMap<LinesRange, String> =
+        space_hash(Space::new(json2, &options.schema))
+            .into_iter()
+            .collect();
+    let mut global_metrics: Vec<SnippetDiff> = Vec::new();
+    let mut snippets_data: HashMap<LinesRange, Vec<SnippetDiff>> = HashMap::new();
+    let mut positions: HashMap<LinesRange, Vec<SnippetDiff>> = HashMap::new();
+    let mut old_ranges: HashMap<LinesRange, LinesRange> = HashMap::new();
+    let mut fixture_spaces: HashMap<LinesRange, metrics_file::Space> = HashMap::new();
+    // The exported tests have the expected metrics of their new spaces,
+    // kept apart from the JSON of the new metric file
+    let metrics_file = (options.emit_fixtures.is_some() || options.interactive)
+        .then(|| MetricsFile::from_value(json2, &options.schema))
+        .transpose()
+        .map_err(ExtractionError::Layout)?;
+
+    // Detect spaces path
+    let re = Regex::new(&format!(
+        r"\.{}\[(\d+)\]",
+        regex::escape(&options.schema.spaces)
+    ))
+    .unwrap();
+    let locator = Locator {
+        json1,
+        json2,
+        ignored_spaces: &ignored_spaces,
+        re: &re,
+        options,
+    };
+    // The spaces of the diffs of a large file are looked up on several
+    // threads, keeping the order of the diffs
+    let places: Vec<Result<Place, ExtractionError>> = if spaces_diff.len() < PARALLEL_DIFFS {
+        spaces_diff.iter().map(|diff| locator.place(diff)).collect()
+    } else {
+        let chunk_size = spaces_diff.len().div_ceil(num_cpus::get().max(1));
+        thread::scope(|scope| {
+            let handles: Vec<_> = spaces_diff
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let locator = &locator;
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|diff| locator.place(diff))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    };
+
+    for (diff, place) in spaces_diff.into_iter().zip(places) {
+        match place? {
+            Place::Pruned => {}
+            Place::SpaceKind => filtered.push(FilteredDiff {
+                diff,
+                rule: FilterRule::SpaceKind,
+            }),
+            Place::Global => global_metrics.push(diff),
+            Place::Position(lines_range) => positions.entry(lines_range).or_default().push(diff),
+            Place::Snippet {
+                lines_range,
+                old_range,
+                source_hash,
+                space_path,
+            } => {
+                source_hashes.extend(source_hash);
+                if let Some(space) = metrics_file
+                    .as_ref()
+                    .and_then(|metrics_file| metrics_file.space(&space_path))
+                {
+                    fixture_spaces.insert(lines_range.clone(), space.clone());
+                }
+                if let Some(old_range) = old_range {
+                    old_ranges.insert(lines_range.clone(), old_range);
+                }
+                snippets_data.entry(lines_range).or_default().push(diff);
+            }
+        }
+    }
+
+    if let Some(distance) = options.merge_distance {
+        snippets_data = merge_ranges(snippets_data, distance);
+        old_ranges = merge_old_ranges(&old_ranges, snippets_data.keys());
+    }
+
+    let source_filename = Space::new(json2, &options.schema)
+        .name()
+        .ok_or(ExtractionError::MissingName)?
+        .to_owned();
+
+    Ok(CodeSnippets {
+        language: metric_language(json2, &source_filename),
+        source_filename,
+        partial: false,
+        global_metrics,
+        snippets_data,
+        positions,
+        space_changes: Vec::new(),
+        line_shift: None,
+        omitted_snippets: 0,
+        filtered,
+        ignored_spaces,
+        source_hashes,
+        stale: Vec::new(),
+        checked: HashMap::new(),
+        old_source_filename: json1
+            .and_then(|json1| Space::new(json1, &options.schema).name())
+            .map(str::to_owned),
+        old_ranges,
+        code_changed: HashMap::new(),
+        fixture_spaces,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::align::SpaceMatching;
+    use crate::ignore::PathIgnore;
+
+    fn space(name: &str, lines: (u64, u64), sum: u64, spaces: Vec<Value>) -> Value {
+        json!({
+            "name": name,
+            "start_line": lines.0,
+            "end_line": lines.1,
+            "kind": if name.ends_with(".rs") { "unit" } else { "function" },
+            "metrics": { "cyclomatic": { "sum": sum } },
+            "spaces": spaces,
+        })
+    }
+
+    fn diff(path: &str, old: &str, new: &str) -> SnippetDiff {
+        SnippetDiff {
+            path: path.to_owned(),
+            old: old.to_owned(),
+            new: new.to_owned(),
+        }
+    }
+
+    fn lines(start_line: usize, end_line: usize) -> LinesRange {
+        LinesRange {
+            start_line,
+            end_line,
+        }
+    }
+
+    #[test]
+    fn json_diffs_paths() {
+        let old = json!({ "a": 1, "b": [1, 2, { "c": "x" }], "d": 1 });
+        let new = json!({ "a": 2, "b": [1, 3, { "c": "y" }], "e": 1 });
+        let diffs: Vec<(String, String, String)> = json_diffs(&old, &new, false)
+            .into_iter()
+            .map(|diff| (diff.path, diff.old, diff.new))
+            .collect();
+        assert_eq!(
+            diffs,
+            [
+                (".a".to_owned(), "1".to_owned(), "2".to_owned()),
+                (".b[1]".to_owned(), "2".to_owned(), "3".to_owned()),
+                (".b[2].c".to_owned(), "\"x\"".to_owned(), "\"y\"".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn json_diffs_skip_multiline_values() {
+        let old = json!({ "a": { "b": 1 } });
+        let new = json!({ "a": 1 });
+        assert!(json_diffs(&old, &new, false).is_empty());
+    }
+
+    #[test]
+    fn json_diffs_missing_keys() {
+        let old = json!({ "a": { "b": 1, "c": 2 }, "s": [1] });
+        let new = json!({ "a": { "b": 1 }, "d": { "e": "x" }, "s": [] });
+        let diffs: Vec<(String, String, String)> = json_diffs(&old, &new, true)
+            .into_iter()
+            .map(|diff| (diff.path, diff.old, diff.new))
+            .collect();
+        assert_eq!(
+            diffs,
+            [
+                (".a.c".to_owned(), "2".to_owned(), ABSENT.to_owned()),
+                (".d.e".to_owned(), ABSENT.to_owned(), "\"x\"".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn equal_files() {
+        let json = space("a.rs", (1, 10), 1, vec![]);
+        let snippets = get_code_snippets(json.clone(), json, &Options::default()).unwrap();
+        assert!(snippets.is_none());
+    }
+
+    #[test]
+    fn global_metric() {
+        let old = space("a.rs", (1, 10), 1, vec![]);
+        let new = space("a.rs", (1, 10), 2, vec![]);
+        let snippets = get_code_snippets(old, new, &Options::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(snippets.source_filename, "a.rs");
+        assert_eq!(snippets.global_metrics.len(), 1);
+        assert_eq!(snippets.global_metrics[0].path, ".metrics.cyclomatic.sum");
+        assert!(snippets.snippets_data.is_empty());
+    }
+
+    #[test]
+    fn space_metric() {
+        let old = space("a.rs", (1, 10), 1, vec![space("f", (2, 4), 1, vec![])]);
+        let new = space("a.rs", (1, 10), 1, vec![space("f", (2, 4), 3, vec![])]);
+        let snippets = get_code_snippets(old, new, &Options::default())
+            .unwrap()
+            .unwrap();
+        assert!(snippets.global_metrics.is_empty());
+        assert_eq!(
+            snippets.snippets_data[&lines(1, 4)][0].path,
+            ".spaces[0].metrics.cyclomatic.sum"
+        );
+        assert_eq!(snippets.old_ranges[&lines(1, 4)], lines(1, 4));
+    }
+
+    #[test]
+    fn ignore_path() {
+        let old = space("a.rs", (1, 10), 1, vec![space("f", (2, 4), 1, vec![])]);
+        let new = space("a.rs", (1, 10), 2, vec![space("f", (2, 4), 3, vec![])]);
+        let options = Options {
+            ignored_paths: vec![PathIgnore::parse("/spaces/*/metrics/cyclomatic").unwrap()],
+            ..Options::default()
+        };
+        let snippets = get_code_snippets(old, new, &options).unwrap().unwrap();
+        assert_eq!(snippets.global_metrics.len(), 1);
+        assert!(snippets.snippets_data.is_empty());
+        let ignore = PathIgnore::parse("/spaces/*/metrics").unwrap();
+        assert!(ignore.matches_diff(".spaces[3].metrics.nom.total"));
+        assert!(!ignore.matches_diff(".metrics.nom.total"));
+    }
+
+    #[test]
+    fn positions_ignored_by_default() {
+        let old = space("a.rs", (1, 10), 1, vec![space("f", (2, 4), 1, vec![])]);
+        let new = space("a.rs", (1, 10), 1, vec![space("f", (2, 5), 1, vec![])]);
+        let snippets = get_code_snippets(old, new, &Options::default())
+            .unwrap()
+            .unwrap();
+        assert!(snippets.snippets_data.is_empty());
+        assert_eq!(snippets.filtered.len(), 1);
+        assert!(matches!(snippets.filtered[0].rule, FilterRule::Default));
+    }
+
+    #[test]
+    fn report_positions() {
+        let old = space("a.rs", (1, 10), 1, vec![space("f", (2, 4), 1, vec![])]);
+        let new = space("a.rs", (1, 10), 2, vec![space("f", (2, 5), 1, vec![])]);
+        let options = Options {
+            report_positions: true,
+            ..Options::default()
+        };
+        let snippets = get_code_snippets(old, new, &options).unwrap().unwrap();
+        assert_eq!(snippets.global_metrics.len(), 1);
+        assert!(snippets.snippets_data.is_empty());
+        assert!(snippets.filtered.is_empty());
+        let range = LinesRange {
+            start_line: 1,
+            end_line: 5,
+        };
+        assert_eq!(snippets.positions[&range][0].path, ".spaces[0].end_line");
+    }
+
+    #[test]
+    fn added_space() {
+        let old = space(
+            "a.rs",
+            (1, 10),
+            2,
+            vec![space("f", (2, 4), 1, vec![]), space("g", (6, 8), 1, vec![])],
+        );
+        let new = space(
+            "a.rs",
+            (1, 12),
+            3,
+            vec![
+                space("f", (2, 4), 1, vec![]),
+                space("h", (6, 7), 1, vec![]),
+                space("g", (9, 11), 2, vec![]),
+            ],
+        );
+        let snippets = get_code_snippets(old, new, &Options::default())
+            .unwrap()
+            .unwrap();
+        let changes: Vec<(&str, &str, &str)> = snippets
+            .space_changes
+            .iter()
+            .map(|diff| (diff.path.as_str(), diff.old.as_str(), diff.new.as_str()))
+            .collect();
+        assert_eq!(changes, [(".spaces[1]", ABSENT, "function h (6, 7)")]);
+        // g is compared with g, not with h
+        assert_eq!(snippets.snippets_data.len(), 1);
+        assert_eq!(snippets.snippets_data[&lines(8, 11)].len(), 1);
+    }
+
+    #[test]
+    fn match_spaces_by_key() {
+        let old = space(
+            "a.rs",
+            (1, 20),
+            1,
+            vec![
+                space("f", (2, 4), 1, vec![]),
+                space("g", (9, 11), 1, vec![]),
+            ],
+        );
+        let new = space(
+            "a.rs",
+            (1, 20),
+            1,
+            vec![
+                space("g", (9, 11), 2, vec![]),
+                space("f", (2, 4), 1, vec![]),
+            ],
+        );
+        let options = Options {
+            space_matching: SpaceMatching::Key,
+            ..Options::default()
+        };
+        let snippets = get_code_snippets(old, new, &options).unwrap().unwrap();
+        assert!(snippets.space_changes.is_empty());
+        assert_eq!(snippets.snippets_data.len(), 1);
+        assert_eq!(snippets.snippets_data[&lines(8, 11)].len(), 1);
+    }
+
+    #[test]
+    fn line_shift() {
+        let old = space(
+            "a.rs",
+            (1, 20),
+            1,
+            vec![
+                space("f", (2, 4), 1, vec![]),
+                space("g", (9, 11), 1, vec![]),
+            ],
+        );
+        let new = space(
+            "a.rs",
+            (1, 20),
+            1,
+            vec![
+                space("f", (3, 5), 1, vec![]),
+                space("g", (10, 12), 2, vec![]),
+            ],
+        );
+        let options = Options {
+            report_positions: true,
+            ..Options::default()
+        };
+        let snippets = get_code_snippets(old, new, &options).unwrap().unwrap();
+        assert_eq!(snippets.line_shift, Some((1, 2)));
+        assert!(snippets.positions.is_empty());
+        assert_eq!(snippets.snippets_data[&lines(9, 12)].len(), 1);
+    }
+
+    #[test]
+    fn many_diffs() {
+        let spaces = |sum| {
+            (1..=PARALLEL_DIFFS as u64 + 10)
+                .map(|i| space(&format!("f{i}"), (i * 3, i * 3 + 1), sum, vec![]))
+                .collect()
+        };
+        let old = space("a.rs", (1, 100_000), 1, spaces(1));
+        let new = space("a.rs", (1, 100_000), 1, spaces(2));
+        let snippets = get_code_snippets(old, new, &Options::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(snippets.snippets_data.len(), PARALLEL_DIFFS + 10);
+        assert_eq!(snippets.snippets_data[&lines(2, 4)].len(), 1);
+    }
+
+    #[test]
+    fn minimize() {
+        let old = space(
+            "a.rs",
+            (1, 20),
+            1,
+            vec![space("f", (2, 12), 2, vec![space("g", (4, 6), 1, vec![])])],
+        );
+        let new = space(
+            "a.rs",
+            (1, 20),
+            1,
+            vec![space("f", (2, 12), 3, vec![space("g", (4, 6), 2, vec![])])],
+        );
+        let diffs = |options| {
+            let snippets = get_code_snippets(old.clone(), new.clone(), &options)
+                .unwrap()
+                .unwrap();
+            let mut ranges: Vec<LinesRange> = snippets.snippets_data.into_keys().collect();
+            ranges.sort_by_key(|range| range.start_line);
+            ranges
+        };
+        assert_eq!(diffs(Options::default()), [lines(1, 12), lines(3, 6)]);
+        let options = Options {
+            minimize: true,
+            ..Options::default()
+        };
+        assert_eq!(diffs(options), [lines(3, 6)]);
+    }
+
+    #[test]
+    fn space_kind() {
+        let old = space("a.rs", (1, 10), 1, vec![space("f", (2, 4), 1, vec![])]);
+        let new = space("a.rs", (1, 10), 2, vec![space("f", (2, 4), 3, vec![])]);
+        let options = Options {
+            space_kinds: vec!["function".to_owned()],
+            ..Options::default()
+        };
+        let snippets = get_code_snippets(old, new, &options).unwrap().unwrap();
+        assert_eq!(snippets.snippets_data.len(), 1);
+        assert!(snippets.global_metrics.is_empty());
+        assert!(matches!(snippets.filtered[0].rule, FilterRule::SpaceKind));
+    }
+
+    #[test]
+    fn merge_distance() {
+        let old = space(
+            "a.rs",
+            (1, 20),
+            1,
+            vec![space("f", (2, 4), 1, vec![]), space("g", (6, 8), 1, vec![])],
+        );
+        let new = space(
+            "a.rs",
+            (1, 20),
+            1,
+            vec![space("f", (2, 4), 2, vec![]), space("g", (6, 8), 2, vec![])],
+        );
+        let options = Options {
+            merge_distance: Some(2),
+            ..Options::default()
+        };
+        let snippets = get_code_snippets(old, new, &options).unwrap().unwrap();
+        assert_eq!(snippets.snippets_data[&lines(1, 8)].len(), 2);
+        assert_eq!(snippets.old_ranges[&lines(1, 8)], lines(1, 8));
+    }
+
+    #[test]
+    fn diffs_from_log() {
+        let json = space("a.rs", (1, 10), 1, vec![space("f", (2, 4), 1, vec![])]);
+        let diffs = vec![diff(".spaces[0].metrics.cyclomatic.sum", "1", "2")];
+        let snippets =
+            snippets_from_diffs(None, &json, diffs, Vec::new(), &Options::default()).unwrap();
+        assert_eq!(snippets.snippets_data[&lines(1, 4)].len(), 1);
+        assert!(snippets.old_ranges.is_empty());
+    }
+
+    #[test]
+    fn missing_space() {
+        let json = space("a.rs", (1, 10), 1, vec![]);
+        let diffs = vec![diff(".spaces[3].metrics.cyclomatic.sum", "1", "2")];
+        let result = snippets_from_diffs(None, &json, diffs, Vec::new(), &Options::default());
+        assert!(matches!(result, Err(ExtractionError::MissingSpace { .. })));
+    }
+}
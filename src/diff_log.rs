@@ -0,0 +1,93 @@
+use crate::diff::ABSENT;
+use crate::SnippetDiff;
+
+const HEADER: &str = "json atoms at path \"";
+const MISSING_HEADER: &str = "json atom at path \"";
+// The log does not print the value of an atom missing from the other side
+const UNKNOWN: &str = "<unknown>";
+const VALUE_INDENT: &str = "        ";
+
+/// Returns the lines of a value, indented under a `lhs:` or `rhs:` label.
+fn value_lines<'a, I: Iterator<Item = &'a str>>(
+    lines: &mut std::iter::Peekable<I>,
+    label: &str,
+) -> Option<Vec<&'a str>> {
+    if lines.next()?.trim() != label {
+        return None;
+    }
+    let mut value = Vec::new();
+    while let Some(line) = lines.next_if(|line| line.starts_with(VALUE_INDENT)) {
+        value.push(line.trim());
+    }
+    Some(value)
+}
+
+/// Parses the text printed by assert_json_diff in strict mode into diffs
+/// between an old (`lhs`) and a new (`rhs`) value.
+///
+/// Each line may start with a prefix, such as the timestamp of a CI log,
+/// as long as the prefix has the same length on all the lines of a diff.
+/// Values spanning more than one line, like an object replaced by a scalar,
+/// are skipped, as well as the missing atoms unless `report_missing` is set.
+/// The missing array items are always skipped, since they are added or
+/// removed spaces rather than metrics.
+pub(crate) fn parse_diff_log(text: &str, report_missing: bool) -> Vec<SnippetDiff> {
+    let mut diffs = Vec::new();
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        if let Some(start) = line.find(MISSING_HEADER).filter(|_| report_missing) {
+            let missing = line[start + MISSING_HEADER.len()..].split_once("\" is missing from ");
+            let (path, old, new) = match missing {
+                Some((path, "lhs")) => (path, ABSENT, UNKNOWN),
+                Some((path, "rhs")) => (path, UNKNOWN, ABSENT),
+                _ => continue,
+            };
+            if !path.ends_with(']') {
+                diffs.push(SnippetDiff {
+                    path: path.to_owned(),
+                    old: old.to_owned(),
+                    new: new.to_owned(),
+                });
+            }
+            continue;
+        }
+        let start = match line.find(HEADER) {
+            Some(start) => start,
+            None => continue,
+        };
+        let path = match line[start + HEADER.len()..].split('"').next() {
+            Some(path) => path,
+            None => continue,
+        };
+        if !line.ends_with("are not equal:") {
+            continue;
+        }
+
+        // Strip the prefix from the lines of the diff, stopping at the
+        // first one which does not belong to it
+        let mut diff_lines = std::iter::from_fn(|| {
+            lines
+                .next_if(|line| {
+                    line.get(start..)
+                        .is_some_and(|line| line.starts_with("    "))
+                })
+                .map(|line| &line[start..])
+        })
+        .peekable();
+        let old = value_lines(&mut diff_lines, "lhs:");
+        let new = old
+            .as_ref()
+            .and_then(|_| value_lines(&mut diff_lines, "rhs:"));
+        // Consume what is left of a malformed diff
+        diff_lines.for_each(drop);
+
+        if let (Some([old]), Some([new])) = (old.as_deref(), new.as_deref()) {
+            diffs.push(SnippetDiff {
+                path: path.to_owned(),
+                old: (*old).to_owned(),
+                new: (*new).to_owned(),
+            });
+        }
+    }
+    diffs
+}
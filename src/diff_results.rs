@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+/// A diff of a previous run, read from its JSON reports.
+#[derive(Clone, PartialEq)]
+pub(crate) struct ResultDiff {
+    pub(crate) source: String,
+    pub(crate) path: String,
+    pub(crate) old: String,
+    pub(crate) new: String,
+}
+
+/// The diffs reported by a run with `--format json`, by fingerprint.
+#[derive(Default)]
+pub(crate) struct ResultSet {
+    diffs: BTreeMap<String, ResultDiff>,
+}
+
+impl ResultSet {
+    /// Reads a JSON report, or the JSON reports of an output directory.
+    /// The filtered diffs are not part of the results.
+    pub(crate) fn read(path: &Path) -> std::io::Result<Self> {
+        let mut results = Self::default();
+        if path.is_dir() {
+            let mut dirs = vec![path.to_path_buf()];
+            while let Some(dir) = dirs.pop() {
+                for entry in std::fs::read_dir(dir)? {
+                    let entry_path = entry?.path();
+                    if entry_path.is_dir() {
+                        dirs.push(entry_path);
+                    } else if entry_path.extension().is_some_and(|ext| ext == "json")
+                        && entry_path
+                            .file_name()
+                            .is_some_and(|name| name != "manifest.json")
+                    {
+                        results.add_report(&entry_path)?;
+                    }
+                }
+            }
+        } else {
+            results.add_report(path)?;
+        }
+        Ok(results)
+    }
+
+    fn add_report(&mut self, path: &Path) -> std::io::Result<()> {
+        let json: Value = serde_json::from_slice(&std::fs::read(path)?)?;
+        if !json.is_object() {
+            return Err(Error::new(ErrorKind::InvalidData, "not a JSON report"));
+        }
+        self.add_diffs(&json, "");
+        Ok(())
+    }
+
+    // Walks a report for the diffs with a fingerprint, whose source is the
+    // one of the report, or their own in a report grouped by metric
+    fn add_diffs(&mut self, value: &Value, source: &str) {
+        match value {
+            Value::Object(map) => {
+                let source = map.get("source").and_then(Value::as_str).unwrap_or(source);
+                let field = |name| map.get(name).and_then(Value::as_str);
+                if let (Some(fingerprint), Some(path), Some(old), Some(new)) = (
+                    field("fingerprint"),
+                    field("path"),
+                    field("old"),
+                    field("new"),
+                ) {
+                    self.diffs.insert(
+                        fingerprint.to_owned(),
+                        ResultDiff {
+                            source: source.to_owned(),
+                            path: path.to_owned(),
+                            old: old.to_owned(),
+                            new: new.to_owned(),
+                        },
+                    );
+                    return;
+                }
+                for (name, value) in map {
+                    if name != "filtered" {
+                        self.add_diffs(value, source);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.add_diffs(item, source);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The regressions of a run compared with the ones of a previous run.
+pub(crate) struct ResultsDiff<'a> {
+    // Only in the new run
+    pub(crate) new: Vec<(&'a str, &'a ResultDiff)>,
+    // Only in the old run
+    pub(crate) fixed: Vec<(&'a str, &'a ResultDiff)>,
+    // In both runs, with their old and new diff, whose values can differ
+    pub(crate) unchanged: Vec<(&'a str, &'a ResultDiff, &'a ResultDiff)>,
+}
+
+/// Matches the diffs of two runs by their fingerprint.
+pub(crate) fn diff_results<'a>(old: &'a ResultSet, new: &'a ResultSet) -> ResultsDiff<'a> {
+    let mut results_diff = ResultsDiff {
+        new: Vec::new(),
+        fixed: Vec::new(),
+        unchanged: Vec::new(),
+    };
+    for (fingerprint, diff) in &new.diffs {
+        match old.diffs.get(fingerprint) {
+            Some(old_diff) => results_diff.unchanged.push((fingerprint, old_diff, diff)),
+            None => results_diff.new.push((fingerprint, diff)),
+        }
+    }
+    for (fingerprint, diff) in &old.diffs {
+        if !new.diffs.contains_key(fingerprint) {
+            results_diff.fixed.push((fingerprint, diff));
+        }
+    }
+    // In the order of their source files, then of their paths
+    results_diff
+        .new
+        .sort_by(|(_, a), (_, b)| (&a.source, &a.path).cmp(&(&b.source, &b.path)));
+    results_diff
+        .fixed
+        .sort_by(|(_, a), (_, b)| (&a.source, &a.path).cmp(&(&b.source, &b.path)));
+    results_diff
+        .unchanged
+        .sort_by(|(_, _, a), (_, _, b)| (&a.source, &a.path).cmp(&(&b.source, &b.path)));
+    results_diff
+}
+
+fn diff_json(fingerprint: &str, diff: &ResultDiff) -> Value {
+    json!({
+        "fingerprint": fingerprint,
+        "source": diff.source,
+        "path": diff.path,
+        "old": diff.old,
+        "new": diff.new,
+    })
+}
+
+impl ResultsDiff<'_> {
+    pub(crate) fn to_json(&self) -> Value {
+        let unchanged: Vec<Value> = self
+            .unchanged
+            .iter()
+            .map(|(fingerprint, old, new)| {
+                let mut diff = diff_json(fingerprint, new);
+                diff["previous"] = json!({ "old": old.old, "new": old.new });
+                diff
+            })
+            .collect();
+        let list = |diffs: &[(&str, &ResultDiff)]| -> Vec<Value> {
+            diffs
+                .iter()
+                .map(|(fingerprint, diff)| diff_json(fingerprint, diff))
+                .collect()
+        };
+        json!({
+            "new": list(&self.new),
+            "fixed": list(&self.fixed),
+            "unchanged": unchanged,
+        })
+    }
+
+    /// Writes the diffs of each kind, a line for each one, e.g.
+    /// `a.rs .spaces[0].metrics.cyclomatic.sum: 2.0 -> 3.0 [be6d7de1b3694ada]`.
+    /// The unchanged diffs whose values differ are followed by their
+    /// previous values.
+    pub(crate) fn write_text<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let line = |fingerprint: &str, diff: &ResultDiff| {
+            format!(
+                "  {} {}: {} -> {} [{fingerprint}]",
+                diff.source, diff.path, diff.old, diff.new
+            )
+        };
+        writeln!(writer, "New: {}", self.new.len())?;
+        for (fingerprint, diff) in &self.new {
+            writeln!(writer, "{}", line(fingerprint, diff))?;
+        }
+        writeln!(writer, "Fixed: {}", self.fixed.len())?;
+        for (fingerprint, diff) in &self.fixed {
+            writeln!(writer, "{}", line(fingerprint, diff))?;
+        }
+        writeln!(writer, "Unchanged: {}", self.unchanged.len())?;
+        for (fingerprint, old, new) in &self.unchanged {
+            write!(writer, "{}", line(fingerprint, new))?;
+            if old != new {
+                write!(writer, ", previously {} -> {}", old.old, old.new)?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
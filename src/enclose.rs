@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use crate::{CodeSnippets, LinesRange};
+
+// The indentation of a line, in characters
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_python_header(line: &str) -> bool {
+    let line = line.trim_start();
+    ["def ", "async def ", "class "]
+        .iter()
+        .any(|keyword| line.starts_with(keyword))
+}
+
+// The decorators right above the line `start`
+fn python_decorators_start(lines: &[&str], start: usize) -> usize {
+    let mut start = start;
+    while start > 0 && lines[start - 1].trim_start().starts_with('@') {
+        start -= 1;
+    }
+    start
+}
+
+/// Starts a Python snippet at the header of the function or class enclosing
+/// its first line, when it does not start with one, e.g. a method body,
+/// and at the decorators of its first header.
+fn python_start(lines: &[&str], start: usize) -> usize {
+    let Some(first) = lines[start..]
+        .iter()
+        .position(|line| !line.trim().is_empty())
+        .map(|first| start + first)
+    else {
+        return start;
+    };
+    if is_python_header(lines[first]) {
+        return python_decorators_start(lines, first);
+    }
+    let indentation_first = indentation(lines[first]);
+    lines[..first]
+        .iter()
+        .rposition(|line| !line.trim().is_empty() && indentation(line) < indentation_first)
+        .filter(|&header| is_python_header(lines[header]))
+        .map_or(start, |header| python_decorators_start(lines, header))
+}
+
+// The preprocessor directive of a line, e.g. `ifdef` for `#  ifdef X`
+fn directive(line: &str) -> Option<&str> {
+    let line = line.trim_start().strip_prefix('#')?.trim_start();
+    Some(
+        line.split(|c: char| !c.is_ascii_alphabetic())
+            .next()
+            .unwrap_or(""),
+    )
+}
+
+fn is_opening(directive: Option<&str>) -> bool {
+    matches!(directive, Some("if" | "ifdef" | "ifndef"))
+}
+
+/// Widens a C snippet to the preprocessor conditionals right above it,
+/// up to the `#endif` closing each of them after the snippet.
+fn c_range(lines: &[&str], lines_range: &LinesRange) -> LinesRange {
+    let mut start = lines_range.start_line;
+    while start > 0 && is_opening(directive(lines[start - 1])) {
+        start -= 1;
+    }
+    let mut open = lines_range.start_line - start;
+    if open == 0 {
+        return lines_range.clone();
+    }
+    // The conditionals opened in the snippet are closed in it
+    let mut depth = 0;
+    for (i, line) in lines.iter().enumerate().skip(lines_range.start_line) {
+        match directive(line) {
+            directive if is_opening(directive) => depth += 1,
+            Some("endif") if depth > 0 => depth -= 1,
+            Some("endif") if i >= lines_range.end_line => {
+                open -= 1;
+                if open == 0 {
+                    return LinesRange {
+                        start_line: start,
+                        end_line: i + 1,
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+    // The conditionals are not closed, a snippet with them would not parse
+    lines_range.clone()
+}
+
+/// Returns the lines a snippet needs around it to be parsed on its own,
+/// in the language of its source file.
+pub(crate) fn enclosing_range(
+    lines: &[&str],
+    lines_range: &LinesRange,
+    language: &str,
+) -> LinesRange {
+    // The lines of a source file which differs from the metrics can be fewer
+    if lines_range.end_line > lines.len() {
+        return lines_range.clone();
+    }
+    match language {
+        "Python" => LinesRange {
+            start_line: python_start(&lines[..lines_range.end_line], lines_range.start_line),
+            end_line: lines_range.end_line,
+        },
+        "C" | "C++" => c_range(lines, lines_range),
+        _ => lines_range.clone(),
+    }
+}
+
+/// Widens the snippets to the lines their language needs to parse them,
+/// merging the ones which then have the same lines.
+pub(crate) fn enclose_snippets(snippets: &mut CodeSnippets, source_file: &str) {
+    let lines: Vec<&str> = source_file.lines().collect();
+    let enclosing: HashMap<LinesRange, LinesRange> = snippets
+        .snippets_data
+        .keys()
+        .map(|lines_range| {
+            (
+                lines_range.clone(),
+                enclosing_range(&lines, lines_range, &snippets.language),
+            )
+        })
+        .collect();
+    if enclosing
+        .iter()
+        .all(|(original, widened)| original == widened)
+    {
+        return;
+    }
+    // The old ranges and the code changes can be kept for filtered snippets
+    let widened =
+        |lines_range: LinesRange| enclosing.get(&lines_range).cloned().unwrap_or(lines_range);
+    for (lines_range, diffs) in std::mem::take(&mut snippets.snippets_data) {
+        snippets
+            .snippets_data
+            .entry(widened(lines_range))
+            .or_default()
+            .extend(diffs);
+    }
+    for (lines_range, old_range) in std::mem::take(&mut snippets.old_ranges) {
+        snippets
+            .old_ranges
+            .entry(widened(lines_range))
+            .and_modify(|merged| {
+                merged.start_line = merged.start_line.min(old_range.start_line);
+                merged.end_line = merged.end_line.max(old_range.end_line);
+            })
+            .or_insert(old_range);
+    }
+    // A merged snippet is changed when one of its snippets is
+    for (lines_range, changed) in std::mem::take(&mut snippets.code_changed) {
+        *snippets
+            .code_changed
+            .entry(widened(lines_range))
+            .or_default() |= changed;
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::validate::SchemaError;
+
+#[derive(Debug)]
+pub(crate) enum ExtractionError {
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    // A metric file in another format than JSON which cannot be parsed
+    Decode {
+        path: PathBuf,
+        format: &'static str,
+        message: String,
+    },
+    // A zero-byte or whitespace-only file, usually a truncated artifact
+    Empty {
+        path: PathBuf,
+    },
+    Schema {
+        path: PathBuf,
+        errors: Vec<SchemaError>,
+    },
+    MissingSpace {
+        diff_path: String,
+        key: String,
+    },
+    MissingLine {
+        diff_path: String,
+        field: String,
+    },
+    MissingName,
+    // The new metric file cannot be read into typed spaces
+    Layout(serde_json::Error),
+    Output(std::io::Error),
+}
+
+impl fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractionError::Read { path, source } => {
+                write!(f, "cannot read {}: {}", path.display(), source)
+            }
+            ExtractionError::Parse { path, source } => {
+                write!(f, "cannot parse {}: {}", path.display(), source)
+            }
+            ExtractionError::Decode {
+                path,
+                format,
+                message,
+            } => {
+                write!(
+                    f,
+                    "cannot parse {} as {}: {}",
+                    path.display(),
+                    format,
+                    message
+                )
+            }
+            ExtractionError::Empty { path } => {
+                write!(f, "{} is empty, the input is corrupt", path.display())
+            }
+            ExtractionError::Schema { path, errors } => {
+                write!(f, "{} does not follow the expected schema", path.display())?;
+                for error in errors {
+                    write!(f, "\n    {error}")?;
+                }
+                Ok(())
+            }
+            ExtractionError::MissingSpace { diff_path, key } => {
+                write!(f, "cannot find space `{key}` while resolving `{diff_path}`")
+            }
+            ExtractionError::MissingLine { diff_path, field } => {
+                write!(
+                    f,
+                    "missing or invalid `{field}` for the space of `{diff_path}`"
+                )
+            }
+            ExtractionError::MissingName => {
+                write!(f, "the new JSON file has no source file `name`")
+            }
+            ExtractionError::Layout(source) => {
+                write!(
+                    f,
+                    "the new JSON file does not have the layout of the schema: {source}"
+                )
+            }
+            ExtractionError::Output(source) => write!(f, "cannot write report: {source}"),
+        }
+    }
+}
+
+impl std::error::Error for ExtractionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExtractionError::Read { source, .. } | ExtractionError::Output(source) => Some(source),
+            ExtractionError::Parse { source, .. } | ExtractionError::Layout(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ExtractionError {
+    fn from(err: std::io::Error) -> Self {
+        ExtractionError::Output(err)
+    }
+}
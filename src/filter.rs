@@ -0,0 +1,109 @@
+use std::fmt;
+
+use crate::schema::Schema;
+use crate::SnippetDiff;
+
+/// The rule which caused a diff to be left out of a report.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum FilterRule {
+    // Positions, names, kinds and derived metrics ignored by default
+    Default,
+    // A space kind not selected with --space-kind
+    SpaceKind,
+    // A snippet whose code changed, left out with --only-unchanged-code
+    CodeChanged,
+    // A diff accepted in the baseline
+    Accepted,
+    // A diff whose fingerprint is in --suppressions
+    Suppressed,
+    // A diff outside of the snippets kept by --top
+    Top,
+    // A snippet beyond the ones of its file kept by --max-snippets-per-file
+    PerFile,
+    // A snippet with too small a change for the --max-output-mb budget
+    Budget,
+    // A metric less severe than --min-severity
+    Severity,
+    // A change not reported by the rule of its metric in --compare-rules
+    Compare,
+}
+
+impl fmt::Display for FilterRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FilterRule::Default => "ignored by default",
+            FilterRule::SpaceKind => "space kind not selected",
+            FilterRule::CodeChanged => "code changed",
+            FilterRule::Accepted => "accepted in the baseline",
+            FilterRule::Suppressed => "suppressed by its fingerprint",
+            FilterRule::Top => "outside of the top snippets",
+            FilterRule::PerFile => "beyond the snippets kept for its file",
+            FilterRule::Budget => "beyond the output size budget",
+            FilterRule::Severity => "below the minimum severity",
+            FilterRule::Compare => "within the comparison rule",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct FilteredDiff {
+    pub(crate) diff: SnippetDiff,
+    pub(crate) rule: FilterRule,
+}
+
+// Halstead metrics derived from the operators and operands counts
+const HALSTEAD_DERIVED: &[&str] = &[
+    "halstead.length",
+    "halstead.volume",
+    "halstead.vocabulary",
+    "halstead.purity_ratio",
+    "halstead.level",
+    "halstead.estimated_program_length",
+    "halstead.time",
+    "halstead.bugs",
+    "halstead.difficulty",
+    "halstead.effort",
+];
+
+/// The diffs ignored by default which are still ignored.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct DefaultIgnores {
+    // None are ignored with --no-default-ignores
+    pub(crate) enabled: bool,
+    pub(crate) halstead: bool,
+    pub(crate) mi: bool,
+}
+
+impl Default for DefaultIgnores {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            halstead: true,
+            mi: true,
+        }
+    }
+}
+
+/// Checks whether a diff path is the start or end line of a space,
+/// reported apart with --report-positions.
+pub(crate) fn is_position(path: &str, schema: &Schema) -> bool {
+    [&schema.start_line, &schema.end_line]
+        .iter()
+        .any(|field| path.ends_with(&format!(".{field}")))
+}
+
+/// Checks whether a diff path is ignored by default: start_line, end_line,
+/// space name and kind changes, together with the metrics derived
+/// from other ones, unless they have been included back.
+pub(crate) fn is_ignored_by_default(path: &str, schema: &Schema, ignores: DefaultIgnores) -> bool {
+    if !ignores.enabled {
+        return false;
+    }
+    schema
+        .structural_fields()
+        .iter()
+        .any(|field| path.contains(field))
+        || (ignores.halstead && HALSTEAD_DERIVED.iter().any(|metric| path.contains(metric)))
+        || (ignores.mi && path.contains("metrics.mi"))
+        || path.contains("average")
+}
@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::html::space_path;
+use crate::schema::Schema;
+use crate::sha256::sha256_hex;
+use crate::summary::metric_name;
+use crate::SnippetDiff;
+
+/// Returns the fingerprint of a diff, derived from its source file, the
+/// path of its space and its metric, e.g. `3f2a9c1e04b7d5a8`. Unlike the
+/// baseline, it does not depend on the values, so a suppressed diff stays
+/// suppressed when the metric changes again.
+pub(crate) fn fingerprint(source_filename: &str, diff: &SnippetDiff, schema: &Schema) -> String {
+    let hash = sha256_hex(
+        format!(
+            "{source_filename}\n{}\n{}",
+            space_path(&diff.path),
+            metric_name(&diff.path, schema)
+        )
+        .as_bytes(),
+    );
+    hash[..16].to_owned()
+}
+
+/// The fingerprints of the diffs which are not reported.
+pub(crate) struct Suppressions {
+    fingerprints: HashSet<String>,
+}
+
+impl Suppressions {
+    /// Reads a file with a fingerprint at the start of each line, which can
+    /// be followed by a note. The empty lines and the ones starting with `#`
+    /// are skipped.
+    pub(crate) fn read(path: &Path) -> std::io::Result<Self> {
+        let fingerprints = std::fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_whitespace().next())
+            .map(str::to_owned)
+            .collect();
+        Ok(Self { fingerprints })
+    }
+
+    pub(crate) fn is_suppressed(&self, fingerprint: &str) -> bool {
+        self.fingerprints.contains(fingerprint)
+    }
+}
@@ -0,0 +1,104 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::html::snippet_lines;
+use crate::metrics_file;
+use crate::schema::Schema;
+use crate::{CodeSnippets, LinesRange};
+
+/// Returns the file name of a minimal test, e.g. `src_lib_12-15.rs` for
+/// the lines 12 to 15 of `src/lib.rs`.
+pub(crate) fn test_file_name(source_filename: &str, lines_range: &LinesRange) -> String {
+    let flat = source_filename.replace(['/', '\\'], "_");
+    let flat = Path::new(&flat);
+    let mut name = format!(
+        "{}_{}-{}",
+        flat.file_stem().unwrap_or_default().to_string_lossy(),
+        lines_range.start_line + 1,
+        lines_range.end_line
+    );
+    if let Some(extension) = flat.extension() {
+        name = format!("{name}.{}", extension.to_string_lossy());
+    }
+    name
+}
+
+/// Returns the expected metrics of a minimal test: the subtrees of the
+/// new metric file of its outermost spaces, with their children and their
+/// lines counted from the first line of the test.
+pub(crate) fn expected_metrics(
+    snippets: &CodeSnippets,
+    lines_range: &LinesRange,
+    schema: &Schema,
+) -> Value {
+    let mut spaces: Vec<(&LinesRange, &metrics_file::Space)> = snippets
+        .fixture_spaces
+        .iter()
+        .filter(|(space_range, _)| {
+            lines_range.start_line <= space_range.start_line
+                && space_range.end_line <= lines_range.end_line
+        })
+        .collect();
+    spaces.sort_by_key(|(space_range, _)| {
+        (
+            space_range.start_line,
+            std::cmp::Reverse(space_range.end_line),
+        )
+    });
+    let mut outermost: Vec<Value> = Vec::new();
+    let mut last_end = 0;
+    for (space_range, space) in spaces {
+        // The others are children of the outermost ones
+        if outermost.is_empty() || space_range.start_line >= last_end {
+            // Counted from the first line of the minimal test
+            let mut space = space.clone();
+            space.relocate(lines_range.start_line as u64);
+            outermost.push(space.to_value(schema));
+            last_end = space_range.end_line;
+        }
+    }
+    json!({
+        "source": snippets.source_filename,
+        "start_line": lines_range.start_line + 1,
+        "end_line": lines_range.end_line,
+        "spaces": outermost,
+    })
+}
+
+/// Returns the name of the expected metrics of a minimal test, next to it,
+/// e.g. `src_lib_12-15.rs.expected.json`.
+pub(crate) fn expected_file_name(test_file_name: &str) -> String {
+    format!("{test_file_name}.expected.json")
+}
+
+/// Writes each minimal test of a source file as a fixture of the tests
+/// of rust-code-analysis: its code, e.g. `src_lib_12-15.rs`, and its
+/// expected metrics, as the new metric file computes them, in
+/// `src_lib_12-15.rs.expected.json`. Returns the paths of the written files.
+pub(crate) fn write_fixtures(
+    dir: &Path,
+    snippets: &CodeSnippets,
+    source_file: &str,
+    schema: &Schema,
+) -> std::io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    for lines_range in snippets.snippets_data.keys() {
+        let name = test_file_name(&snippets.source_filename, lines_range);
+        let (_, lines, _) = snippet_lines(source_file, lines_range, 0);
+        let mut code = lines.join("\n");
+        code.push('\n');
+        let code_path = dir.join(&name);
+        std::fs::write(&code_path, code)?;
+        let expected = expected_metrics(snippets, lines_range, schema);
+        let expected_path = dir.join(expected_file_name(&name));
+        std::fs::write(
+            &expected_path,
+            serde_json::to_string_pretty(&expected).unwrap() + "\n",
+        )?;
+        written.push(code_path);
+        written.push(expected_path);
+    }
+    Ok(written)
+}
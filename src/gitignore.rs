@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::glob::Glob;
+
+// The ignore files read in each directory, the later taking precedence
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+struct Rule {
+    glob: Glob,
+    // A `!pattern`, including back what an earlier rule ignored
+    negated: bool,
+    // A `pattern/`, only matching directories
+    dir_only: bool,
+}
+
+/// Parses a line of an ignore file in the format of `.gitignore`.
+fn parse_rule(line: &str) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negated, pattern) = match line.strip_prefix('!') {
+        Some(pattern) => (true, pattern),
+        // `\#` and `\!` start a pattern with the character itself
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (dir_only, pattern) = match pattern.strip_suffix('/') {
+        Some(pattern) => (true, pattern),
+        None => (false, pattern),
+    };
+    // A pattern with a slash is relative to the directory of its file,
+    // the others match a name at any depth
+    let pattern = if pattern.contains('/') {
+        format!("/{}", pattern.trim_start_matches('/'))
+    } else {
+        pattern.to_owned()
+    };
+    Glob::new(&pattern).ok().map(|glob| Rule {
+        glob,
+        negated,
+        dir_only,
+    })
+}
+
+struct IgnoreFile {
+    // The directory of the file, relative to the root of the walk
+    dir: PathBuf,
+    // The root of the walk relative to the directory of the file,
+    // for the files above it
+    prefix: PathBuf,
+    rules: Vec<Rule>,
+}
+
+fn read_ignore_file(path: &Path, dir: &Path, prefix: &Path) -> Option<Arc<IgnoreFile>> {
+    let content = std::fs::read_to_string(path).ok()?;
+    Some(Arc::new(IgnoreFile {
+        dir: dir.to_path_buf(),
+        prefix: prefix.to_path_buf(),
+        rules: content.lines().filter_map(parse_rule).collect(),
+    }))
+}
+
+/// The ignore files which apply to a directory of a walk, from the one
+/// with the lowest precedence to the one with the highest.
+#[derive(Clone, Default)]
+pub(crate) struct Ignores {
+    files: Vec<Arc<IgnoreFile>>,
+}
+
+impl Ignores {
+    /// Returns the ignore files applying to the root of a walk: the ones
+    /// given with --ignore-file, which are relative to the root, then the
+    /// ones of the directories above it up to the git checkout which
+    /// contains it, if any, then its own.
+    pub(crate) fn new(root: &Path, ignore_files: &[PathBuf]) -> Self {
+        let mut files: Vec<Arc<IgnoreFile>> = ignore_files
+            .iter()
+            .filter_map(|path| read_ignore_file(path, Path::new(""), Path::new("")))
+            .collect();
+        if let Ok(root) = root.canonicalize() {
+            let mut above = Vec::new();
+            for dir in root.ancestors().skip(1) {
+                let prefix = root.strip_prefix(dir).unwrap();
+                above.extend(
+                    IGNORE_FILES.iter().rev().filter_map(|name| {
+                        read_ignore_file(&dir.join(name), Path::new(""), prefix)
+                    }),
+                );
+                if dir.join(".git").exists() {
+                    files.extend(above.into_iter().rev());
+                    break;
+                }
+            }
+        }
+        Self { files }.enter(root, Path::new(""))
+    }
+
+    /// Returns the ignore files applying to a directory of a walk, given
+    /// relative to its root, which are the ones of its parent directory
+    /// together with its own.
+    pub(crate) fn enter(&self, root: &Path, dir: &Path) -> Self {
+        let mut ignores = self.clone();
+        ignores.files.extend(
+            IGNORE_FILES.iter().filter_map(|name| {
+                read_ignore_file(&root.join(dir).join(name), dir, Path::new(""))
+            }),
+        );
+        ignores
+    }
+
+    /// Checks whether a file or a directory, given relative to the root of
+    /// the walk, is ignored. The last rule matching it decides.
+    pub(crate) fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for file in &self.files {
+            let Ok(path) = relative.strip_prefix(&file.dir) else {
+                continue;
+            };
+            let path = format!("/{}", file.prefix.join(path).display());
+            for rule in &file.rules {
+                if (is_dir || !rule.dir_only) && rule.glob.is_match(&path) {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The rules of an ignore file at the root of the walk
+    fn ignores(content: &str) -> Ignores {
+        Ignores {
+            files: vec![Arc::new(IgnoreFile {
+                dir: PathBuf::new(),
+                prefix: PathBuf::new(),
+                rules: content.lines().filter_map(parse_rule).collect(),
+            })],
+        }
+    }
+
+    #[test]
+    fn names_at_any_depth() {
+        let ignores = ignores("# build outputs\n\n*.json\nbuild\n");
+        assert!(ignores.is_ignored(Path::new("a.rs.json"), false));
+        assert!(ignores.is_ignored(Path::new("src/a.rs.json"), false));
+        assert!(ignores.is_ignored(Path::new("src/build"), true));
+        assert!(!ignores.is_ignored(Path::new("src/a.rs"), false));
+        assert!(!ignores.is_ignored(Path::new("# build outputs"), false));
+    }
+
+    #[test]
+    fn negation() {
+        let kept = ignores("*.json\n!keep.json\n");
+        assert!(kept.is_ignored(Path::new("a.json"), false));
+        assert!(!kept.is_ignored(Path::new("src/keep.json"), false));
+        // The last matching rule decides
+        assert!(ignores("!keep.json\n*.json\n").is_ignored(Path::new("keep.json"), false));
+    }
+
+    #[test]
+    fn escapes() {
+        let ignores = ignores("\\#notes\n\\!important\n");
+        assert!(ignores.is_ignored(Path::new("#notes"), false));
+        assert!(ignores.is_ignored(Path::new("!important"), false));
+    }
+
+    #[test]
+    fn anchored() {
+        let ignores = ignores("/target\nsrc/gen\n");
+        assert!(ignores.is_ignored(Path::new("target"), true));
+        assert!(!ignores.is_ignored(Path::new("a/target"), true));
+        // A slash in the middle anchors the pattern too
+        assert!(ignores.is_ignored(Path::new("src/gen"), true));
+        assert!(!ignores.is_ignored(Path::new("a/src/gen"), true));
+    }
+
+    #[test]
+    fn directories_only() {
+        let ignores = ignores("out/\n");
+        assert!(ignores.is_ignored(Path::new("out"), true));
+        assert!(ignores.is_ignored(Path::new("a/out"), true));
+        assert!(!ignores.is_ignored(Path::new("out"), false));
+    }
+
+    #[test]
+    fn double_stars() {
+        let ignores = ignores("**/gen/*.json\nlogs/**\na/**/b\n");
+        assert!(ignores.is_ignored(Path::new("gen/x.json"), false));
+        assert!(ignores.is_ignored(Path::new("src/gen/x.json"), false));
+        assert!(!ignores.is_ignored(Path::new("gen/sub/x.json"), false));
+        assert!(ignores.is_ignored(Path::new("logs/a/b.json"), false));
+        assert!(ignores.is_ignored(Path::new("a/b"), true));
+        assert!(ignores.is_ignored(Path::new("a/x/y/b"), true));
+    }
+
+    #[test]
+    fn nested_ignore_files() {
+        let root = std::env::temp_dir().join(format!(
+            "json-minimal-tests-gitignore-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.json\n/top.rs\n").unwrap();
+        // The deeper file takes precedence, and .ignore over .gitignore
+        std::fs::write(root.join("sub").join(".gitignore"), "!*.json\n").unwrap();
+        std::fs::write(root.join("sub").join(".ignore"), "b.json\n").unwrap();
+
+        let ignores = Ignores::new(&root, &[]);
+        assert!(ignores.is_ignored(Path::new("a.json"), false));
+        assert!(ignores.is_ignored(Path::new("top.rs"), false));
+        let sub = ignores.enter(&root, Path::new("sub"));
+        assert!(!sub.is_ignored(Path::new("sub/a.json"), false));
+        assert!(sub.is_ignored(Path::new("sub/b.json"), false));
+        // The anchored pattern of the root does not apply below it
+        assert!(!sub.is_ignored(Path::new("sub/top.rs"), false));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use regex::Regex;
+
+/// A glob pattern matched against `/`-separated paths.
+///
+/// `**` matches any number of directories, `*` and `?` do not cross
+/// directory boundaries, `[...]` is a character class, `[!...]` its
+/// complement, `{a,b}` matches one of the alternatives and `\` escapes
+/// the next character. A relative pattern matches any trailing part of a
+/// path, so `src/**/*.rs` matches `/home/user/repo/src/lib.rs`.
+///
+/// ```
+/// use json_minimal_tests::glob::Glob;
+///
+/// let glob = Glob::new("src/**/*.rs").unwrap();
+/// assert!(glob.is_match("/repo/src/lib.rs"));
+/// assert!(glob.is_match("/repo/src/a/b/lib.rs"));
+/// assert!(!glob.is_match("/repo/src/lib.rs.json"));
+///
+/// let glob = Glob::new("halstead.*").unwrap();
+/// assert!(glob.is_match("halstead.volume"));
+/// assert!(!glob.is_match("halstead_volume"));
+/// ```
+#[derive(Debug)]
+pub struct Glob {
+    regex: Regex,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        let mut regex = String::from(if pattern.starts_with('/') {
+            "^"
+        } else {
+            "^(?:.*/)?"
+        });
+        let mut chars = pattern.chars().peekable();
+        let mut in_alternatives = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        // `**/` also matches no directory at all
+                        chars.next();
+                        regex.push_str("(?:.*/)?");
+                    } else {
+                        regex.push_str(".*");
+                    }
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                '[' => {
+                    regex.push('[');
+                    if chars.peek() == Some(&'!') {
+                        chars.next();
+                        regex.push('^');
+                    }
+                    // An unclosed class is left unclosed in the regex too,
+                    // which is then an error
+                    for c in chars.by_ref() {
+                        if c == ']' {
+                            regex.push(']');
+                            break;
+                        }
+                        if c == '\\' || c == '[' {
+                            regex.push('\\');
+                        }
+                        regex.push(c);
+                    }
+                }
+                '{' => {
+                    in_alternatives = true;
+                    regex.push_str("(?:");
+                }
+                '}' if in_alternatives => {
+                    in_alternatives = false;
+                    regex.push(')');
+                }
+                ',' if in_alternatives => regex.push('|'),
+                '\\' => {
+                    if let Some(c) = chars.next() {
+                        regex.push_str(&regex::escape(&c.to_string()));
+                    }
+                }
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+        Ok(Self {
+            regex: Regex::new(&regex)?,
+        })
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        self.regex.is_match(&path.replace('\\', "/"))
+    }
+}
+
+/// Reads a file with a `glob = value` rule per line, e.g.
+/// `cyclomatic.* = high`, parsing each value with `parse`. The blank
+/// lines and the lines starting with `#` are skipped.
+pub fn read_glob_rules<T>(
+    path: &Path,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> std::io::Result<Vec<(Glob, T)>> {
+    let mut rules = Vec::new();
+    for (number, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |message: String| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("line {}: {message}", number + 1),
+            )
+        };
+        let (pattern, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid("expected `glob = value`".to_owned()))?;
+        let glob =
+            Glob::new(pattern.trim()).map_err(|err| invalid(format!("invalid glob: {err}")))?;
+        rules.push((glob, parse(value.trim()).map_err(invalid)?));
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Glob::new(pattern).unwrap().is_match(path)
+    }
+
+    #[test]
+    fn stars() {
+        assert!(matches("*.rs", "/a/b/lib.rs"));
+        assert!(matches("/*.rs", "/lib.rs"));
+        assert!(!matches("/*.rs", "/a/lib.rs"));
+        assert!(matches("/**/*.rs", "/lib.rs"));
+        assert!(matches("/**/*.rs", "/a/b/lib.rs"));
+        assert!(matches("/a/**", "/a/b/c"));
+        assert!(!matches("/a/*", "/a/b/c"));
+        assert!(matches("cyclomatic.*", "cyclomatic.sum"));
+        assert!(matches("**", "cyclomatic.sum"));
+    }
+
+    #[test]
+    fn question_mark() {
+        assert!(matches("a?.rs", "ab.rs"));
+        assert!(!matches("a?.rs", "a.rs"));
+        assert!(!matches("/a?b", "/a/b"));
+    }
+
+    #[test]
+    fn character_classes() {
+        assert!(matches("[ab].rs", "a.rs"));
+        assert!(!matches("[ab].rs", "c.rs"));
+        assert!(matches("[a-c]x", "bx"));
+        assert!(matches("[!ab].rs", "c.rs"));
+        assert!(!matches("[!ab].rs", "a.rs"));
+        assert!(matches("[[]x", "[x"));
+        assert!(Glob::new("[ab").is_err());
+    }
+
+    #[test]
+    fn alternatives() {
+        assert!(matches("*.{rs,json}", "lib.json"));
+        assert!(!matches("*.{rs,json}", "lib.toml"));
+        // Outside the braces a comma is itself
+        assert!(matches("a,b", "a,b"));
+    }
+
+    #[test]
+    fn escaping() {
+        // The characters of a regex are themselves
+        assert!(matches("a.rs", "a.rs"));
+        assert!(!matches("a.rs", "abrs"));
+        assert!(matches("f(x)+$", "f(x)+$"));
+        assert!(matches("\\*.rs", "*.rs"));
+        assert!(!matches("\\*.rs", "lib.rs"));
+        assert!(matches("\\[a]", "[a]"));
+        assert!(matches("a\\{b}", "a{b}"));
+    }
+
+    #[test]
+    fn windows_separators() {
+        assert!(matches("src/*.rs", "C:\\repo\\src\\lib.rs"));
+    }
+}
@@ -0,0 +1,180 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+/// Name of the grammar diff written in the output directory.
+pub(crate) const GRAMMAR_DIFF_NAME: &str = "grammar.diff";
+
+// Files generated by tree-sitter from the grammar, whose hunks
+// only repeat the changes of the grammar itself
+const GENERATED_FILES: &[&str] = &[
+    "src/parser.c",
+    "src/grammar.json",
+    "src/node-types.json",
+    "src/tree_sitter/parser.h",
+];
+
+/// A file changed by a patch.
+pub(crate) struct PatchFile {
+    name: String,
+    added: usize,
+    removed: usize,
+    // The lines of the patch about this file
+    text: String,
+}
+
+/// The change of a tree-sitter grammar which produced the new metrics,
+/// as a unified diff.
+pub(crate) struct GrammarDiff {
+    path: PathBuf,
+    files: Vec<PatchFile>,
+    // Changed files generated from the grammar, left out
+    generated: Vec<String>,
+}
+
+fn is_generated(name: &str) -> bool {
+    GENERATED_FILES
+        .iter()
+        .any(|generated| name == *generated || name.ends_with(&format!("/{generated}")))
+}
+
+/// Returns the name of a file from a `---` or `+++` line of a patch.
+fn patch_file_name(line: &str) -> Option<&str> {
+    let name = line[4..].split('\t').next()?.trim_end();
+    if name == "/dev/null" {
+        return None;
+    }
+    Some(
+        name.strip_prefix("a/")
+            .or_else(|| name.strip_prefix("b/"))
+            .unwrap_or(name),
+    )
+}
+
+/// Returns the number of old and new lines of a hunk
+/// from its header, e.g. `@@ -12,7 +12,9 @@`.
+fn hunk_lengths(line: &str) -> Option<(usize, usize)> {
+    let mut ranges = line.strip_prefix("@@ ")?.split(' ');
+    let length = |range: &str| {
+        range
+            .split_once(',')
+            .map_or(Some(1), |(_, len)| len.parse().ok())
+    };
+    let old = length(ranges.next()?.strip_prefix('-')?)?;
+    let new = length(ranges.next()?.strip_prefix('+')?)?;
+    Some((old, new))
+}
+
+fn parse_patch(patch: &str) -> Vec<PatchFile> {
+    let mut files: Vec<PatchFile> = Vec::new();
+    let mut old_name = None;
+    // Between a `diff --git` line and the first hunk
+    let mut in_git_header = false;
+    // Lines left in the current hunk
+    let (mut old_left, mut new_left): (usize, usize) = (0, 0);
+    for line in patch.lines() {
+        if old_left > 0 || new_left > 0 {
+            if let Some(file) = files.last_mut() {
+                match line.chars().next() {
+                    Some('+') => {
+                        file.added += 1;
+                        new_left = new_left.saturating_sub(1);
+                    }
+                    Some('-') => {
+                        file.removed += 1;
+                        old_left = old_left.saturating_sub(1);
+                    }
+                    Some('\\') => {}
+                    _ => {
+                        old_left = old_left.saturating_sub(1);
+                        new_left = new_left.saturating_sub(1);
+                    }
+                }
+            }
+        } else {
+            if line.starts_with("diff --git ") || (line.starts_with("--- ") && !in_git_header) {
+                in_git_header = line.starts_with("diff --git ");
+                files.push(PatchFile {
+                    name: String::new(),
+                    added: 0,
+                    removed: 0,
+                    text: String::new(),
+                });
+            }
+            if line.starts_with("--- ") {
+                old_name = patch_file_name(line).map(str::to_owned);
+            } else if line.starts_with("+++ ") {
+                if let Some(file) = files.last_mut() {
+                    file.name = patch_file_name(line)
+                        .map(str::to_owned)
+                        .or_else(|| old_name.take())
+                        .unwrap_or_default();
+                }
+            } else if let Some((old, new)) = hunk_lengths(line) {
+                in_git_header = false;
+                old_left = old;
+                new_left = new;
+            }
+        }
+        if let Some(file) = files.last_mut() {
+            file.text.push_str(line);
+            file.text.push('\n');
+        }
+    }
+    files
+}
+
+impl GrammarDiff {
+    pub(crate) fn read(path: &Path) -> std::io::Result<Self> {
+        let patch = std::fs::read_to_string(path)?;
+        let (generated, files): (Vec<PatchFile>, Vec<PatchFile>) = parse_patch(&patch)
+            .into_iter()
+            .partition(|file| is_generated(&file.name));
+        Ok(Self {
+            path: path.to_path_buf(),
+            files,
+            generated: generated.into_iter().map(|file| file.name).collect(),
+        })
+    }
+
+    /// Returns the hunks of the patch, without the generated files.
+    pub(crate) fn relevant_text(&self) -> String {
+        self.files.iter().map(|file| file.text.as_str()).collect()
+    }
+
+    pub(crate) fn write_header<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "Grammar diff {}", self.path.display())?;
+        for file in &self.files {
+            writeln!(
+                writer,
+                "{:>8}  {:>8}  {}",
+                format!("+{}", file.added),
+                format!("-{}", file.removed),
+                file.name
+            )?;
+        }
+        if !self.generated.is_empty() {
+            writeln!(
+                writer,
+                "          (generated: {})",
+                self.generated.join(", ")
+            )?;
+        }
+        writeln!(writer)
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        let files: Vec<Value> = self
+            .files
+            .iter()
+            .map(|file| json!({ "name": file.name, "added": file.added, "removed": file.removed }))
+            .collect();
+        json!({
+            "path": self.path,
+            "files": files,
+            "generated_files": self.generated,
+            "patch": self.relevant_text(),
+        })
+    }
+}
@@ -0,0 +1,1071 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use html_escape::{encode_double_quoted_attribute, encode_text};
+
+use crate::by_metric::{MetricDiff, MetricGroups};
+use crate::filter::FilteredDiff;
+use crate::fingerprint::fingerprint;
+use crate::report::{sorted_snippets, Report, ReportWriter};
+use crate::results_db::Trend;
+use crate::schema::Schema;
+use crate::severity::Severity;
+use crate::sha256::sha256_hex;
+use crate::summary::{format_change, metric_name};
+use crate::{CodeSnippets, LinesRange, Options, SnippetDiff};
+
+const STYLE: &str = "body { background: var(--bg); color: var(--fg); font-family: system-ui, sans-serif; font-size: 16px; line-height: 1.5; margin: 2em auto; max-width: 75em; padding: 0 1em }
+a { color: var(--link) }
+table { border-collapse: collapse; margin: 0.5em 0 1em }
+th, td { border: 1px solid var(--border); padding: 0.2em 0.8em; text-align: left }
+th { background: var(--header) }
+td.path { font-family: ui-monospace, monospace }
+td.old { color: var(--old) }
+td.new { color: var(--new) }
+td.change { font-family: ui-monospace, monospace; text-align: right; white-space: nowrap }
+td.fingerprint { color: var(--muted); font-family: ui-monospace, monospace; font-size: 0.8em }
+pre { background: var(--code); border: 1px solid var(--border); font-family: ui-monospace, monospace; font-size: 0.9em; overflow-x: auto; padding: 0.6em }
+pre.context { color: var(--muted); margin: 0 }
+p.partial, p.stale { background: var(--warning-bg); border: 1px solid var(--warning-border); padding: 0.5em }
+p.checked, p.shift, p.omitted, p.unchanged { border: 1px solid var(--border); padding: 0.5em }
+details { margin: 1em 0 }
+details.snippet > summary { cursor: pointer; padding: 0.3em 0 }
+span.line-number { color: var(--muted); margin-right: 1em; user-select: none }
+mark.bound { background: var(--warning-bg); color: inherit }
+td.severity-high { color: var(--old); font-weight: bold }
+td.severity-low { color: var(--muted) }
+span.badge { background: var(--header); border: 1px solid var(--border); border-radius: 1em; font-family: ui-monospace, monospace; font-size: 0.8em; margin-left: 0.3em; padding: 0 0.6em }
+form.filters { display: flex; flex-wrap: wrap; gap: 0.5em; margin: 1em 0 }";
+
+// Hides the diffs, the snippets and the files which do not match the
+// filters of the page, without running the tool again
+const FILTER_SCRIPT: &str = "<script>
+const filters = document.querySelector('form.filters');
+const allHidden = (element) => {
+    const rows = element.querySelectorAll('tr[data-metric]');
+    return rows.length > 0 && [...rows].every((row) => row.hidden);
+};
+filters.addEventListener('input', () => {
+    const file = filters.elements.file ? filters.elements.file.value.toLowerCase() : '';
+    const metric = filters.elements.metric.value.toLowerCase();
+    const minDelta = parseFloat(filters.elements.delta.value);
+    for (const row of document.querySelectorAll('tr[data-metric]')) {
+        const delta = Math.abs(parseFloat(row.dataset.delta));
+        row.hidden = !row.dataset.metric.toLowerCase().includes(metric)
+            || (!isNaN(minDelta) && !(delta >= minDelta));
+    }
+    for (const element of document.querySelectorAll('table, details.snippet, details.metric')) {
+        element.hidden = allHidden(element);
+    }
+    for (const element of document.querySelectorAll('details.file')) {
+        element.hidden = !element.dataset.file.toLowerCase().includes(file) || allHidden(element);
+    }
+    for (const link of document.querySelectorAll('li.file-link')) {
+        link.hidden = document.getElementById(link.dataset.target).hidden;
+    }
+});
+</script>";
+
+/// Writes the filters of the diffs of a page, by metric name and minimum
+/// absolute change, and by source file name in the single report.
+fn write_filters<W: Write + ?Sized>(writer: &mut W, by_file: bool) -> std::io::Result<()> {
+    writeln!(writer, "<form class=\"filters\">")?;
+    if by_file {
+        writeln!(
+            writer,
+            "<input type=\"search\" name=\"file\" placeholder=\"File name\">"
+        )?;
+    }
+    writeln!(
+        writer,
+        "<input type=\"search\" name=\"metric\" placeholder=\"Metric name\">
+<input type=\"number\" name=\"delta\" placeholder=\"Minimum change\" min=\"0\" step=\"any\">
+</form>"
+    )
+}
+
+const LIGHT: &str = "--bg: #ffffff; --fg: #1f2328; --link: #0969da; --border: #d0d7de; --header: #f0f3f6; --code: #f6f8fa; --muted: #8c959f; --old: #b31d28; --new: #22863a; --warning-bg: #fff8c5; --warning-border: #d4a72c";
+
+const DARK: &str = "--bg: #0d1117; --fg: #e6edf3; --link: #4493f8; --border: #30363d; --header: #161b22; --code: #161b22; --muted: #7d8590; --old: #ff7b72; --new: #7ee787; --warning-bg: #3b2e00; --warning-border: #9e6a03";
+
+/// The colors of the styled reports.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Theme {
+    // The theme of the system showing the report
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub(crate) const NAMES: &'static [&'static str] = &["auto", "light", "dark"];
+
+    pub(crate) fn parse(name: &str) -> Self {
+        match name {
+            "light" => Theme::Light,
+            "dark" => Theme::Dark,
+            _ => Theme::Auto,
+        }
+    }
+
+    /// Returns the style sheet of the reports with the colors of the theme.
+    fn style(self) -> String {
+        let colors = match self {
+            Theme::Auto => format!(
+                ":root {{ color-scheme: light dark; {LIGHT} }}
+@media (prefers-color-scheme: dark) {{ :root {{ {DARK} }} }}"
+            ),
+            Theme::Light => format!(":root {{ color-scheme: light; {LIGHT} }}"),
+            Theme::Dark => format!(":root {{ color-scheme: dark; {DARK} }}"),
+        };
+        format!("{colors}\n{STYLE}")
+    }
+}
+
+// The path of the space of a diff, e.g. `.spaces[0].spaces[1]`,
+// empty for the metrics of the whole file
+pub(crate) fn space_path(diff_path: &str) -> &str {
+    diff_path.rfind(']').map_or("", |end| &diff_path[..=end])
+}
+
+/// Returns the id of a section of the report of a source file, derived
+/// from the file and the path of the space it is about, so that it is the
+/// same in every run and can be linked, e.g. `#snippet-3f2a9c1e04b7`.
+fn anchor(kind: &str, source_filename: &str, space_path: &str) -> String {
+    let hash = sha256_hex(format!("{source_filename}\n{space_path}").as_bytes());
+    format!("{kind}-{}", &hash[..12])
+}
+
+/// Splits the lines of a snippet into the ones before it, the snippet
+/// itself and the ones after it, clamping the context to the file bounds.
+pub(crate) fn snippet_lines<'a>(
+    source_file: &'a str,
+    lines_range: &LinesRange,
+    context: usize,
+) -> (Vec<&'a str>, Vec<&'a str>, Vec<&'a str>) {
+    let lines: Vec<&str> = source_file.lines().collect();
+    let end_line = lines_range.end_line.min(lines.len());
+    let start_line = lines_range.start_line.min(end_line);
+    (
+        lines[start_line.saturating_sub(context)..start_line].to_vec(),
+        lines[start_line..end_line].to_vec(),
+        lines[end_line..(end_line + context).min(lines.len())].to_vec(),
+    )
+}
+
+fn write_stale_warning<W: Write + ?Sized>(
+    writer: &mut W,
+    snippets: &CodeSnippets,
+    lines_range: &LinesRange,
+) -> std::io::Result<()> {
+    let is_stale = snippets.stale.iter().any(|stale| {
+        stale.start_line < lines_range.end_line && lines_range.start_line < stale.end_line
+    });
+    if is_stale {
+        write_stale_paragraph(writer)?;
+    }
+    Ok(())
+}
+
+fn write_stale_paragraph<W: Write + ?Sized>(writer: &mut W) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "<p class=\"stale\"><b>Stale source:</b> these lines differ from the ones the metrics were computed on</p>"
+    )
+}
+
+fn write_check_label<W: Write + ?Sized>(
+    writer: &mut W,
+    snippets: &CodeSnippets,
+    lines_range: &LinesRange,
+) -> std::io::Result<()> {
+    match snippets.checked.get(lines_range) {
+        Some(true) => writeln!(
+            writer,
+            "<p class=\"checked\"><b>Self-contained:</b> the snippet compiles on its own</p>"
+        ),
+        Some(false) => writeln!(
+            writer,
+            "<p class=\"checked\"><b>Context-dependent:</b> the snippet does not compile without the rest of the file</p>"
+        ),
+        None => Ok(()),
+    }
+}
+
+fn write_unchanged_code<W: Write + ?Sized>(
+    writer: &mut W,
+    snippets: &CodeSnippets,
+    lines_range: &LinesRange,
+) -> std::io::Result<()> {
+    if snippets.code_changed.get(lines_range) == Some(&false) {
+        writeln!(
+            writer,
+            "<p class=\"unchanged\"><b>Same code:</b> only the metrics of these lines changed, not the lines themselves</p>"
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the severity of a diff, when there are severity rules.
+pub(crate) fn diff_severity(diff: &SnippetDiff, options: &Options) -> Option<Severity> {
+    options
+        .severity_rules
+        .as_ref()
+        .map(|rules| rules.severity(metric_name(&diff.path, &options.schema)))
+}
+
+/// Writes a badge for each metric changed in a snippet, with the sum
+/// of its changes when they are numbers, e.g. `cyclomatic.sum +2`.
+fn write_badges<W: Write + ?Sized>(
+    writer: &mut W,
+    diffs: &[SnippetDiff],
+    schema: &Schema,
+    precision: usize,
+) -> std::io::Result<()> {
+    let mut metrics: BTreeMap<&str, Option<f64>> = BTreeMap::new();
+    for diff in diffs {
+        let total = metrics
+            .entry(metric_name(&diff.path, schema))
+            .or_insert(Some(0.));
+        *total = total.zip(diff.delta()).map(|(total, delta)| total + delta);
+    }
+    for (name, total) in metrics {
+        match total {
+            Some(total) => write!(
+                writer,
+                " <span class=\"badge\">{} {:+.precision$}</span>",
+                encode_text(name),
+                total
+            )?,
+            None => write!(
+                writer,
+                " <span class=\"badge\">{}</span>",
+                encode_text(name)
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes the diffs in a table, with the change of the numeric ones,
+/// their severity when there are severity rules and their fingerprint.
+fn write_diffs_table<'a, W: Write + ?Sized, I: IntoIterator<Item = &'a SnippetDiff>>(
+    writer: &mut W,
+    source_filename: &str,
+    diffs: I,
+    options: &Options,
+) -> std::io::Result<()> {
+    let severity_header = match options.severity_rules {
+        Some(_) => "<th>Severity</th>",
+        None => "",
+    };
+    writeln!(
+        writer,
+        "<table>
+<tr><th>Path</th><th>Old</th><th>New</th><th>Change</th>{severity_header}<th>Fingerprint</th></tr>"
+    )?;
+    // The values of the missing keys are not JSON, e.g. <absent>
+    for diff in diffs {
+        let SnippetDiff { path, old, new } = diff;
+        // The filters of the page read the metric and the change of a row
+        let delta = diff
+            .delta()
+            .map(|delta| format!(" data-delta=\"{delta}\""))
+            .unwrap_or_default();
+        write!(
+            writer,
+            "<tr data-metric=\"{}\"{delta}><td class=\"path\">{}</td><td class=\"old\">{}</td><td class=\"new\">{}</td><td class=\"change\">{}</td>",
+            encode_double_quoted_attribute(metric_name(path, &options.schema)),
+            encode_text(path),
+            encode_text(old),
+            encode_text(new),
+            format_change(diff, options.precision).unwrap_or_default()
+        )?;
+        if let Some(severity) = diff_severity(diff, options) {
+            write!(
+                writer,
+                "<td class=\"severity-{0}\">{0}</td>",
+                severity.name()
+            )?;
+        }
+        writeln!(
+            writer,
+            "<td class=\"fingerprint\">{}</td></tr>",
+            fingerprint(source_filename, diff, &options.schema)
+        )?;
+    }
+    writeln!(writer, "</table>")
+}
+
+/// Writes some lines of code in a block, each one after its number
+/// in the source file, which is not copied with the code.
+/// With `highlight_bounds`, the first and the last line are marked.
+fn write_numbered<W: Write + ?Sized>(
+    writer: &mut W,
+    class: Option<&str>,
+    lines: &[&str],
+    first_number: usize,
+    width: usize,
+    highlight_bounds: bool,
+) -> std::io::Result<()> {
+    match class {
+        Some(class) => write!(writer, "<pre class=\"{class}\">")?,
+        None => write!(writer, "<pre>")?,
+    }
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        write!(
+            writer,
+            "<span class=\"line-number\">{:>width$}</span>",
+            first_number + i
+        )?;
+        if highlight_bounds && (i == 0 || i + 1 == lines.len()) {
+            write!(writer, "<mark class=\"bound\">{line}</mark>")?;
+        } else {
+            write!(writer, "{line}")?;
+        }
+    }
+    writeln!(writer, "</pre>")
+}
+
+fn write_code<W: Write + ?Sized>(
+    writer: &mut W,
+    source_file: &str,
+    lines_range: &LinesRange,
+    options: &Options,
+) -> std::io::Result<()> {
+    let (before, lines, after) = snippet_lines(source_file, lines_range, options.context);
+    // The first line of the context, starting from 1
+    let first_number = lines_range.start_line.saturating_sub(before.len()) + 1;
+    // The width of the last line number
+    let width = (first_number + before.len() + lines.len() + after.len() - 1)
+        .to_string()
+        .len();
+    if !before.is_empty() {
+        write_numbered(writer, Some("context"), &before, first_number, width, false)?;
+    }
+    write_numbered(
+        writer,
+        None,
+        &lines,
+        first_number + before.len(),
+        width,
+        options.highlight_bounds,
+    )?;
+    if !after.is_empty() {
+        write_numbered(
+            writer,
+            Some("context"),
+            &after,
+            first_number + before.len() + lines.len(),
+            width,
+            false,
+        )?;
+    }
+    Ok(())
+}
+
+/// The styled report, with the diffs laid out in tables.
+pub(crate) struct HtmlReport {
+    // Whether the sections are written in a page of their own,
+    // rather than in the single report
+    page: bool,
+    // The source file, escaped
+    source_file: String,
+    // Whether the heading of the snippets has been written
+    snippets_started: bool,
+}
+
+impl HtmlReport {
+    pub(crate) fn new(source_file: &str, page: bool) -> Self {
+        Self {
+            page,
+            source_file: encode_text(source_file).into_owned(),
+            snippets_started: false,
+        }
+    }
+}
+
+impl ReportWriter for HtmlReport {
+    fn write_header(&mut self, writer: &mut dyn Write, report: &Report) -> std::io::Result<()> {
+        let snippets = report.snippets;
+        if self.page {
+            writeln!(
+                writer,
+                "<!DOCTYPE html>
+<html>
+<head>
+    <meta charset=\"utf-8\">
+    <title>{}</title>
+    <style>
+{}
+    </style>
+</head>
+<body>
+<h1>{}</h1>",
+                report.output_filename,
+                report.options.theme.style(),
+                encode_text(&snippets.source_filename)
+            )?;
+            write_filters(writer, false)?;
+        }
+        if snippets.partial {
+            writeln!(
+                writer,
+                "<p class=\"partial\"><b>Partial report:</b> the metrics have been recovered from a truncated JSON file</p>"
+            )?;
+        }
+        if let Some((shift, moved)) = snippets.line_shift {
+            writeln!(
+                writer,
+                "<p class=\"shift\"><b>Global line shift detected:</b> the lines of {moved} spaces moved by {shift:+}, which is not reported as diffs</p>"
+            )?;
+        }
+        if snippets.omitted_snippets > 0 {
+            writeln!(
+                writer,
+                "<p class=\"omitted\"><b>Minimal tests left out:</b> the ones with the largest change are reported, … and {} more</p>",
+                snippets.omitted_snippets
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_global_metrics(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+    ) -> std::io::Result<()> {
+        let snippets = report.snippets;
+        writeln!(
+            writer,
+            "<h2 id=\"{}\">Global Metrics</h2>",
+            anchor("global", &snippets.source_filename, "")
+        )?;
+        write_diffs_table(
+            writer,
+            &snippets.source_filename,
+            &snippets.global_metrics,
+            report.options,
+        )?;
+        if snippets.snippets_data.is_empty() {
+            writeln!(writer, "<h3>Code</h3>")?;
+            // The whole file is shown, so a single warning covers its
+            // stale lines
+            if !snippets.stale.is_empty() {
+                write_stale_paragraph(writer)?;
+            }
+            let lines: Vec<&str> = self.source_file.lines().collect();
+            let width = lines.len().to_string().len();
+            write_numbered(writer, None, &lines, 1, width, false)?;
+        }
+        Ok(())
+    }
+
+    fn write_snippet(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+        lines_range: &LinesRange,
+        diffs: &[SnippetDiff],
+    ) -> std::io::Result<()> {
+        let (snippets, options) = (report.snippets, report.options);
+        if !self.snippets_started {
+            writeln!(
+                writer,
+                "<h2 id=\"{}\">Spaces Data</h2>",
+                anchor("spaces", &snippets.source_filename, "")
+            )?;
+            self.snippets_started = true;
+        }
+        // A merged snippet is named after its first space
+        let space_path = diffs
+            .iter()
+            .map(|diff| space_path(&diff.path))
+            .min()
+            .unwrap_or_default();
+        // Collapsed, the changed metrics tell which snippets to open
+        write!(
+            writer,
+            "<details class=\"snippet\" id=\"{}\">\n<summary><b>Minimal test - lines ({}, {})</b>",
+            anchor("snippet", &snippets.source_filename, space_path),
+            lines_range.start_line + 1,
+            lines_range.end_line
+        )?;
+        write_badges(writer, diffs, &options.schema, options.precision)?;
+        writeln!(writer, "</summary>")?;
+        write_diffs_table(writer, &snippets.source_filename, diffs, options)?;
+        write_stale_warning(writer, snippets, lines_range)?;
+        write_check_label(writer, snippets, lines_range)?;
+        write_unchanged_code(writer, snippets, lines_range)?;
+        write_code(writer, &self.source_file, lines_range, options)?;
+        writeln!(writer, "</details>")
+    }
+
+    fn finish(&mut self, writer: &mut dyn Write, report: &Report) -> std::io::Result<()> {
+        let (snippets, options) = (report.snippets, report.options);
+        if !snippets.space_changes.is_empty() {
+            writeln!(
+                writer,
+                "<h2 id=\"{}\">Added and Removed Spaces</h2>",
+                anchor("space-changes", &snippets.source_filename, "")
+            )?;
+            write_diffs_table(
+                writer,
+                &snippets.source_filename,
+                &snippets.space_changes,
+                options,
+            )?;
+        }
+        if !snippets.positions.is_empty() {
+            writeln!(
+                writer,
+                "<h2 id=\"{}\">Positions</h2>",
+                anchor("positions", &snippets.source_filename, "")
+            )?;
+            for (lines_range, diffs) in sorted_snippets(&snippets.positions, options) {
+                let space_path = diffs
+                    .iter()
+                    .map(|diff| space_path(&diff.path))
+                    .min()
+                    .unwrap_or_default();
+                writeln!(
+                    writer,
+                    "<h3 id=\"{}\">Moved space - lines ({}, {})</h3>",
+                    anchor("moved", &snippets.source_filename, space_path),
+                    lines_range.start_line + 1,
+                    lines_range.end_line
+                )?;
+                write_diffs_table(writer, &snippets.source_filename, diffs, options)?;
+                write_stale_warning(writer, snippets, lines_range)?;
+                write_code(writer, &self.source_file, lines_range, options)?;
+            }
+        }
+        if !snippets.filtered.is_empty() || !snippets.ignored_spaces.is_empty() {
+            // Print the diffs left out of the report, collapsed
+            writeln!(
+                writer,
+                "<details id=\"{}\">
+<summary>Filtered out ({} diffs, {} spaces)</summary>
+<table>
+<tr><th>Path</th><th>Old</th><th>New</th><th>Rule</th></tr>",
+                anchor("filtered", &snippets.source_filename, ""),
+                snippets.filtered.len(),
+                snippets.ignored_spaces.len()
+            )?;
+            for pointer in &snippets.ignored_spaces {
+                writeln!(
+                    writer,
+                    "<tr><td class=\"path\">{}</td><td></td><td></td><td>ignored with --ignore-space</td></tr>",
+                    encode_text(pointer)
+                )?;
+            }
+            for FilteredDiff { diff, rule } in &snippets.filtered {
+                writeln!(
+                    writer,
+                    "<tr><td class=\"path\">{}</td><td class=\"old\">{}</td><td class=\"new\">{}</td><td>{}</td></tr>",
+                    encode_text(&diff.path),
+                    encode_text(&diff.old),
+                    encode_text(&diff.new),
+                    rule
+                )?;
+            }
+            writeln!(writer, "</table>\n</details>")?;
+        }
+        if self.page {
+            writeln!(
+                writer,
+                "{FILTER_SCRIPT}
+</body>
+</html>"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a single page with the sections of every reported source file,
+/// each one collapsible and linked from a table of contents, together
+/// with the number of its diffs.
+pub(crate) fn write_single<W: Write>(
+    writer: &mut W,
+    sections: &BTreeMap<String, (usize, String)>,
+    theme: Theme,
+) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "<!DOCTYPE html>
+<html>
+<head>
+    <meta charset=\"utf-8\">
+    <title>Metric diffs</title>
+    <style>
+{}
+details.file > summary {{ font-size: 1.3em; font-weight: bold }}
+    </style>
+</head>
+<body>
+<h1>Metric diffs</h1>
+<p>{} files with diffs</p>",
+        theme.style(),
+        sections.len()
+    )?;
+    write_filters(writer, true)?;
+    writeln!(writer, "<ul>")?;
+    for (source, (diffs, _)) in sections {
+        let id = anchor("file", source, "");
+        writeln!(
+            writer,
+            "<li class=\"file-link\" data-target=\"{id}\"><a href=\"#{id}\">{}</a> ({diffs} diffs)</li>",
+            encode_text(source)
+        )?;
+    }
+    writeln!(writer, "</ul>")?;
+    for (source, (_, section)) in sections {
+        writeln!(
+            writer,
+            "<details class=\"file\" id=\"{}\" data-file=\"{}\">
+<summary>{}</summary>
+{section}</details>",
+            anchor("file", source, ""),
+            encode_double_quoted_attribute(source),
+            encode_text(source)
+        )?;
+    }
+    writeln!(
+        writer,
+        "{FILTER_SCRIPT}
+</body>
+</html>"
+    )
+}
+
+/// Writes the report grouped by metric, a section for each metric with
+/// a row for each diff of it, in any source file.
+pub(crate) fn write_by_metric_page(
+    writer: &mut dyn Write,
+    groups: &MetricGroups,
+    options: &Options,
+) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "<!DOCTYPE html>
+<html>
+<head>
+    <meta charset=\"utf-8\">
+    <title>Metric diffs by metric</title>
+    <style>
+{}
+details.metric > summary {{ font-size: 1.3em; font-weight: bold }}
+    </style>
+</head>
+<body>
+<h1>Metric diffs by metric</h1>
+<p>{} metrics with diffs</p>",
+        options.theme.style(),
+        groups.groups.len()
+    )?;
+    write_filters(writer, false)?;
+    writeln!(writer, "<ul>")?;
+    for (metric, diffs) in &groups.groups {
+        writeln!(
+            writer,
+            "<li><a href=\"#{}\">{}</a> ({} diffs)</li>",
+            anchor("metric", metric, ""),
+            encode_text(metric),
+            diffs.len()
+        )?;
+    }
+    writeln!(writer, "</ul>")?;
+    for (metric, diffs) in &groups.groups {
+        writeln!(
+            writer,
+            "<details class=\"metric\" id=\"{}\" open>
+<summary>{}</summary>
+<table>
+<tr><th>Source</th><th>Path</th><th>Old</th><th>New</th><th>Change</th><th>Fingerprint</th></tr>",
+            anchor("metric", metric, ""),
+            encode_text(metric)
+        )?;
+        for MetricDiff {
+            source_filename,
+            diff,
+        } in diffs
+        {
+            let delta = diff
+                .delta()
+                .map(|delta| format!(" data-delta=\"{delta}\""))
+                .unwrap_or_default();
+            writeln!(
+                writer,
+                "<tr data-metric=\"{}\"{delta}><td class=\"path\">{}</td><td class=\"path\">{}</td><td class=\"old\">{}</td><td class=\"new\">{}</td><td class=\"change\">{}</td><td class=\"fingerprint\">{}</td></tr>",
+                encode_double_quoted_attribute(metric),
+                encode_text(source_filename),
+                encode_text(&diff.path),
+                encode_text(&diff.old),
+                encode_text(&diff.new),
+                format_change(diff, options.precision).unwrap_or_default(),
+                fingerprint(source_filename, diff, &options.schema)
+            )?;
+        }
+        writeln!(writer, "</table>\n</details>")?;
+    }
+    writeln!(
+        writer,
+        "{FILTER_SCRIPT}
+</body>
+</html>"
+    )
+}
+
+// The size of the trend chart and its margin for the axis labels
+const CHART_WIDTH: usize = 800;
+const CHART_HEIGHT: usize = 300;
+const CHART_MARGIN: usize = 40;
+
+/// Writes the number of diffs of each metric over the runs of a results
+/// database, as a line chart followed by a table of the counts.
+pub(crate) fn write_trend<W: Write + ?Sized>(
+    writer: &mut W,
+    trend: &Trend,
+    theme: Theme,
+) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "<!DOCTYPE html>
+<html>
+<head>
+    <meta charset=\"utf-8\">
+    <title>Metric diffs over the runs</title>
+    <style>
+{}
+svg {{ max-width: 100%; height: auto }}
+svg text {{ fill: currentColor; font-size: 12px }}
+svg .axis {{ stroke: currentColor }}
+ul.legend {{ list-style: none; padding: 0 }}
+ul.legend span {{ display: inline-block; width: 1em; height: 1em; margin-right: 0.5em; vertical-align: middle }}
+    </style>
+</head>
+<body>
+<h1>Metric diffs over the runs</h1>
+<p>{} runs, {} metrics with diffs</p>",
+        theme.style(),
+        trend.runs.len(),
+        trend.counts.len()
+    )?;
+    let max = trend
+        .counts
+        .values()
+        .flatten()
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let (width, height) = (
+        CHART_WIDTH - 2 * CHART_MARGIN,
+        CHART_HEIGHT - 2 * CHART_MARGIN,
+    );
+    let x = |run: usize| CHART_MARGIN + run * width / trend.runs.len().saturating_sub(1).max(1);
+    let y = |count: usize| CHART_MARGIN + height - count * height / max;
+    // A hue for each metric, far from the ones of the previous metrics
+    let color = |i: usize| format!("hsl({}, 65%, 45%)", i * 137 % 360);
+    writeln!(
+        writer,
+        "<svg viewBox=\"0 0 {CHART_WIDTH} {CHART_HEIGHT}\" role=\"img\">
+<line class=\"axis\" x1=\"{CHART_MARGIN}\" y1=\"{0}\" x2=\"{1}\" y2=\"{0}\"/>
+<line class=\"axis\" x1=\"{CHART_MARGIN}\" y1=\"{CHART_MARGIN}\" x2=\"{CHART_MARGIN}\" y2=\"{0}\"/>
+<text x=\"{2}\" y=\"{3}\" text-anchor=\"end\">{max}</text>
+<text x=\"{2}\" y=\"{0}\" text-anchor=\"end\">0</text>",
+        CHART_MARGIN + height,
+        CHART_MARGIN + width,
+        CHART_MARGIN - 5,
+        CHART_MARGIN + 4
+    )?;
+    for (run, (id, _)) in trend.runs.iter().enumerate() {
+        writeln!(
+            writer,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{id}</text>",
+            x(run),
+            CHART_MARGIN + height + 16
+        )?;
+    }
+    for (i, (metric, counts)) in trend.counts.iter().enumerate() {
+        let points: Vec<String> = counts
+            .iter()
+            .enumerate()
+            .map(|(run, count)| format!("{},{}", x(run), y(*count)))
+            .collect();
+        writeln!(
+            writer,
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" points=\"{}\"><title>{}</title></polyline>",
+            color(i),
+            points.join(" "),
+            encode_text(metric)
+        )?;
+    }
+    writeln!(writer, "</svg>\n<ul class=\"legend\">")?;
+    for (i, metric) in trend.counts.keys().enumerate() {
+        writeln!(
+            writer,
+            "<li><span style=\"background: {}\"></span>{}</li>",
+            color(i),
+            encode_text(metric)
+        )?;
+    }
+    write!(writer, "</ul>\n<table>\n<tr><th>Metric</th>")?;
+    for (id, started) in &trend.runs {
+        write!(
+            writer,
+            "<th title=\"{}\">Run {id}</th>",
+            encode_double_quoted_attribute(started)
+        )?;
+    }
+    writeln!(writer, "</tr>")?;
+    for (metric, counts) in &trend.counts {
+        write!(
+            writer,
+            "<tr><td class=\"path\">{}</td>",
+            encode_text(metric)
+        )?;
+        for count in counts {
+            write!(writer, "<td>{count}</td>")?;
+        }
+        writeln!(writer, "</tr>")?;
+    }
+    writeln!(
+        writer,
+        "</table>
+</body>
+</html>"
+    )
+}
+
+/// A report listed by the index of an output directory.
+pub(crate) struct IndexEntry {
+    pub(crate) source: String,
+    // Link to the report, relative to the index
+    pub(crate) report: String,
+    pub(crate) diffs: usize,
+    pub(crate) metrics: Vec<String>,
+}
+
+/// The filters of the index, empty to list all the reports.
+#[derive(Default)]
+pub(crate) struct IndexFilter {
+    // Text in the source file name or in a metric name
+    pub(crate) search: String,
+    pub(crate) metric: String,
+    // Start of the source file path, e.g. a directory
+    pub(crate) path: String,
+}
+
+impl IndexFilter {
+    fn matches(&self, entry: &IndexEntry) -> bool {
+        (entry.source.contains(&self.search)
+            || entry
+                .metrics
+                .iter()
+                .any(|metric| metric.contains(&self.search)))
+            && (self.metric.is_empty() || entry.metrics.contains(&self.metric))
+            && entry.source.starts_with(&self.path)
+    }
+}
+
+/// Writes the index of the reports of an output directory, with a form
+/// filtering them by source file and metric, sent back as the query of
+/// the page.
+pub(crate) fn write_index<W: Write>(
+    writer: &mut W,
+    entries: &[IndexEntry],
+    filter: &IndexFilter,
+    theme: Theme,
+) -> std::io::Result<()> {
+    let mut metrics: Vec<&String> = entries.iter().flat_map(|entry| &entry.metrics).collect();
+    metrics.sort();
+    metrics.dedup();
+    let listed: Vec<&IndexEntry> = entries
+        .iter()
+        .filter(|entry| filter.matches(entry))
+        .collect();
+    writeln!(
+        writer,
+        "<!DOCTYPE html>
+<html>
+<head>
+    <meta charset=\"utf-8\">
+    <title>Metric diffs</title>
+    <style>
+{}
+form {{ display: flex; flex-wrap: wrap; gap: 0.5em; margin: 1em 0 }}
+    </style>
+</head>
+<body>
+<h1>Metric diffs</h1>
+<form method=\"get\">
+<input type=\"search\" name=\"q\" placeholder=\"Search\" value=\"{}\">
+<select name=\"metric\">
+<option value=\"\">All metrics</option>",
+        theme.style(),
+        encode_double_quoted_attribute(&filter.search)
+    )?;
+    for metric in metrics {
+        let selected = if *metric == filter.metric {
+            " selected"
+        } else {
+            ""
+        };
+        writeln!(writer, "<option{selected}>{}</option>", encode_text(metric))?;
+    }
+    writeln!(
+        writer,
+        "</select>
+<input type=\"text\" name=\"path\" placeholder=\"Path, e.g. src/\" value=\"{}\">
+<button type=\"submit\">Filter</button>
+</form>
+<p>{} of {} files with diffs</p>
+<table>
+<tr><th>File</th><th>Diffs</th><th>Metrics</th></tr>",
+        encode_double_quoted_attribute(&filter.path),
+        listed.len(),
+        entries.len()
+    )?;
+    for entry in listed {
+        writeln!(
+            writer,
+            "<tr><td class=\"path\"><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td></tr>",
+            encode_double_quoted_attribute(&entry.report),
+            encode_text(&entry.source),
+            entry.diffs,
+            encode_text(&entry.metrics.join(", "))
+        )?;
+    }
+    writeln!(
+        writer,
+        "</table>
+</body>
+</html>"
+    )
+}
+
+/// The report with the minimal markup of the first releases,
+/// for the tools which parse it. Unlike those releases, the paths,
+/// the values and the code are HTML-escaped, e.g. `<` as `&lt;`.
+pub(crate) struct LegacyReport {
+    // The source file, escaped
+    source_file: String,
+    // Whether the heading of the snippets has been written
+    snippets_started: bool,
+}
+
+impl LegacyReport {
+    pub(crate) fn new(source_file: &str) -> Self {
+        Self {
+            source_file: encode_text(source_file).into_owned(),
+            snippets_started: false,
+        }
+    }
+}
+
+impl ReportWriter for LegacyReport {
+    fn write_header(&mut self, writer: &mut dyn Write, report: &Report) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "<!DOCTYPE html>
+<html>
+<head>
+    <title>{}</title>
+</head>
+<body>",
+            report.output_filename
+        )?;
+        if report.snippets.partial {
+            writeln!(
+                writer,
+                "<p><b>Partial report:</b> the metrics have been recovered from a truncated JSON file</p>"
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_global_metrics(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+    ) -> std::io::Result<()> {
+        // Print global metrics
+        writeln!(writer, "<h1>Global Metrics</h1>")?;
+        for SnippetDiff { path, old, new } in &report.snippets.global_metrics {
+            writeln!(
+                writer,
+                "<b>path:</b> {} <br>
+<b>old:</b> {} <br>
+<b>new:</b> {} <br><br>",
+                encode_text(path),
+                encode_text(old),
+                encode_text(new)
+            )?;
+        }
+        if report.snippets.snippets_data.is_empty() {
+            writeln!(writer, "<h2>Code</h2>")?;
+            writeln!(writer, "<pre><i>{}</i></pre>\n", self.source_file)?;
+        }
+        Ok(())
+    }
+
+    fn write_snippet(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+        lines_range: &LinesRange,
+        diffs: &[SnippetDiff],
+    ) -> std::io::Result<()> {
+        if !self.snippets_started {
+            // Print spaces data
+            writeln!(writer, "<h1>Spaces Data</h1>")?;
+            self.snippets_started = true;
+        }
+        writeln!(
+            writer,
+            "<h2>Minimal test - lines ({}, {})</h2>",
+            lines_range.start_line + 1,
+            lines_range.end_line
+        )?;
+        for diff in diffs {
+            writeln!(
+                writer,
+                "<b>path:</b> {}<br>
+<b>old:</b> {}<br>
+<b>new:</b> {}<br><br>",
+                encode_text(&diff.path),
+                encode_text(&diff.old),
+                encode_text(&diff.new)
+            )?;
+        }
+        writeln!(writer, "<h3>Code</h3>")?;
+        let (before, lines, after) =
+            snippet_lines(&self.source_file, lines_range, report.options.context);
+        if !before.is_empty() {
+            writeln!(writer, "<pre class=\"context\">{}</pre>", before.join("\n"))?;
+        }
+        writeln!(writer, "<pre><i>{}</i></pre>\n", lines.join("\n"))?;
+        if !after.is_empty() {
+            writeln!(
+                writer,
+                "<pre class=\"context\">{}</pre>\n",
+                after.join("\n")
+            )?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, writer: &mut dyn Write, _report: &Report) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "</body>
+</html>"
+        )
+    }
+}
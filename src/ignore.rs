@@ -0,0 +1,148 @@
+use serde_json::Value;
+
+use crate::schema::Schema;
+use crate::space::Space;
+
+#[derive(Debug)]
+pub(crate) enum SpaceIgnore {
+    // A JSON pointer to a space, e.g. /spaces/0/spaces/12
+    Pointer(String),
+    // The name of a space, matched at any depth
+    Name(String),
+}
+
+impl SpaceIgnore {
+    pub(crate) fn parse(value: &str) -> Self {
+        if value.starts_with('/') {
+            SpaceIgnore::Pointer(value.trim_end_matches('/').to_owned())
+        } else {
+            SpaceIgnore::Name(value.to_owned())
+        }
+    }
+
+    fn matches(&self, pointer: &str, space: &Value, schema: &Schema) -> bool {
+        match self {
+            SpaceIgnore::Pointer(ignored) => ignored == pointer,
+            SpaceIgnore::Name(name) => Space::new(space, schema).name() == Some(name.as_str()),
+        }
+    }
+}
+
+/// Removes the metrics and the children of the ignored spaces, so they
+/// are neither compared nor used to extract snippets.
+///
+/// The spaces themselves are kept in place to preserve the indices
+/// of their siblings. Returns the JSON pointers of the pruned spaces.
+pub(crate) fn prune_spaces(
+    json: &mut Value,
+    ignores: &[SpaceIgnore],
+    schema: &Schema,
+) -> Vec<String> {
+    let mut pruned = Vec::new();
+    if !ignores.is_empty() {
+        prune_space(json, String::new(), ignores, schema, &mut pruned);
+    }
+    pruned
+}
+
+fn prune_space(
+    space: &mut Value,
+    pointer: String,
+    ignores: &[SpaceIgnore],
+    schema: &Schema,
+    pruned: &mut Vec<String>,
+) {
+    if ignores
+        .iter()
+        .any(|ignore| ignore.matches(&pointer, space, schema))
+    {
+        if let Some(space) = space.as_object_mut() {
+            space.remove(&schema.metrics);
+            space.remove(&schema.spaces);
+        }
+        pruned.push(pointer);
+        return;
+    }
+    if let Some(Value::Array(spaces)) = space.get_mut(&schema.spaces) {
+        for (i, child) in spaces.iter_mut().enumerate() {
+            let pointer = format!("{pointer}/{}/{i}", schema.spaces);
+            prune_space(child, pointer, ignores, schema, pruned);
+        }
+    }
+}
+
+/// A JSON pointer to the sub-trees left out of the comparison, whose
+/// segments can be `*` to match any key or index,
+/// e.g. `/spaces/*/metrics/halstead`.
+#[derive(Debug)]
+pub(crate) struct PathIgnore {
+    segments: Vec<String>,
+}
+
+impl PathIgnore {
+    /// Parses a JSON pointer, unescaping `~1` and `~0` in its segments.
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let pointer = value.strip_prefix('/')?;
+        Some(Self {
+            segments: pointer
+                .split('/')
+                .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+                .collect(),
+        })
+    }
+
+    /// Whether the path of a diff, e.g. `.spaces[0].metrics.nom.total`,
+    /// is inside an ignored sub-tree.
+    pub(crate) fn matches_diff(&self, path: &str) -> bool {
+        let mut segments = Vec::new();
+        for part in path.split('.').skip(1) {
+            let (key, indices) = part.split_once('[').unwrap_or((part, ""));
+            segments.push(key);
+            segments.extend(indices.split(['[', ']']).filter(|index| !index.is_empty()));
+        }
+        segments.len() >= self.segments.len()
+            && self
+                .segments
+                .iter()
+                .zip(segments)
+                .all(|(ignored, segment)| ignored == "*" || ignored == segment)
+    }
+}
+
+/// Removes the sub-trees matched by the ignored paths from a metric
+/// file, so they are not compared. The matched elements of an array
+/// are replaced by null, to preserve the indices of their siblings.
+pub(crate) fn prune_paths(json: &mut Value, ignores: &[PathIgnore]) {
+    for ignore in ignores {
+        prune_path(json, &ignore.segments);
+    }
+}
+
+fn prune_path(value: &mut Value, segments: &[String]) {
+    let Some((segment, rest)) = segments.split_first() else {
+        return;
+    };
+    let matches = |key: &str| segment == "*" || segment == key;
+    match value {
+        Value::Object(object) if rest.is_empty() => object.retain(|key, _| !matches(key)),
+        Value::Object(object) => {
+            for (_, child) in object.iter_mut().filter(|(key, _)| matches(key)) {
+                prune_path(child, rest);
+            }
+        }
+        Value::Array(values) => {
+            for (_, child) in values
+                .iter_mut()
+                .enumerate()
+                .filter(|(i, _)| matches(&i.to_string()))
+            {
+                if rest.is_empty() {
+                    *child = Value::Null;
+                } else {
+                    prune_path(child, rest);
+                }
+            }
+        }
+        _ => {}
+    }
+}
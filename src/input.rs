@@ -0,0 +1,89 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::cbor::parse_cbor;
+use crate::error::ExtractionError;
+use crate::msgpack::parse_msgpack;
+use crate::yaml::parse_yaml;
+
+/// The format of the metric files.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum InputFormat {
+    // Chosen by the extension of each file, JSON unless it is another one
+    #[default]
+    Auto,
+    Json,
+    Yaml,
+    MessagePack,
+    Cbor,
+}
+
+impl InputFormat {
+    pub(crate) const NAMES: &'static [&'static str] = &["auto", "json", "yaml", "msgpack", "cbor"];
+
+    pub(crate) fn parse(name: &str) -> Self {
+        match name {
+            "json" => InputFormat::Json,
+            "yaml" => InputFormat::Yaml,
+            "msgpack" => InputFormat::MessagePack,
+            "cbor" => InputFormat::Cbor,
+            _ => InputFormat::Auto,
+        }
+    }
+
+    /// The extensions of the metric files walked in the directories,
+    /// unless others are given.
+    pub(crate) fn extensions(self) -> &'static [&'static str] {
+        match self {
+            InputFormat::Auto => &["json", "yaml", "yml", "msgpack", "cbor"],
+            InputFormat::Json => &["json"],
+            InputFormat::Yaml => &["yaml", "yml"],
+            InputFormat::MessagePack => &["msgpack"],
+            InputFormat::Cbor => &["cbor"],
+        }
+    }
+
+    /// The format of a metric file.
+    pub(crate) fn of_file(self, path: &Path) -> Self {
+        match self {
+            InputFormat::Auto => match path.extension().and_then(OsStr::to_str) {
+                Some("yaml" | "yml") => InputFormat::Yaml,
+                Some("msgpack") => InputFormat::MessagePack,
+                Some("cbor") => InputFormat::Cbor,
+                _ => InputFormat::Json,
+            },
+            format => format,
+        }
+    }
+}
+
+/// Parses the content of a metric file in its format.
+pub(crate) fn parse_metrics(
+    buffer: &[u8],
+    format: InputFormat,
+    path: &Path,
+) -> Result<Value, ExtractionError> {
+    let (result, format) = match format.of_file(path) {
+        InputFormat::Yaml => (
+            std::str::from_utf8(buffer)
+                .map_err(|err| err.to_string())
+                .and_then(|text| parse_yaml(text).map_err(|err| err.to_string())),
+            "YAML",
+        ),
+        InputFormat::MessagePack => (parse_msgpack(buffer), "MessagePack"),
+        InputFormat::Cbor => (parse_cbor(buffer), "CBOR"),
+        _ => {
+            return serde_json::from_slice(buffer).map_err(|source| ExtractionError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })
+        }
+    };
+    result.map_err(|message| ExtractionError::Decode {
+        path: path.to_path_buf(),
+        format,
+        message,
+    })
+}
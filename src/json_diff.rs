@@ -0,0 +1,108 @@
+use serde_json::Value;
+
+use crate::SnippetDiff;
+
+/// The value of a diff on the side where its key is missing.
+pub const ABSENT: &str = "<absent>";
+
+/// Compares two JSON values as assert_json_diff does in strict mode,
+/// returning the atoms which differ with their path,
+/// e.g. `.spaces[0].metrics.cyclomatic.sum`.
+///
+/// The values are only borrowed, so comparing two large metric files
+/// does not need a copy of them. As in the text output of assert_json_diff,
+/// the atoms missing from one side are skipped, unless `report_missing`
+/// is set, as well as the values spanning more than one line, like
+/// an object replaced by a scalar.
+pub fn json_diffs(lhs: &Value, rhs: &Value, report_missing: bool) -> Vec<SnippetDiff> {
+    let mut diffs = Vec::new();
+    let mut path = String::new();
+    diff_values(lhs, rhs, report_missing, &mut path, &mut diffs);
+    diffs
+}
+
+/// Adds a diff for each atom of an object key missing from one side,
+/// with [`ABSENT`] as its value on that side. The arrays are skipped,
+/// since an array of spaces is a change of the spaces, not of the metrics.
+fn missing_values(value: &Value, is_old: bool, path: &mut String, diffs: &mut Vec<SnippetDiff>) {
+    match value {
+        Value::Object(object) => {
+            let len = path.len();
+            for (key, value) in object {
+                path.push('.');
+                path.push_str(key);
+                missing_values(value, is_old, path, diffs);
+                path.truncate(len);
+            }
+        }
+        Value::Array(_) => {}
+        _ => {
+            let value = value.to_string();
+            let (old, new) = if is_old {
+                (value, ABSENT.to_owned())
+            } else {
+                (ABSENT.to_owned(), value)
+            };
+            diffs.push(SnippetDiff {
+                path: path.clone(),
+                old,
+                new,
+            });
+        }
+    }
+}
+
+fn diff_values(
+    lhs: &Value,
+    rhs: &Value,
+    report_missing: bool,
+    path: &mut String,
+    diffs: &mut Vec<SnippetDiff>,
+) {
+    if lhs == rhs {
+        return;
+    }
+    let len = path.len();
+    match (lhs, rhs) {
+        (Value::Object(lhs), Value::Object(rhs)) => {
+            for (key, lhs) in lhs {
+                path.push('.');
+                path.push_str(key);
+                if let Some(rhs) = rhs.get(key) {
+                    diff_values(lhs, rhs, report_missing, path, diffs);
+                } else if report_missing {
+                    missing_values(lhs, true, path, diffs);
+                }
+                path.truncate(len);
+            }
+            if report_missing {
+                for (key, rhs) in rhs {
+                    if !lhs.contains_key(key) {
+                        path.push('.');
+                        path.push_str(key);
+                        missing_values(rhs, false, path, diffs);
+                        path.truncate(len);
+                    }
+                }
+            }
+        }
+        (Value::Array(lhs), Value::Array(rhs)) => {
+            for (i, (lhs, rhs)) in lhs.iter().zip(rhs).enumerate() {
+                path.push_str(&format!("[{i}]"));
+                diff_values(lhs, rhs, report_missing, path, diffs);
+                path.truncate(len);
+            }
+        }
+        _ => {
+            let old = serde_json::to_string_pretty(lhs).unwrap();
+            let new = serde_json::to_string_pretty(rhs).unwrap();
+            if !old.contains('\n') && !new.contains('\n') {
+                diffs.push(SnippetDiff {
+                    path: path.clone(),
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::io::Write;
+
+use serde_json::{json, Map, Value};
+
+use crate::fingerprint::fingerprint;
+use crate::html::{diff_severity, snippet_lines};
+use crate::report::{sorted_snippets, Report, ReportWriter};
+use crate::{LinesRange, Options, SnippetDiff};
+
+/// The report as a JSON object, to be read by other tools.
+#[derive(Default)]
+pub(crate) struct JsonReport {
+    report: Map<String, Value>,
+    snippets: Vec<Value>,
+}
+
+fn diffs_json(source_filename: &str, diffs: &[SnippetDiff], options: &Options) -> Value {
+    diffs
+        .iter()
+        .map(|diff| {
+            json!({
+                "path": diff.path,
+                "old": diff.old,
+                "new": diff.new,
+                "change": diff.delta(),
+                "severity": diff_severity(diff, options).map(|severity| severity.name()),
+                "fingerprint": fingerprint(source_filename, diff, &options.schema),
+            })
+        })
+        .collect()
+}
+
+fn snippet_json(report: &Report, lines_range: &LinesRange, diffs: &[SnippetDiff]) -> Value {
+    let (_, lines, _) = snippet_lines(report.source_file, lines_range, 0);
+    json!({
+        "start_line": lines_range.start_line + 1,
+        "end_line": lines_range.end_line,
+        "diffs": diffs_json(&report.snippets.source_filename, diffs, report.options),
+        "code": lines.join("\n"),
+    })
+}
+
+impl ReportWriter for JsonReport {
+    fn write_header(&mut self, _writer: &mut dyn Write, report: &Report) -> std::io::Result<()> {
+        let snippets = report.snippets;
+        self.report
+            .insert("source".to_owned(), json!(snippets.source_filename));
+        self.report
+            .insert("partial".to_owned(), json!(snippets.partial));
+        self.report.insert("global_metrics".to_owned(), json!([]));
+        if let Some((shift, moved)) = snippets.line_shift {
+            self.report.insert(
+                "line_shift".to_owned(),
+                json!({ "shift": shift, "spaces": moved }),
+            );
+        }
+        if snippets.omitted_snippets > 0 {
+            self.report.insert(
+                "omitted_snippets".to_owned(),
+                json!(snippets.omitted_snippets),
+            );
+        }
+        Ok(())
+    }
+
+    fn write_global_metrics(
+        &mut self,
+        _writer: &mut dyn Write,
+        report: &Report,
+    ) -> std::io::Result<()> {
+        self.report.insert(
+            "global_metrics".to_owned(),
+            diffs_json(
+                &report.snippets.source_filename,
+                &report.snippets.global_metrics,
+                report.options,
+            ),
+        );
+        Ok(())
+    }
+
+    fn write_snippet(
+        &mut self,
+        _writer: &mut dyn Write,
+        report: &Report,
+        lines_range: &LinesRange,
+        diffs: &[SnippetDiff],
+    ) -> std::io::Result<()> {
+        self.snippets.push(snippet_json(report, lines_range, diffs));
+        Ok(())
+    }
+
+    /// Writes the whole object, since the snippets are inside it.
+    fn finish(&mut self, writer: &mut dyn Write, report: &Report) -> std::io::Result<()> {
+        let report = self.take_report(report);
+        writeln!(writer, "{}", serde_json::to_string_pretty(&report)?)
+    }
+}
+
+impl JsonReport {
+    /// Completes the object of the report with the sections following the
+    /// snippets and returns it, for the formats written from it.
+    pub(crate) fn take_report(&mut self, report: &Report) -> Value {
+        let (snippets, options) = (report.snippets, report.options);
+        self.report.insert(
+            "snippets".to_owned(),
+            Value::Array(std::mem::take(&mut self.snippets)),
+        );
+        self.report.insert(
+            "space_changes".to_owned(),
+            diffs_json(&snippets.source_filename, &snippets.space_changes, options),
+        );
+        self.report.insert(
+            "positions".to_owned(),
+            sorted_snippets(&snippets.positions, options)
+                .into_iter()
+                .map(|(lines_range, diffs)| snippet_json(report, lines_range, diffs))
+                .collect(),
+        );
+        self.report.insert(
+            "filtered".to_owned(),
+            snippets
+                .filtered
+                .iter()
+                .map(|filtered| {
+                    json!({
+                        "path": filtered.diff.path,
+                        "old": filtered.diff.old,
+                        "new": filtered.diff.new,
+                        "rule": filtered.rule.to_string(),
+                    })
+                })
+                .collect(),
+        );
+        Value::Object(std::mem::take(&mut self.report))
+    }
+}
@@ -0,0 +1,160 @@
+use std::io::Write;
+
+use html_escape::{encode_double_quoted_attribute, encode_text};
+
+use crate::fingerprint::fingerprint;
+use crate::html::snippet_lines;
+use crate::schema::Schema;
+use crate::summary::{format_change, PairStatus, Summary};
+use crate::{CodeSnippets, SnippetDiff};
+
+/// Name of the JUnit report written in the output directory.
+pub(crate) const JUNIT_NAME: &str = "junit.xml";
+
+// Name of the test suite and of the class of its test cases
+const SUITE_NAME: &str = "json-minimal-tests";
+
+fn write_diffs<W: Write>(
+    writer: &mut W,
+    snippets: &CodeSnippets,
+    diffs: &[SnippetDiff],
+    precision: usize,
+    schema: &Schema,
+) -> std::io::Result<()> {
+    for diff in diffs {
+        write!(
+            writer,
+            "{}: {} -> {}",
+            encode_text(&diff.path),
+            encode_text(&diff.old),
+            encode_text(&diff.new)
+        )?;
+        if let Some(change) = format_change(diff, precision) {
+            write!(writer, ", {change}")?;
+        }
+        writeln!(
+            writer,
+            " [{}]",
+            fingerprint(&snippets.source_filename, diff, schema)
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the failure message of a pair with diffs, listing the diffs of
+/// each snippet followed by its code. The source file is already escaped.
+pub(crate) fn write_failure<W: Write>(
+    writer: &mut W,
+    source_file: &str,
+    snippets: &CodeSnippets,
+    context: usize,
+    precision: usize,
+    schema: &Schema,
+) -> std::io::Result<()> {
+    writeln!(writer, "{}", encode_text(&snippets.source_filename))?;
+    if let Some((shift, moved)) = snippets.line_shift {
+        writeln!(
+            writer,
+            "\nGlobal line shift detected: the lines of {moved} spaces moved by {shift:+}"
+        )?;
+    }
+    if snippets.omitted_snippets > 0 {
+        writeln!(
+            writer,
+            "\nMinimal tests left out: … and {} more",
+            snippets.omitted_snippets
+        )?;
+    }
+    if !snippets.global_metrics.is_empty() {
+        writeln!(writer, "\nGlobal metrics")?;
+        write_diffs(
+            writer,
+            snippets,
+            &snippets.global_metrics,
+            precision,
+            schema,
+        )?;
+    }
+    if !snippets.space_changes.is_empty() {
+        writeln!(writer, "\nAdded and removed spaces")?;
+        write_diffs(writer, snippets, &snippets.space_changes, precision, schema)?;
+    }
+    for (title, snippets_data) in [
+        ("Minimal test", &snippets.snippets_data),
+        ("Moved space", &snippets.positions),
+    ] {
+        let mut snippets_data: Vec<_> = snippets_data.iter().collect();
+        snippets_data
+            .sort_by_key(|(lines_range, _)| (lines_range.start_line, lines_range.end_line));
+        for (lines_range, diffs) in snippets_data {
+            writeln!(
+                writer,
+                "\n{title} - lines ({}, {})",
+                lines_range.start_line + 1,
+                lines_range.end_line
+            )?;
+            write_diffs(writer, snippets, diffs, precision, schema)?;
+            let (before, lines, after) = snippet_lines(source_file, lines_range, context);
+            for line in before.iter().chain(&lines).chain(&after) {
+                writeln!(writer, "    {line}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes a test suite where each compared pair is a test case, which
+/// fails when the pair has diffs and errs when it cannot be compared.
+pub(crate) fn write_junit<W: Write>(writer: &mut W, summary: &Summary) -> std::io::Result<()> {
+    let is_failure =
+        |status: PairStatus| matches!(status, PairStatus::Reported | PairStatus::SourceMissing);
+    let errors = summary
+        .pairs
+        .iter()
+        .filter(|(_, status)| status.is_error())
+        .count();
+    let failures = summary
+        .pairs
+        .iter()
+        .filter(|(_, status)| is_failure(*status))
+        .count();
+
+    writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        writer,
+        "<testsuites name=\"{SUITE_NAME}\" tests=\"{}\" failures=\"{failures}\" errors=\"{errors}\">",
+        summary.pairs.len()
+    )?;
+    writeln!(
+        writer,
+        "<testsuite name=\"{SUITE_NAME}\" tests=\"{}\" failures=\"{failures}\" errors=\"{errors}\">",
+        summary.pairs.len()
+    )?;
+    let mut pairs: Vec<&(String, PairStatus)> = summary.pairs.iter().collect();
+    pairs.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, status) in pairs {
+        write!(
+            writer,
+            "<testcase classname=\"{SUITE_NAME}\" name=\"{}\"",
+            encode_double_quoted_attribute(name)
+        )?;
+        if status.is_error() {
+            writeln!(
+                writer,
+                ">\n<error message=\"{}\" type=\"{}\"/>\n</testcase>",
+                status.name(),
+                status.name()
+            )?;
+        } else if is_failure(*status) {
+            let message = summary.failures.get(name).map_or("", String::as_str);
+            writeln!(
+                writer,
+                ">\n<failure message=\"{}\" type=\"metric-diff\">{message}</failure>\n</testcase>",
+                status.name()
+            )?;
+        } else {
+            writeln!(writer, "/>")?;
+        }
+    }
+    writeln!(writer, "</testsuite>\n</testsuites>")
+}
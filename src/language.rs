@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+/// Returns the language of a source file from its extension,
+/// among the ones supported by rust-code-analysis.
+pub(crate) fn language(source_filename: &str) -> String {
+    let extension = Path::new(source_filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("");
+    match extension {
+        "rs" => "Rust",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" | "inc" | "mm" | "m" => "C++",
+        "py" => "Python",
+        "js" | "mjs" | "jsm" | "jsx" => "JavaScript",
+        "ts" => "TypeScript",
+        "tsx" => "TSX",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "" => "unknown",
+        extension => return extension.to_owned(),
+    }
+    .to_owned()
+}
+
+/// Returns the language of a metric file, from its `language` field when
+/// the tool which wrote it sets one, or else from the extension of its
+/// source file.
+pub(crate) fn metric_language(json: &Value, source_filename: &str) -> String {
+    let Some(name) = json.get("language").and_then(Value::as_str) else {
+        return language(source_filename);
+    };
+    match name.to_ascii_lowercase().as_str() {
+        "rust" => "Rust",
+        "c" => "C",
+        "cpp" | "c++" | "ccomment" | "preproc" => "C++",
+        "python" => "Python",
+        "javascript" | "mozjs" => "JavaScript",
+        "typescript" => "TypeScript",
+        "tsx" => "TSX",
+        "java" => "Java",
+        "kotlin" => "Kotlin",
+        _ => name,
+    }
+    .to_owned()
+}
@@ -0,0 +1,52 @@
+//! The comparison of metric files behind json-minimal-tests, for the
+//! applications which embed it rather than running the command.
+//!
+//! A [`Comparator`] is configured with its builder methods and returns
+//! the [`SnippetDiff`]s between two JSON values, laid out as the metric
+//! files of rust-code-analysis or as described by a [`Schema`].
+
+mod comparator;
+pub mod glob;
+mod json_diff;
+pub mod schema;
+
+pub use comparator::Comparator;
+pub use json_diff::{json_diffs, ABSENT};
+pub use schema::Schema;
+
+/// A value which differs between two metric files, at a path like
+/// `.spaces[0].metrics.cyclomatic.sum`. The values are JSON text,
+/// or [`ABSENT`] on the side where the key is missing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnippetDiff {
+    pub path: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl SnippetDiff {
+    /// The name of the metric, the part of the path after `.metrics.`,
+    /// e.g. `cyclomatic.sum`, or the whole path outside the metrics.
+    pub fn metric_name(&self) -> &str {
+        self.metric_name_after("metrics")
+    }
+
+    /// The name of the metric in a file with the layout of `schema`,
+    /// the part of the path after its metrics key.
+    pub fn metric_name_in(&self, schema: &Schema) -> &str {
+        self.metric_name_after(&schema.metrics)
+    }
+
+    fn metric_name_after(&self, metrics: &str) -> &str {
+        let metrics = format!(".{metrics}.");
+        match self.path.rfind(&metrics) {
+            Some(start) => &self.path[start + metrics.len()..],
+            None => self.path.trim_start_matches('.'),
+        }
+    }
+
+    /// The new value minus the old one, when both are numbers.
+    pub fn delta(&self) -> Option<f64> {
+        Some(self.new.parse::<f64>().ok()? - self.old.parse::<f64>().ok()?)
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::schema::Schema;
+use crate::space::Space;
+
+type SpaceKey<'a> = (Option<&'a str>, Option<&'a str>);
+
+// The start lines of the spaces below the root by name and kind,
+// in the order they are found
+fn start_lines<'a>(space: Space<'a>, lines: &mut HashMap<SpaceKey<'a>, Vec<usize>>) {
+    for child in space.spaces().into_iter().flatten() {
+        if let Some(start_line) = child.start_line() {
+            lines
+                .entry((child.name(), child.kind()))
+                .or_default()
+                .push(start_line);
+        }
+        start_lines(child, lines);
+    }
+}
+
+/// Detects the lines of the spaces shifted by a constant between two
+/// metric files, e.g. when a grammar changes how it handles a BOM.
+///
+/// The spaces with the same name and kind are paired in order, and the
+/// shift is the most common difference of their start lines, when it is
+/// not zero and more than half of the pairs, at least two, have it.
+pub(crate) fn detect_line_shift(old: &Value, new: &Value, schema: &Schema) -> Option<i64> {
+    let (mut old_lines, mut new_lines) = (HashMap::new(), HashMap::new());
+    start_lines(Space::new(old, schema), &mut old_lines);
+    start_lines(Space::new(new, schema), &mut new_lines);
+
+    let mut shifts: HashMap<i64, usize> = HashMap::new();
+    let mut pairs = 0;
+    for (key, old_lines) in &old_lines {
+        let Some(new_lines) = new_lines.get(key) else {
+            continue;
+        };
+        for (old_line, new_line) in old_lines.iter().zip(new_lines) {
+            *shifts
+                .entry(*new_line as i64 - *old_line as i64)
+                .or_default() += 1;
+            pairs += 1;
+        }
+    }
+    let (shift, count) = shifts
+        .into_iter()
+        .max_by_key(|(shift, count)| (*count, -shift.abs()))?;
+    // A single moved space is not a shift of the file
+    (shift != 0 && count > 1 && count * 2 > pairs).then_some(shift)
+}
+
+/// Moves the lines of the old spaces by the shift wherever the space at
+/// the same place in the new metric file has them moved by it, so that
+/// only the other moves are diffs. Returns the number of moved spaces.
+pub(crate) fn apply_line_shift(old: &mut Value, new: &Value, shift: i64, schema: &Schema) -> usize {
+    let mut moved = 0;
+    let (Some(Value::Array(old_spaces)), Some(Value::Array(new_spaces))) =
+        (old.get_mut(&schema.spaces), new.get(&schema.spaces))
+    else {
+        return moved;
+    };
+    for (old, new) in old_spaces.iter_mut().zip(new_spaces) {
+        let mut is_moved = false;
+        for field in [&schema.start_line, &schema.end_line] {
+            let (Some(old_line), Some(new_line)) = (old.get_mut(field), new.get(field)) else {
+                continue;
+            };
+            if new_line.as_i64().is_some()
+                && old_line.as_i64().map(|line| line + shift) == new_line.as_i64()
+            {
+                *old_line = new_line.clone();
+                is_moved = true;
+            }
+        }
+        moved += usize::from(is_moved) + apply_line_shift(old, new, shift, schema);
+    }
+    moved
+}
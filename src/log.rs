@@ -0,0 +1,135 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::Duration;
+
+use serde_json::json;
+
+use crate::SnippetDiff;
+
+/// How much is printed while comparing, set once with -q, -v or -vv.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub(crate) enum Level {
+    // Only the errors and the warnings
+    Quiet,
+    // The names of the source files with diffs too
+    Normal,
+    // The pairing decisions, the skipped files and the encoding fallbacks
+    Verbose,
+    // The time spent on each pair
+    Timing,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Normal as u8);
+// The events are printed as JSON objects, with --log-format json
+static JSON: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub(crate) fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+/// Whether the messages of a level are printed as text, which they are
+/// not when the events are printed as JSON.
+pub(crate) fn enabled(level: Level) -> bool {
+    LEVEL.load(Ordering::Relaxed) >= level as u8 && !JSON.load(Ordering::Relaxed)
+}
+
+/// Prints a message on stderr with -v.
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::log::Level::Verbose) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use verbose;
+
+/// Something which happened to a pair while comparing.
+pub(crate) enum Event<'a> {
+    Paired {
+        path1: &'a Path,
+        path2: &'a Path,
+    },
+    Skipped {
+        name: &'a str,
+        reason: &'a str,
+    },
+    Diff {
+        source: &'a str,
+        diff: &'a SnippetDiff,
+    },
+    Compared {
+        name: &'a str,
+        status: &'a str,
+        elapsed: Duration,
+    },
+    Error {
+        name: &'a str,
+        // What the text message starts with, e.g. the two files of the pair
+        context: &'a str,
+        message: String,
+    },
+}
+
+/// Prints an event on stderr: as a JSON object on its own line with
+/// --log-format json, otherwise as text for the levels showing it.
+/// A diff is only printed as JSON, since its report has it.
+pub(crate) fn log(event: Event) {
+    if !JSON.load(Ordering::Relaxed) {
+        match event {
+            Event::Paired { path1, path2 } => {
+                verbose!("{}: paired with {}", path2.display(), path1.display())
+            }
+            Event::Skipped { name, reason } => verbose!("{name}: {reason}"),
+            Event::Diff { .. } => {}
+            Event::Compared {
+                name,
+                status,
+                elapsed,
+            } => {
+                if enabled(Level::Timing) {
+                    eprintln!("{name}: {status} in {elapsed:.1?}");
+                }
+            }
+            Event::Error {
+                context, message, ..
+            } => eprintln!("{context}: {message}"),
+        }
+        return;
+    }
+    let json = match event {
+        Event::Paired { path1, path2 } => json!({
+            "event": "paired",
+            "old": path1.display().to_string(),
+            "new": path2.display().to_string(),
+        }),
+        Event::Skipped { name, reason } => {
+            json!({ "event": "skipped", "name": name, "reason": reason })
+        }
+        Event::Diff { source, diff } => json!({
+            "event": "diff",
+            "source": source,
+            "path": diff.path,
+            "old": diff.old,
+            "new": diff.new,
+        }),
+        Event::Compared {
+            name,
+            status,
+            elapsed,
+        } => json!({
+            "event": "compared",
+            "name": name,
+            "status": status,
+            "seconds": elapsed.as_secs_f64(),
+        }),
+        Event::Error { name, message, .. } => {
+            json!({ "event": "error", "name": name, "message": message })
+        }
+    };
+    eprintln!("{json}");
+}
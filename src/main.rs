@@ -1,31 +1,141 @@
 #[macro_use]
 extern crate clap;
 
+mod align;
+mod atomic;
+mod baseline;
+mod binary;
+mod budget;
+mod by_metric;
+mod cache;
+mod cbor;
+mod changed;
+mod check;
+mod compare;
+mod config;
+mod csv;
+mod diff;
+mod diff_log;
+mod diff_results;
+mod enclose;
+mod error;
+mod filter;
+mod fingerprint;
+mod fixtures;
+mod gitignore;
+mod grammar_diff;
+mod html;
+mod ignore;
+mod input;
+mod json_report;
+mod junit;
+mod language;
+mod line_shift;
+mod log;
+mod manifest;
+mod markdown;
+mod merge;
+mod metrics_file;
+mod mmap;
+mod msgpack;
+mod name_pairs;
+mod narrow;
+mod ndjson;
 mod non_utf8;
+mod notify;
+mod pr_comment;
+mod prescan;
+mod repair;
+mod report;
+mod results_db;
+mod review;
+mod serve;
+mod severity;
+mod sha256;
+mod sources;
+mod space;
+mod stale;
+mod stream;
+mod summary;
+mod toml_report;
+mod top;
+mod validate;
+mod walk;
+mod watch;
+mod wrap;
+mod yaml;
 
-use std::collections::HashMap;
-use std::fs::File;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::io::Write;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
 use std::{process, thread};
 
-use assert_json_diff::{assert_json_matches_no_panic, CompareMode, Config};
-use clap::{App, Arg};
-use crossbeam::channel::{unbounded, Receiver, Sender};
-use regex::Regex;
+use clap::{App, AppSettings, Arg, ArgMatches, Shell, SubCommand};
+use crossbeam::channel::{bounded, Receiver, Sender};
+use json_minimal_tests::glob::{self, Glob};
+use json_minimal_tests::schema::{self, Schema};
+use json_minimal_tests::SnippetDiff;
 use serde_json::Value;
-use walkdir::{DirEntry, WalkDir};
 
-use non_utf8::{encode_to_utf8, read_file_with_eol};
+use align::SpaceMatching;
+use atomic::write_atomically;
+use baseline::Baseline;
+use budget::{fit_budget, ByteCounter};
+use by_metric::{write_by_metric, BY_METRIC_NAME};
+use cache::Cache;
+use changed::{changed_files, is_changed, is_source_changed};
+use check::CheckCommand;
+use compare::CompareRules;
+use config::{is_long_option_given, long_option_value, Config, Profile, CONFIG_NAME};
+use csv::CsvReport;
+use diff::{get_code_snippets, snippets_from_diffs};
+use diff_log::parse_diff_log;
+use diff_results::{diff_results, ResultSet};
+use enclose::enclose_snippets;
+use error::ExtractionError;
+use filter::{DefaultIgnores, FilterRule, FilteredDiff};
+use fingerprint::{fingerprint, Suppressions};
+use fixtures::{expected_metrics, write_fixtures};
+use grammar_diff::{GrammarDiff, GRAMMAR_DIFF_NAME};
+use html::{snippet_lines, HtmlReport, LegacyReport, Theme};
+use ignore::{prune_spaces, PathIgnore, SpaceIgnore};
+use input::{parse_metrics, InputFormat};
+use json_report::JsonReport;
+use junit::{write_junit, JUNIT_NAME};
+use log::{verbose, Event, Level};
+use manifest::{clean, write_manifest, write_run_manifest, Run};
+use markdown::MarkdownReport;
+use mmap::read_file;
+use name_pairs::pair_by_name;
+use ndjson::{document_name, for_each_document};
+use non_utf8::{encode_to_utf8, read_file_with_eol, Encodings};
+use notify::{notification, post_webhook};
+use pr_comment::write_pr_comment;
+use prescan::prescan;
+use repair::repair_truncated;
+use report::{Report, ReportWriter};
+use results_db::{db_diffs, read_trend, record_run, write_trend_csv};
+use review::{review_tests, ReviewFile};
+use serve::serve;
+use severity::{Severity, SeverityRules};
+use sha256::sha256_hex;
+use sources::SourceTrees;
+use space::Space;
+use stale::{read_sidecar, stale_ranges};
+use stream::parse_against;
+use summary::{metric_name, read_total_diffs, PairStatus, Summary};
+use toml_report::TomlReport;
+use top::{keep_largest_snippets, keep_top, DeferredReport};
+use validate::validate;
+use walk::{parallel_walk, walk_files, WalkOptions};
+use watch::{Watcher, WATCH_INTERVAL};
 
-#[derive(Clone, Debug)]
-struct SnippetDiff {
-    path: String,
-    old: String,
-    new: String,
-}
-
-#[derive(Hash, Eq, PartialEq, Debug)]
+#[derive(Clone, Hash, Eq, PartialEq, Debug)]
 struct LinesRange {
     start_line: usize,
     end_line: usize,
@@ -33,138 +143,396 @@ struct LinesRange {
 
 struct CodeSnippets {
     source_filename: String,
+    // From the metric file or the extension of the source file
+    language: String,
+    // Extracted from a truncated JSON file which has been repaired
+    partial: bool,
     global_metrics: Vec<SnippetDiff>,
     snippets_data: HashMap<LinesRange, Vec<SnippetDiff>>,
+    // Start and end line diffs, by the lines of their space,
+    // with --report-positions
+    positions: HashMap<LinesRange, Vec<SnippetDiff>>,
+    // Spaces found in only one of the metric files
+    space_changes: Vec<SnippetDiff>,
+    // Lines shifted by the same amount in most spaces, not reported as
+    // diffs, and the number of spaces moved by it
+    line_shift: Option<(i64, usize)>,
+    // Snippets left out with --max-snippets-per-file
+    omitted_snippets: usize,
+    // Diffs left out of the report and the rule which dropped them
+    filtered: Vec<FilteredDiff>,
+    // JSON pointers of the spaces excluded with --ignore-space
+    ignored_spaces: Vec<String>,
+    // Expected hashes of the source lines of the spaces
+    source_hashes: HashMap<LinesRange, String>,
+    // Line ranges whose source does not match the expected hash
+    stale: Vec<LinesRange>,
+    // Whether each snippet compiles on its own, when checked
+    checked: HashMap<LinesRange, bool>,
+    // Name of the old source file, in the old metric file
+    old_source_filename: Option<String>,
+    // Lines of the old version of each snippet
+    old_ranges: HashMap<LinesRange, LinesRange>,
+    // Whether the code of each snippet differs from its old version,
+    // when the old source is available
+    code_changed: HashMap<LinesRange, bool>,
+    // The new spaces of the snippets, by their lines, for their expected
+    // metrics with --emit-fixtures or --interactive
+    fixture_spaces: HashMap<LinesRange, metrics_file::Space>,
 }
 
-struct JobItem {
-    path1: PathBuf,
-    path2: PathBuf,
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Format {
+    #[default]
+    Html,
+    HtmlLegacy,
+    // A single JUnit report for all the pairs
+    Junit,
+    Json,
+    Csv,
+    Markdown,
+    Toml,
+}
+
+impl Format {
+    const NAMES: &'static [&'static str] = &[
+        "html",
+        "html-legacy",
+        "junit",
+        "json",
+        "csv",
+        "markdown",
+        "toml",
+    ];
+
+    fn parse(name: &str) -> Self {
+        match name {
+            "html-legacy" => Format::HtmlLegacy,
+            "junit" => Format::Junit,
+            "json" => Format::Json,
+            "csv" => Format::Csv,
+            "markdown" => Format::Markdown,
+            "toml" => Format::Toml,
+            _ => Format::Html,
+        }
+    }
+
+    /// The extension of the report files.
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Html | Format::HtmlLegacy | Format::Junit => "html",
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Markdown => "md",
+            Format::Toml => "toml",
+        }
+    }
+
+    fn is_html(self) -> bool {
+        matches!(self, Format::Html | Format::HtmlLegacy)
+    }
+}
+
+/// How the report of a source file is named in the output directory.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Naming {
+    // The components of the source path joined with underscores
+    #[default]
+    Flat,
+    // The flat name followed by a hash of the source path
+    Hash,
+    // The source path itself, in subdirectories of the output directory
+    Mirror,
+}
+
+impl Naming {
+    const NAMES: &'static [&'static str] = &["flat", "hash", "mirror"];
+
+    fn parse(name: &str) -> Self {
+        match name {
+            "hash" => Naming::Hash,
+            "mirror" => Naming::Mirror,
+            _ => Naming::Flat,
+        }
+    }
+}
+
+/// How the metric files of two directories are paired.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum PairBy {
+    // The same path relative to the directories
+    #[default]
+    Path,
+    // The same name of the root space, i.e. the same source file
+    Name,
+}
+
+impl PairBy {
+    const NAMES: &'static [&'static str] = &["path", "name"];
+
+    fn parse(name: &str) -> Self {
+        match name {
+            "name" => PairBy::Name,
+            _ => PairBy::Path,
+        }
+    }
+}
+
+/// How the diffs of the reports are grouped.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum GroupBy {
+    // A report for each source file
+    #[default]
+    File,
+    // A single report with a section for each metric
+    Metric,
+}
+
+impl GroupBy {
+    const NAMES: &'static [&'static str] = &["file", "metric"];
+
+    fn parse(name: &str) -> Self {
+        match name {
+            "metric" => GroupBy::Metric,
+            _ => GroupBy::File,
+        }
+    }
+}
+
+/// What is done with a report already in the output directory.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum OnExisting {
+    #[default]
+    Overwrite,
+    // The report of the previous run is kept
+    Skip,
+    // The pair fails
+    Error,
+    // The report gets the next free numbered name, e.g. a.rs-2.html
+    Version,
+}
+
+impl OnExisting {
+    const NAMES: &'static [&'static str] = &["overwrite", "skip", "error", "version"];
+
+    fn parse(name: &str) -> Self {
+        match name {
+            "skip" => OnExisting::Skip,
+            "error" => OnExisting::Error,
+            "version" => OnExisting::Version,
+            _ => OnExisting::Overwrite,
+        }
+    }
+}
+
+/// Which snippets `--top` keeps.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum SortBy {
+    // The largest absolute change of a metric
+    #[default]
+    Delta,
+    // The most diffs
+    Count,
+}
+
+impl SortBy {
+    const NAMES: &'static [&'static str] = &["delta", "count"];
+
+    fn parse(name: &str) -> Self {
+        match name {
+            "count" => SortBy::Count,
+            _ => SortBy::Delta,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Options {
     output_path: Option<PathBuf>,
+    // A page gathering the reports of all the source files
+    single_report: Option<PathBuf>,
+    format: Format,
+    theme: Theme,
+    naming: Naming,
+    // What is done with the reports of a previous run
+    on_existing: OnExisting,
+    pair_by: PairBy,
+    group_by: GroupBy,
+    // Report only this number of snippets, the top ones of the run
+    top: Option<usize>,
+    sort_by: SortBy,
+    // Report only this number of snippets of each source file
+    max_snippets_per_file: Option<usize>,
+    // Bytes of all the reports, over which the smallest changes are left out
+    max_output: Option<u64>,
+    // SQLite database the diffs of the run are appended to
+    results_db: Option<PathBuf>,
+    validate: bool,
+    ignored_spaces: Vec<SpaceIgnore>,
+    // Sub-trees left out of the comparison
+    ignored_paths: Vec<PathIgnore>,
+    // How the values of each metric are compared
+    compare_rules: Option<CompareRules>,
+    // How the children of two spaces are matched
+    space_matching: SpaceMatching,
+    default_ignores: DefaultIgnores,
+    // Report the start and end line diffs in their own section
+    report_positions: bool,
+    // Report the keys missing from one of the metric files
+    report_missing: bool,
+    // Only report the diffs of the spaces with these kinds, all if empty
+    space_kinds: Vec<String>,
+    // Narrow the snippets to the deepest changed child spaces
+    minimize: bool,
+    // Snippets longer than this are narrowed to their changed child spaces
+    max_snippet_lines: Option<usize>,
+    // Lines of code shown before and after each snippet
+    context: usize,
+    // Decimals of the changes of the numeric diffs
+    precision: usize,
+    // Mark the first and the last line of each snippet
+    highlight_bounds: bool,
+    // Maximum number of lines between two snippets which are merged
+    merge_distance: Option<usize>,
+    // Globs matched against the source file name
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+    // The format of the metric files, by their extension if auto
+    input_format: InputFormat,
+    // What the walks of the compared directories skip
+    walk: WalkOptions,
+    // Sources changed since a given revision, relative to the repository root
+    changed_files: Option<Vec<PathBuf>>,
+    ndjson: bool,
+    // Salvage the complete part of truncated JSON files
+    repair_truncated: bool,
+    // Map the metric files in memory rather than reading them
+    mmap: bool,
+    // Stop at the first pair which cannot be compared
+    fail_fast: bool,
+    // Skip the pairs of identical files before parsing them
+    prescan: bool,
+    // Only count identical and different pairs
+    count: bool,
+    // Skip the pairs compared in a previous run, if unchanged
+    cache: Option<Cache>,
+    // Directories of the relative new and old source file names
+    source_root: Option<PathBuf>,
+    old_source_root: Option<PathBuf>,
+    // Encodings tried on the source files which are not UTF-8
+    encodings: Encodings,
+    // Only report the snippets whose code is the same in the old sources
+    only_unchanged_code: bool,
+    // Leave out the accepted diffs
+    baseline: Option<Baseline>,
+    // Leave out the diffs with these fingerprints
+    suppressions: Option<Suppressions>,
+    // Severity of each metric, to sort the diffs
+    severity_rules: Option<SeverityRules>,
+    // Leave out the diffs less severe than this
+    min_severity: Option<Severity>,
+    // Record the diffs to accept them
+    accept: bool,
+    // Browse the minimal tests in the terminal after the comparison
+    interactive: bool,
+    // Directory the minimal tests are written to as test fixtures
+    emit_fixtures: Option<PathBuf>,
+    // Commands checking whether the snippets compile on their own
+    check_commands: Vec<CheckCommand>,
+    // Where the fields of the spaces are in the metric files
+    schema: Schema,
+}
+
+enum JobItem {
+    Files {
+        path1: PathBuf,
+        path2: PathBuf,
+    },
+    // Two documents with the same name read from a pair of NDJSON streams
+    Documents {
+        name: String,
+        path1: PathBuf,
+        path2: PathBuf,
+        json1: Value,
+        json2: Value,
+        partial: bool,
+    },
 }
 
 type JobReceiver = Receiver<Option<JobItem>>;
 type JobSender = Sender<Option<JobItem>>;
 
-fn get_code_snippets(path1: &Path, path2: &Path) -> Option<CodeSnippets> {
-    let buffer1 = match std::fs::read(path1) {
-        Ok(buffer1) => buffer1,
-        Err(_) => return None,
-    };
-    let json1: Value = match serde_json::from_slice(&buffer1) {
-        Ok(json1) => json1,
-        Err(_) => return None,
-    };
-    let buffer2 = match std::fs::read(path2) {
-        Ok(buffer2) => buffer2,
-        Err(_) => return None,
-    };
-    let json2: Value = match serde_json::from_slice(&buffer2) {
-        Ok(json2) => json2,
-        Err(_) => return None,
-    };
-
-    // Two JSON values MUST be exactly equal
-    let config = Config::new(CompareMode::Strict);
-
-    if let Err(json_diff) = assert_json_matches_no_panic(&json1, &json2, config) {
-        // Do not consider spaces parsed ONLY by the new version of
-        // a grammar. Since they were not present in an old version, they COULD
-        //  be an improvement.
-        // FIXME: Find a more decent way to do this
-        let without_missing_spaces: Vec<&str> = json_diff
-            .lines()
-            .filter(|line| !(line.contains("is missing from") || line.is_empty()))
-            .collect();
-
-        // Get json diffs information
-        let spaces_diff: Vec<SnippetDiff> = without_missing_spaces
-            .chunks(5)
-            // Do not consider start_line, end_line, space_name, space_kind changes
-            .filter(|chunk| {
-                !(chunk[0].contains("start_line")
-                    || chunk[0].contains("end_line")
-                    || chunk[0].contains("name")
-                    || chunk[0].contains("kind")
-                    || chunk[0].contains("halstead.length")
-                    || chunk[0].contains("halstead.volume")
-                    || chunk[0].contains("halstead.vocabulary")
-                    || chunk[0].contains("halstead.purity_ratio")
-                    || chunk[0].contains("halstead.level")
-                    || chunk[0].contains("halstead.estimated_program_length")
-                    || chunk[0].contains("halstead.time")
-                    || chunk[0].contains("halstead.bugs")
-                    || chunk[0].contains("halstead.difficulty")
-                    || chunk[0].contains("halstead.effort")
-                    || chunk[0].contains("metrics.mi")
-                    || chunk[0].contains("average"))
-            })
-            .map(|chunk| {
-                let path_tmp: Vec<&str> = chunk[0].splitn(3, '"').collect();
-                SnippetDiff {
-                    path: path_tmp[1].to_owned(),
-                    old: chunk[2].trim_start().to_owned(),
-                    new: chunk[4].trim_start().to_owned(),
-                }
-            })
-            .collect();
+/// Reads a JSON file, returning whether it had to be repaired too.
+fn read_json(path: &Path, options: &Options) -> Result<(Value, bool), ExtractionError> {
+    read_json_against(path, None, options)
+}
 
-        let mut global_metrics: Vec<SnippetDiff> = Vec::new();
-        let mut snippets_data: HashMap<LinesRange, Vec<SnippetDiff>> = HashMap::new();
-
-        // Detect spaces path
-        let re = Regex::new(r"(spaces\[\d+\])").unwrap();
-        for diff in spaces_diff {
-            let space_path_items: Vec<String> = re
-                .find_iter(&diff.path)
-                .map(|mat| {
-                    let space_path_item = diff.path.get(mat.start()..mat.end()).unwrap();
-                    space_path_item.replace("[", " ").replace("]", "")
-                })
-                .collect();
-            let space_path = space_path_items.join(" ");
+// Whether the new metric files can be parsed against the old ones, leaving
+// out their equal metrics: the spaces are paired by index, none of them is
+// pruned and the whole new spaces are not needed
+fn streams(options: &Options) -> bool {
+    options.space_matching == SpaceMatching::Index
+        && options.ignored_spaces.is_empty()
+        && options.ignored_paths.is_empty()
+        && !options.validate
+        && options.emit_fixtures.is_none()
+        && !options.interactive
+}
 
-            // If empty, it is a global metric
-            if space_path.is_empty() {
-                global_metrics.push(diff);
-            } else {
-                let mut value = json2.get("spaces").unwrap();
-                for key in space_path.split(' ').skip(1) {
-                    value = if let Ok(number) = key.parse::<usize>() {
-                        value.get(number).unwrap()
-                    } else {
-                        value.get(key).unwrap()
-                    };
-                }
-                // Subtracting one since the lines of a file start from 0
-                let start_line = value.get("start_line").unwrap().as_u64().unwrap() as usize - 1;
-                let end_line = value.get("end_line").unwrap().as_u64().unwrap() as usize;
-                let lines_range = LinesRange {
-                    start_line,
-                    end_line,
-                };
-                if let Some(val) = snippets_data.get_mut(&lines_range) {
-                    val.push(diff);
-                } else {
-                    snippets_data.insert(lines_range, vec![diff]);
-                }
+/// Reads a metric file, a new JSON one against the old metric file `old`
+/// if any, so that the metrics equal in both are left out as it is parsed.
+fn read_json_against(
+    path: &Path,
+    old: Option<&mut Value>,
+    options: &Options,
+) -> Result<(Value, bool), ExtractionError> {
+    let buffer = read_file(path, options.mmap).map_err(|source| ExtractionError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    if buffer.iter().all(u8::is_ascii_whitespace) {
+        return Err(ExtractionError::Empty {
+            path: path.to_path_buf(),
+        });
+    }
+    if let Some(old) = old {
+        if streams(options) && options.input_format.of_file(path) == InputFormat::Json {
+            // A file which cannot be streamed is parsed as a whole, to
+            // report its error or to repair it
+            if let Ok(json) = parse_against(&buffer, old, &options.schema) {
+                return Ok((json, false));
             }
         }
+    }
+    match parse_metrics(&buffer, options.input_format, path) {
+        Ok(json) => Ok((json, false)),
+        Err(ExtractionError::Parse { source, .. })
+            if options.repair_truncated && source.is_eof() =>
+        {
+            repair_truncated(&buffer)
+                .map(|json| (json, true))
+                .ok_or(ExtractionError::Parse {
+                    path: path.to_path_buf(),
+                    source,
+                })
+        }
+        Err(err) => Err(err),
+    }
+}
 
-        let source_filename = json2.get("name").unwrap().as_str().unwrap().to_owned();
-        println!("{source_filename}");
-
-        Some(CodeSnippets {
-            source_filename,
-            global_metrics,
-            snippets_data,
-        })
+fn check_schema(json: &Value, path: &Path, schema: &Schema) -> Result<(), ExtractionError> {
+    let errors = validate(json, schema);
+    if errors.is_empty() {
+        Ok(())
     } else {
-        None
+        Err(ExtractionError::Schema {
+            path: path.to_path_buf(),
+            errors,
+        })
     }
 }
 
-fn get_output_filename(source_path: &Path) -> String {
+fn get_output_filename(source_path: &Path, naming: Naming, extension: &str) -> String {
     let clean_filename: Vec<&str> = source_path
         .iter()
         .filter(|v| {
@@ -176,286 +544,2778 @@ fn get_output_filename(source_path: &Path) -> String {
         })
         .map(|s| s.to_str().unwrap())
         .collect();
-    clean_filename.join("_") + ".html"
+    match naming {
+        Naming::Flat => format!("{}.{extension}", clean_filename.join("_")),
+        Naming::Hash => {
+            let hash = sha256_hex(source_path.to_string_lossy().as_bytes());
+            format!("{}-{}.{extension}", clean_filename.join("_"), &hash[..8])
+        }
+        Naming::Mirror => format!("{}.{extension}", clean_filename.join("/")),
+    }
 }
 
-fn write<W: Write>(
+fn is_selected(json: &Value, options: &Options) -> bool {
+    let name = match Space::new(json, &options.schema).name() {
+        Some(name) => name,
+        None => return true,
+    };
+    (options.include.is_empty() || options.include.iter().any(|glob| glob.is_match(name)))
+        && !options.exclude.iter().any(|glob| glob.is_match(name))
+}
+
+fn write_report<W: Write>(
     writer: &mut W,
     output_filename: &str,
     source_file: &str,
     snippets: &CodeSnippets,
+    options: &Options,
 ) -> std::io::Result<()> {
-    writeln!(
-        writer,
-        "<!DOCTYPE html>
-<html>
-<head>
-    <title>{}</title>
-</head>
-<body>",
-        output_filename
-    )?;
-    if !snippets.global_metrics.is_empty() {
-        // Print global metrics
-        writeln!(writer, "<h1>Global Metrics</h1>")?;
-        for SnippetDiff { path, old, new } in &snippets.global_metrics {
-            writeln!(
+    let mut report_writer: Box<dyn ReportWriter> = match options.format {
+        Format::Html => Box::new(HtmlReport::new(source_file, true)),
+        Format::HtmlLegacy => Box::new(LegacyReport::new(source_file)),
+        Format::Json => Box::<JsonReport>::default(),
+        Format::Csv => Box::new(CsvReport),
+        Format::Markdown => Box::<MarkdownReport>::default(),
+        Format::Toml => Box::<TomlReport>::default(),
+        Format::Junit => {
+            return junit::write_failure(
                 writer,
-                "<b>path:</b> {} <br>
-<b>old:</b> {} <br>
-<b>new:</b> {} <br><br>",
-                path, old, new
-            )?;
-        }
-    }
-    if !snippets.global_metrics.is_empty() && snippets.snippets_data.is_empty() {
-        writeln!(writer, "<h2>Code</h2>")?;
-        writeln!(writer, "<pre><i>{}</i></pre>\n", source_file)?;
-    }
-    if !snippets.snippets_data.is_empty() {
-        // Print spaces data
-        writeln!(writer, "<h1>Spaces Data</h1>")?;
-        for (lines_range, diffs) in &snippets.snippets_data {
-            writeln!(
-                writer,
-                "<h2>Minimal test - lines ({}, {})</h2>",
-                lines_range.start_line + 1,
-                lines_range.end_line
-            )?;
-            for diff in diffs {
-                writeln!(
-                    writer,
-                    "<b>path:</b> {}<br>
-<b>old:</b> {}<br>
-<b>new:</b> {}<br><br>",
-                    diff.path, diff.old, diff.new
-                )?;
-            }
-            writeln!(writer, "<h3>Code</h3>")?;
-            let str_lines: Vec<&str> = source_file
-                .lines()
-                .skip(lines_range.start_line)
-                .take(lines_range.end_line - lines_range.start_line)
-                .collect();
-            writeln!(writer, "<pre><i>{}</i></pre>\n", str_lines.join("\n"))?;
+                &html_escape::encode_text(source_file),
+                snippets,
+                options.context,
+                options.precision,
+                &options.schema,
+            )
         }
-    }
-    writeln!(
-        writer,
-        "</body>
-</html>"
-    )?;
-    Ok(())
+    };
+    let report = Report {
+        output_filename,
+        source_file,
+        snippets,
+        options,
+    };
+    report::write_report(writer, report_writer.as_mut(), &report)
 }
 
 fn act_on_file(
-    path1: PathBuf,
-    path2: PathBuf,
-    output_path: Option<PathBuf>,
-) -> std::io::Result<()> {
-    if let Some(snippets) = get_code_snippets(&path1, &path2) {
-        let source_path = PathBuf::from(&snippets.source_filename);
-        let source_file_bytes = match read_file_with_eol(&source_path) {
-            Ok(source_file_bytes) => match source_file_bytes {
-                Some(bytes) => bytes,
-                None => return Ok(()),
-            },
-            Err(_) => return Ok(()),
-        };
+    path1: &Path,
+    path2: &Path,
+    options: &Options,
+    summary: &Mutex<Summary>,
+) -> Result<PairStatus, ExtractionError> {
+    let (mut json1, partial1) = read_json(path1, options)?;
+    let (json2, partial2) = read_json_against(path2, Some(&mut json1), options)?;
+    act_on_jsons(
+        json1,
+        path1,
+        json2,
+        path2,
+        partial1 || partial2,
+        options,
+        summary,
+    )
+}
 
-        let source_file = match std::str::from_utf8(&source_file_bytes) {
-            Ok(source_file) => source_file.to_owned(),
-            Err(_) => match encode_to_utf8(&source_file_bytes) {
-                Ok(source_file) => source_file,
-                Err(_) => return Ok(()),
-            },
-        };
+fn act_on_jsons(
+    json1: Value,
+    path1: &Path,
+    json2: Value,
+    path2: &Path,
+    partial: bool,
+    options: &Options,
+    summary: &Mutex<Summary>,
+) -> Result<PairStatus, ExtractionError> {
+    if !is_selected(&json2, options) {
+        log::log(Event::Skipped {
+            name: &path2.display().to_string(),
+            reason: "skipped by --include or --exclude",
+        });
+        return Ok(PairStatus::Filtered);
+    }
 
-        let source_escape_html = html_escape::encode_text(&source_file);
+    // Check both files before walking them, so that a malformed space
+    // is reported with its exact location
+    if options.validate {
+        check_schema(&json1, path1, &options.schema)?;
+        check_schema(&json2, path2, &options.schema)?;
+    }
 
-        let output_filename = get_output_filename(&source_path);
-        if let Some(output_path) = output_path {
-            let mut output_file = File::create(output_path.join(&output_filename))?;
-            write(
-                &mut output_file,
-                &output_filename,
-                &source_escape_html,
-                &snippets,
-            )?;
-        } else {
-            let stdout = std::io::stdout();
-            let mut stdout = stdout.lock();
-            write(
-                &mut stdout,
-                &output_filename,
-                &source_escape_html,
-                &snippets,
-            )?;
+    if let Some(mut snippets) = get_code_snippets(json1, json2, options)? {
+        if log::enabled(Level::Normal) {
+            println!("{}", snippets.source_filename);
         }
+        snippets.partial = partial;
+        write_snippets(snippets, path2, options, summary)
+    } else {
+        Ok(PairStatus::Identical)
     }
+}
 
-    Ok(())
+/// Reads a source file as UTF-8, from the directory of the relative
+/// source file names if any.
+fn read_source(
+    source_path: &Path,
+    source_root: Option<&Path>,
+    encodings: &Encodings,
+) -> Option<String> {
+    let source_path = match source_root {
+        Some(source_root) => source_root.join(source_path),
+        None => source_path.to_path_buf(),
+    };
+    let source_file_bytes = read_file_with_eol(&source_path).ok()??;
+    match std::str::from_utf8(&source_file_bytes) {
+        Ok(source_file) => Some(source_file.to_owned()),
+        Err(_) => match encode_to_utf8(&source_file_bytes, encodings) {
+            Ok((source_file, encoding)) => {
+                verbose!(
+                    "{}: not UTF-8, decoded as {}",
+                    source_path.display(),
+                    encoding.name()
+                );
+                Some(source_file)
+            }
+            Err(err) => {
+                verbose!("{}: {err}", source_path.display());
+                None
+            }
+        },
+    }
 }
 
-fn consumer(receiver: JobReceiver) {
-    while let Ok(job) = receiver.recv() {
-        if job.is_none() {
-            break;
-        }
-        let job = job.unwrap();
-        let path1 = job.path1.clone();
-        let path2 = job.path2.clone();
+/// Writes the report of some snippets, next to their source file.
+/// `metric_path` is the new metric file, where the source hashes
+/// may be stored.
+fn write_snippets(
+    mut snippets: CodeSnippets,
+    metric_path: &Path,
+    options: &Options,
+    summary: &Mutex<Summary>,
+) -> Result<PairStatus, ExtractionError> {
+    let source_path = PathBuf::from(&snippets.source_filename);
+    let source_file = read_source(
+        &source_path,
+        options.source_root.as_deref(),
+        &options.encodings,
+    );
 
-        if let Err(err) = act_on_file(job.path1, job.path2, job.output_path) {
-            eprintln!("{:?} for files {:?} {:?}", err, path1, path2);
+    if let (Some(source_file), Some(old_source_filename)) =
+        (&source_file, &snippets.old_source_filename)
+    {
+        let old_source_file = read_source(
+            Path::new(old_source_filename),
+            options.old_source_root.as_deref(),
+            &options.encodings,
+        );
+        if let Some(old_source_file) = old_source_file {
+            for (lines_range, old_range) in &snippets.old_ranges {
+                let (_, lines, _) = snippet_lines(source_file, lines_range, 0);
+                let (_, old_lines, _) = snippet_lines(&old_source_file, old_range, 0);
+                snippets
+                    .code_changed
+                    .insert(lines_range.clone(), lines != old_lines);
+            }
         }
     }
-}
-
-fn send_file(path1: PathBuf, path2: PathBuf, output_path: Option<PathBuf>, sender: &JobSender) {
-    sender
-        .send(Some(JobItem {
-            path1,
-            path2,
-            output_path,
-        }))
-        .unwrap();
-}
+    if let Some(source_file) = &source_file {
+        enclose_snippets(&mut snippets, source_file);
+    }
 
-fn is_hidden(entry: &DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s.starts_with('.'))
-        .unwrap_or(false)
-}
+    if options.only_unchanged_code {
+        let changed: Vec<LinesRange> = snippets
+            .code_changed
+            .iter()
+            .filter(|(_, changed)| **changed)
+            .map(|(lines_range, _)| lines_range.clone())
+            .collect();
+        for lines_range in changed {
+            if let Some(diffs) = snippets.snippets_data.remove(&lines_range) {
+                snippets
+                    .filtered
+                    .extend(diffs.into_iter().map(|diff| FilteredDiff {
+                        diff,
+                        rule: FilterRule::CodeChanged,
+                    }));
+            }
+        }
+    }
 
-fn explore(path1: PathBuf, path2: PathBuf, output_path: Option<PathBuf>, sender: &JobSender) {
-    if path1.is_dir() && path2.is_dir() {
-        WalkDir::new(&path1)
+    if let Some(baseline) = &options.baseline {
+        let source_filename = &snippets.source_filename;
+        let mut accepted = Vec::new();
+        for diffs in [&mut snippets.global_metrics, &mut snippets.space_changes]
             .into_iter()
-            .filter_entry(|e| !is_hidden(e))
-            .zip(
-                WalkDir::new(&path2)
-                    .into_iter()
-                    .filter_entry(|e| !is_hidden(e)),
-            )
-            .for_each(|(entry1, entry2)| {
-                let entry1 = entry1.as_ref().unwrap();
-                let path1_file: PathBuf = entry1.path().to_path_buf();
-                let entry2 = entry2.as_ref().unwrap();
-                let path2_file: PathBuf = entry2.path().to_path_buf();
-                if path1_file.is_file()
-                    && path2_file.is_file()
-                    && path1_file.extension().unwrap() == "json"
-                    && path2_file.extension().unwrap() == "json"
-                    && path1_file.file_name().unwrap() == path2_file.file_name().unwrap()
-                {
-                    send_file(path1_file, path2_file, output_path.clone(), sender);
-                }
-            });
-    } else {
-        send_file(path1, path2, output_path, sender);
+            .chain(snippets.snippets_data.values_mut())
+            .chain(snippets.positions.values_mut())
+        {
+            let (accepted_diffs, kept): (Vec<SnippetDiff>, Vec<SnippetDiff>) = diffs
+                .drain(..)
+                .partition(|diff| baseline.is_accepted(source_filename, diff));
+            *diffs = kept;
+            accepted.extend(accepted_diffs);
+        }
+        snippets.snippets_data.retain(|_, diffs| !diffs.is_empty());
+        snippets.positions.retain(|_, diffs| !diffs.is_empty());
+        snippets
+            .filtered
+            .extend(accepted.into_iter().map(|diff| FilteredDiff {
+                diff,
+                rule: FilterRule::Accepted,
+            }));
     }
-}
 
-#[inline(always)]
-fn exist_or_exit(path: &Path, which_path: &str) {
-    if !(path.exists()) {
-        eprintln!(
-            "The {which_path} path `{}` is not correct",
-            path.to_str().unwrap()
-        );
-        process::exit(1);
+    if let Some(suppressions) = &options.suppressions {
+        let source_filename = &snippets.source_filename;
+        let mut suppressed = Vec::new();
+        for diffs in [&mut snippets.global_metrics, &mut snippets.space_changes]
+            .into_iter()
+            .chain(snippets.snippets_data.values_mut())
+            .chain(snippets.positions.values_mut())
+        {
+            let (suppressed_diffs, kept): (Vec<SnippetDiff>, Vec<SnippetDiff>) =
+                diffs.drain(..).partition(|diff| {
+                    suppressions.is_suppressed(&fingerprint(source_filename, diff, &options.schema))
+                });
+            *diffs = kept;
+            suppressed.extend(suppressed_diffs);
+        }
+        snippets.snippets_data.retain(|_, diffs| !diffs.is_empty());
+        snippets.positions.retain(|_, diffs| !diffs.is_empty());
+        snippets
+            .filtered
+            .extend(suppressed.into_iter().map(|diff| FilteredDiff {
+                diff,
+                rule: FilterRule::Suppressed,
+            }));
     }
-}
-
-fn main() {
-    let matches = App::new("json-minimal-tests")
-        .version(crate_version!())
-        .author(&*env!("CARGO_PKG_AUTHORS").replace(':', "\n"))
-        .about(
-            "Find the minimal tests from a source code using the differences
-between the metrics of the two JSON files passed in input.",
-        )
-        .arg(
-            Arg::with_name("output")
-                .help("Output directory")
-                .short("o")
-                .long("output")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("first-json")
-                .help("Old json file")
-                .required(true)
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("second-json")
-                .help("New json file")
-                .required(true)
-                .takes_value(true),
-        )
-        .get_matches();
 
-    let path1 = PathBuf::from(matches.value_of("first-json").unwrap());
-    let path2 = PathBuf::from(matches.value_of("second-json").unwrap());
-    let output_path = if let Some(path) = matches.value_of("output") {
-        let path = PathBuf::from(path);
-        exist_or_exit(&path, "output");
-        Some(path)
-    } else {
-        None
-    };
-
-    exist_or_exit(&path1, "first");
-    exist_or_exit(&path2, "second");
+    if let Some(rules) = &options.severity_rules {
+        let severity =
+            |diff: &SnippetDiff| rules.severity(metric_name(&diff.path, &options.schema));
+        let mut below = Vec::new();
+        for diffs in [&mut snippets.global_metrics, &mut snippets.space_changes]
+            .into_iter()
+            .chain(snippets.snippets_data.values_mut())
+            .chain(snippets.positions.values_mut())
+        {
+            if let Some(min_severity) = options.min_severity {
+                let (kept, below_diffs): (Vec<SnippetDiff>, Vec<SnippetDiff>) = diffs
+                    .drain(..)
+                    .partition(|diff| severity(diff) >= min_severity);
+                *diffs = kept;
+                below.extend(below_diffs);
+            }
+            // The most severe diffs first
+            diffs.sort_by_key(|diff| Reverse(severity(diff)));
+        }
+        snippets.snippets_data.retain(|_, diffs| !diffs.is_empty());
+        snippets.positions.retain(|_, diffs| !diffs.is_empty());
+        snippets
+            .filtered
+            .extend(below.into_iter().map(|diff| FilteredDiff {
+                diff,
+                rule: FilterRule::Severity,
+            }));
+    }
 
-    if (path1.is_dir() && !path2.is_dir()) || (!path1.is_dir() && path2.is_dir()) {
-        eprintln!("Both the paths should be a directory or a file",);
-        process::exit(1);
+    if let Some(max_snippets) = options.max_snippets_per_file {
+        snippets.omitted_snippets = keep_largest_snippets(&mut snippets, max_snippets);
     }
 
-    let num_jobs = std::cmp::max(2, num_cpus::get()) - 1;
+    // The filters above walk the snippets in no particular order
+    snippets
+        .filtered
+        .sort_by(|a, b| a.diff.path.cmp(&b.diff.path));
 
-    let (sender, receiver) = unbounded();
+    // No report when every diff has been filtered out
+    if snippets.global_metrics.is_empty()
+        && snippets.snippets_data.is_empty()
+        && snippets.positions.is_empty()
+        && snippets.space_changes.is_empty()
+    {
+        verbose!("{}: every diff filtered out", snippets.source_filename);
+        return Ok(PairStatus::Filtered);
+    }
+    {
+        let mut summary = summary.lock().unwrap();
+        summary.add(&snippets, &options.schema);
+        if options.results_db.is_some() {
+            summary
+                .db_diffs
+                .extend(db_diffs(&snippets, &options.schema));
+        }
+        for diff in snippets
+            .global_metrics
+            .iter()
+            .chain(snippets.snippets_data.values().flatten())
+            .chain(snippets.positions.values().flatten())
+            .chain(&snippets.space_changes)
+        {
+            log::log(Event::Diff {
+                source: &snippets.source_filename,
+                diff,
+            });
+            if options.accept {
+                summary.accepted.accept(&snippets.source_filename, diff);
+            }
+        }
+    }
 
-    let producer = {
-        let sender = sender.clone();
+    let source_file = match source_file {
+        Some(source_file) => source_file,
+        None => return Ok(PairStatus::SourceMissing),
+    };
 
-        thread::Builder::new()
-            .name(String::from("Producer"))
-            .spawn(move || explore(path1, path2, output_path, &sender))
+    snippets.source_hashes.extend(read_sidecar(metric_path));
+    snippets.stale = stale_ranges(&source_file, &snippets.source_hashes);
+    if !snippets.stale.is_empty() {
+        eprintln!(
+            "{}: the source differs from the one the metrics were computed on",
+            snippets.source_filename
+        );
+        summary
+            .lock()
             .unwrap()
-    };
+            .stale
+            .push(snippets.source_filename.clone());
+    }
 
-    let mut receivers = Vec::with_capacity(num_jobs);
-    for i in 0..num_jobs {
-        let receiver = receiver.clone();
+    if let Some(check_command) = options
+        .check_commands
+        .iter()
+        .find(|command| command.applies_to(&snippets.source_filename))
+    {
+        for lines_range in snippets.snippets_data.keys() {
+            let (_, lines, _) = snippet_lines(&source_file, lines_range, 0);
+            let mut snippet = lines.join("\n");
+            snippet.push('\n');
+            match check_command.check(&snippet, &snippets.source_filename) {
+                Ok(compiles) => {
+                    snippets.checked.insert(lines_range.clone(), compiles);
+                }
+                Err(err) => eprintln!(
+                    "{}: cannot check the snippet at lines ({}, {}): {}",
+                    snippets.source_filename,
+                    lines_range.start_line + 1,
+                    lines_range.end_line,
+                    err
+                ),
+            }
+        }
+    }
 
-        let thread = thread::Builder::new()
-            .name(format!("Consumer {}", i))
-            .spawn(move || {
-                consumer(receiver);
-            })
-            .unwrap();
+    if options.top.is_some() || options.max_output.is_some() {
+        summary.lock().unwrap().deferred.push(DeferredReport {
+            snippets,
+            source_file,
+            metric_path: metric_path.to_path_buf(),
+        });
+        return Ok(PairStatus::Reported);
+    }
+    report_snippets(snippets, source_file, metric_path, options, summary)?;
+    Ok(PairStatus::Reported)
+}
 
-        receivers.push(thread);
+/// Writes the report of the snippets of a source file, once filtered.
+fn report_snippets(
+    snippets: CodeSnippets,
+    source_file: String,
+    metric_path: &Path,
+    options: &Options,
+    summary: &Mutex<Summary>,
+) -> std::io::Result<()> {
+    let source_path = PathBuf::from(&snippets.source_filename);
+    // A file whose diffs are all outside of the snippets has nothing to review
+    if options.interactive && !snippets.snippets_data.is_empty() {
+        let tests = report::sorted_snippets(&snippets.snippets_data, options)
+            .into_iter()
+            .map(|(lines_range, diffs)| (lines_range.clone(), diffs.clone()))
+            .collect();
+        let expected = snippets
+            .snippets_data
+            .keys()
+            .map(|lines_range| {
+                (
+                    lines_range.clone(),
+                    expected_metrics(&snippets, lines_range, &options.schema),
+                )
+            })
+            .collect();
+        summary.lock().unwrap().review.insert(
+            snippets.source_filename.clone(),
+            ReviewFile {
+                source_file: source_file.clone(),
+                language: snippets.language.clone(),
+                tests,
+                expected,
+            },
+        );
     }
 
-    if producer.join().is_err() {
-        process::exit(1);
+    if let Some(dir) = &options.emit_fixtures {
+        let fixtures = write_fixtures(dir, &snippets, &source_file, &options.schema)?;
+        summary.lock().unwrap().fixtures.extend(fixtures);
     }
 
-    // Poison the receiver, now that the producer is finished.
-    for _ in 0..num_jobs {
-        sender.send(None).unwrap();
+    let output_filename =
+        get_output_filename(&source_path, options.naming, options.format.extension());
+    if options.group_by == GroupBy::Metric {
+        summary.lock().unwrap().by_metric.add(&snippets, options);
+    } else if options.single_report.is_some() {
+        let mut section = Vec::new();
+        let report = Report {
+            output_filename: &output_filename,
+            source_file: &source_file,
+            snippets: &snippets,
+            options,
+        };
+        report::write_report(
+            &mut section,
+            &mut HtmlReport::new(&source_file, false),
+            &report,
+        )?;
+        let diffs = snippets.global_metrics.len()
+            + snippets.snippets_data.values().map(Vec::len).sum::<usize>()
+            + snippets.positions.values().map(Vec::len).sum::<usize>()
+            + snippets.space_changes.len();
+        summary.lock().unwrap().sections.insert(
+            snippets.source_filename.clone(),
+            (diffs, String::from_utf8_lossy(&section).into_owned()),
+        );
+    } else if options.format == Format::Junit {
+        // The diffs become the failure message of the pair
+        let mut message = Vec::new();
+        write_report(
+            &mut message,
+            &output_filename,
+            &source_file,
+            &snippets,
+            options,
+        )?;
+        let pair_name = if options.ndjson {
+            snippets.source_filename.clone()
+        } else {
+            metric_path.display().to_string()
+        };
+        summary
+            .lock()
+            .unwrap()
+            .failures
+            .insert(pair_name, String::from_utf8_lossy(&message).into_owned());
+    } else if let Some(output_path) = &options.output_path {
+        // Two source files can have the same report name
+        let output_filename = summary.lock().unwrap().reserve_report_name(
+            &snippets.source_filename,
+            output_filename,
+            |name| options.on_existing == OnExisting::Version && output_path.join(name).exists(),
+        );
+        let output_file_path = output_path.join(&output_filename);
+        if output_file_path.exists() {
+            match options.on_existing {
+                OnExisting::Skip => {
+                    verbose!("{}: the report exists, skipped", output_file_path.display());
+                    return Ok(());
+                }
+                OnExisting::Error => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::AlreadyExists,
+                        format!("the report {} exists", output_file_path.display()),
+                    ))
+                }
+                OnExisting::Overwrite | OnExisting::Version => {}
+            }
+        }
+        if let Some(parent) = output_file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomically(&output_file_path, |output_file| {
+            write_report(
+                output_file,
+                &output_filename,
+                &source_file,
+                &snippets,
+                options,
+            )
+        })?;
+        summary.lock().unwrap().artifacts.push(output_filename);
+    } else if !options.interactive {
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        write_report(
+            &mut stdout,
+            &output_filename,
+            &source_file,
+            &snippets,
+            options,
+        )?;
     }
+    Ok(())
+}
 
-    for receiver in receivers {
-        if receiver.join().is_err() {
+/// Writes the reports of the top snippets of the run, with `--top`, within
+/// the output budget of `--max-output-mb`.
+fn report_deferred(options: &Options, summary: &Mutex<Summary>) {
+    if options.top.is_none() && options.max_output.is_none() {
+        return;
+    }
+    let mut deferred = std::mem::take(&mut summary.lock().unwrap().deferred);
+    // In the order of the source files, whatever the order of the pairs
+    deferred.sort_by(|a, b| a.snippets.source_filename.cmp(&b.snippets.source_filename));
+    if let Some(top) = options.top {
+        keep_top(&mut deferred, top, options.sort_by);
+    }
+    if let Some(max_output) = options.max_output {
+        let omitted = fit_budget(&mut deferred, max_output, |report| {
+            let output_filename = get_output_filename(
+                Path::new(&report.snippets.source_filename),
+                options.naming,
+                options.format.extension(),
+            );
+            let mut counter = ByteCounter::default();
+            // Writing to the counter cannot fail
+            let _ = write_report(
+                &mut counter,
+                &output_filename,
+                &report.source_file,
+                &report.snippets,
+                options,
+            );
+            counter.0
+        });
+        summary.lock().unwrap().omitted.extend(omitted);
+    }
+    for report in deferred {
+        let source_filename = report.snippets.source_filename.clone();
+        if let Err(err) = report_snippets(
+            report.snippets,
+            report.source_file,
+            &report.metric_path,
+            options,
+            summary,
+        ) {
+            eprintln!("Cannot write the report of {source_filename}: {err}");
             process::exit(1);
         }
     }
 }
+
+/// Writes the report of the diffs captured in an assert_json_diff log,
+/// resolving their spaces in the new metric file they come from.
+fn import_diff_log(
+    log_path: &Path,
+    metric_path: &Path,
+    options: &Options,
+    summary: &Mutex<Summary>,
+) -> Result<PairStatus, ExtractionError> {
+    let log = std::fs::read_to_string(log_path).map_err(|source| ExtractionError::Read {
+        path: log_path.to_path_buf(),
+        source,
+    })?;
+    let (mut json, partial) = read_json(metric_path, options)?;
+    if options.validate {
+        check_schema(&json, metric_path, &options.schema)?;
+    }
+    let mut diffs = parse_diff_log(&log, options.report_missing);
+    // The imported diffs cannot be pruned with the metric files
+    diffs.retain(|diff| {
+        !options
+            .ignored_paths
+            .iter()
+            .any(|ignore| ignore.matches_diff(&diff.path))
+    });
+    if diffs.is_empty() {
+        return Ok(PairStatus::Identical);
+    }
+    let ignored_spaces = prune_spaces(&mut json, &options.ignored_spaces, &options.schema);
+    let mut snippets = snippets_from_diffs(None, &json, diffs, ignored_spaces, options)?;
+    if log::enabled(Level::Normal) {
+        println!("{}", snippets.source_filename);
+    }
+    snippets.partial = partial;
+    write_snippets(snippets, metric_path, options, summary)
+}
+
+// Compares the files or the documents of a job
+fn compare_job(job: JobItem, options: &Options, summary: &Mutex<Summary>) -> PairStatus {
+    match job {
+        JobItem::Files { path1, path2 } => {
+            let cache_key = options
+                .cache
+                .as_ref()
+                .and_then(|cache| cache.pair_key(&path1, &path2).ok());
+            let cached = options
+                .cache
+                .as_ref()
+                .zip(cache_key.as_ref())
+                .and_then(|(cache, key)| cache.lookup(&path1, &path2, key));
+            let status = match cached {
+                Some(status) => {
+                    verbose!("{}: {} in the cache", path2.display(), status.name());
+                    status
+                }
+                None => match act_on_file(&path1, &path2, options, summary) {
+                    Ok(status) => status,
+                    Err(err) => {
+                        log::log(Event::Error {
+                            name: &path2.display().to_string(),
+                            context: &format!("Files {:?} {:?}", path1, path2),
+                            message: err.to_string(),
+                        });
+                        summary.lock().unwrap().add_error(&err);
+                        PairStatus::from_error(&err)
+                    }
+                },
+            };
+            if let (Some(cache), Some(key), None) = (&options.cache, &cache_key, cached) {
+                if let Err(err) = cache.store(&path1, &path2, key, status) {
+                    eprintln!("Cannot cache the comparison of {:?}: {}", path2, err);
+                }
+            }
+            status
+        }
+        JobItem::Documents {
+            name,
+            path1,
+            path2,
+            json1,
+            json2,
+            partial,
+        } => match act_on_jsons(json1, &path1, json2, &path2, partial, options, summary) {
+            Ok(status) => status,
+            Err(err) => {
+                log::log(Event::Error {
+                    name: &name,
+                    context: &format!("Document `{}`", name),
+                    message: err.to_string(),
+                });
+                summary.lock().unwrap().add_error(&err);
+                PairStatus::from_error(&err)
+            }
+        },
+    }
+}
+
+fn consumer(receiver: JobReceiver, options: Arc<Options>, summary: Arc<Mutex<Summary>>) {
+    while let Ok(job) = receiver.recv() {
+        let Some(job) = job else {
+            break;
+        };
+        // The pairs left after a failure with --fail-fast are drained,
+        // so that the producer does not wait for the consumers
+        if summary.lock().unwrap().stopped {
+            continue;
+        }
+        let start = Instant::now();
+        let name = match &job {
+            JobItem::Files { path2, .. } => path2.display().to_string(),
+            JobItem::Documents { name, .. } => name.clone(),
+        };
+        // A bug met by a pair fails the pair, not the consumer
+        let status = panic::catch_unwind(AssertUnwindSafe(|| compare_job(job, &options, &summary)))
+            .unwrap_or_else(|payload| {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                log::log(Event::Error {
+                    name: &name,
+                    context: &format!("Panicked comparing {name}"),
+                    message,
+                });
+                // The other pairs are still added to the summary
+                summary.clear_poison();
+                PairStatus::Panicked
+            });
+        log::log(Event::Compared {
+            name: &name,
+            status: status.name(),
+            elapsed: start.elapsed(),
+        });
+        let mut summary = summary.lock().unwrap();
+        // The failed pair is still counted and reported
+        if options.fail_fast && status.is_error() && !summary.stopped {
+            eprintln!("Stopped at the first failed pair, {name}");
+            summary.stopped = true;
+        }
+        summary.add_status(name, status);
+    }
+}
+
+fn send_file(path1: PathBuf, path2: PathBuf, sender: &JobSender) {
+    sender.send(Some(JobItem::Files { path1, path2 })).unwrap();
+}
+
+fn send_documents(
+    path1: &Path,
+    path2: &Path,
+    options: &Options,
+    sender: &JobSender,
+) -> std::io::Result<()> {
+    // Index the documents of the first stream by name
+    let mut documents1 = HashMap::new();
+    for_each_document(
+        path1,
+        options.repair_truncated,
+        |json1, partial1| match document_name(&json1, &options.schema) {
+            Some(name) => {
+                documents1.insert(name.to_owned(), (json1, partial1));
+            }
+            None => eprintln!("A document in {:?} has no name", path1),
+        },
+    )?;
+
+    for_each_document(path2, options.repair_truncated, |json2, partial2| {
+        let name = match document_name(&json2, &options.schema) {
+            Some(name) => name.to_owned(),
+            None => {
+                eprintln!("A document in {:?} has no name", path2);
+                return;
+            }
+        };
+        let (json1, partial1) = match documents1.remove(&name) {
+            Some(document1) => document1,
+            None => {
+                eprintln!("Document `{}` is only in {:?}", name, path2);
+                return;
+            }
+        };
+        if let Some(changed_files) = &options.changed_files {
+            if !is_source_changed(Path::new(&name), changed_files) {
+                log::log(Event::Skipped {
+                    name: &name,
+                    reason: "skipped, not changed",
+                });
+                return;
+            }
+        }
+        sender
+            .send(Some(JobItem::Documents {
+                name,
+                path1: path1.to_path_buf(),
+                path2: path2.to_path_buf(),
+                json1,
+                json2,
+                partial: partial1 || partial2,
+            }))
+            .unwrap();
+    })?;
+
+    for name in documents1.keys() {
+        eprintln!("Document `{}` is only in {:?}", name, path1);
+    }
+
+    Ok(())
+}
+
+fn explore<F: Fn(PathBuf, PathBuf) + Sync>(
+    path1: PathBuf,
+    path2: PathBuf,
+    options: &Options,
+    num_jobs: usize,
+    on_pair: F,
+) {
+    let on_pair = |path1: PathBuf, path2: PathBuf| {
+        if let Some(changed_files) = &options.changed_files {
+            if !is_changed(&path2, &options.walk.extensions, changed_files) {
+                log::log(Event::Skipped {
+                    name: &path2.display().to_string(),
+                    reason: "skipped, not changed",
+                });
+                return;
+            }
+        }
+        log::log(Event::Paired {
+            path1: &path1,
+            path2: &path2,
+        });
+        on_pair(path1, path2);
+    };
+    if path1.is_dir() && path2.is_dir() {
+        match options.pair_by {
+            PairBy::Path => parallel_walk(&path1, &path2, num_jobs, &options.walk, on_pair),
+            PairBy::Name => pair_by_name(
+                &path1,
+                &path2,
+                num_jobs,
+                &options.walk,
+                options.input_format,
+                &options.schema,
+                on_pair,
+            ),
+        }
+    } else {
+        on_pair(path1, path2);
+    }
+}
+
+fn produce(
+    path1: PathBuf,
+    path2: PathBuf,
+    options: &Options,
+    num_jobs: usize,
+    sender: &JobSender,
+    summary: &Mutex<Summary>,
+) {
+    if options.ndjson {
+        if let Err(err) = send_documents(&path1, &path2, options, sender) {
+            eprintln!("Cannot read the streams {:?} {:?}: {}", path1, path2, err);
+        }
+    } else if options.prescan {
+        let pairs = Mutex::new(Vec::new());
+        explore(path1, path2, options, num_jobs, |path1, path2| {
+            pairs.lock().unwrap().push((path1, path2))
+        });
+        let pairs = pairs.into_inner().unwrap();
+        let total = pairs.len();
+        let prescan = prescan(pairs, num_jobs);
+        let counts = format!(
+            "{} pairs: {} identical, {} different",
+            total,
+            prescan.identical.len(),
+            prescan.different.len()
+        );
+        if options.count {
+            println!("{counts}");
+            return;
+        }
+        eprintln!("{counts}");
+        {
+            let mut summary = summary.lock().unwrap();
+            for (_, path2) in prescan.identical {
+                log::log(Event::Skipped {
+                    name: &path2.display().to_string(),
+                    reason: "identical, skipped by the prescan",
+                });
+                summary.add_status(path2.display().to_string(), PairStatus::Identical);
+            }
+        }
+        for (path1, path2) in prescan.different {
+            send_file(path1, path2, sender);
+        }
+    } else {
+        explore(path1, path2, options, num_jobs, |path1, path2| {
+            send_file(path1, path2, sender)
+        });
+    }
+}
+
+/// Compares again the pairs whose files are added or modified,
+/// until the process is interrupted.
+fn watch(path1: &Path, path2: &Path, options: &Options, num_jobs: usize) -> ! {
+    let pairs = || {
+        let pairs = Mutex::new(Vec::new());
+        explore(
+            path1.to_path_buf(),
+            path2.to_path_buf(),
+            options,
+            num_jobs,
+            |path1, path2| pairs.lock().unwrap().push((path1, path2)),
+        );
+        pairs.into_inner().unwrap()
+    };
+    let mut watcher = Watcher::new(pairs());
+    eprintln!("Watching {:?} and {:?} for changes", path1, path2);
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+        for (path1, path2) in watcher.changed(pairs()) {
+            // Each comparison is reported on its own
+            let summary = Mutex::new(Summary::default());
+            let status = match act_on_file(&path1, &path2, options, &summary) {
+                Ok(status) => status,
+                Err(err) => {
+                    eprintln!("Files {:?} {:?}: {}", path1, path2, err);
+                    PairStatus::from_error(&err)
+                }
+            };
+            report_deferred(options, &summary);
+            eprintln!("{}: {}", path2.display(), status.name());
+        }
+    }
+}
+
+/// Prints the pairs which would be compared, then the JSON files
+/// left out of them, without comparing anything.
+fn dry_run(path1: &Path, path2: &Path, options: &Options, num_jobs: usize) {
+    let pairs = Mutex::new(Vec::new());
+    explore(
+        path1.to_path_buf(),
+        path2.to_path_buf(),
+        options,
+        num_jobs,
+        |path1, path2| pairs.lock().unwrap().push((path1, path2)),
+    );
+    let mut pairs = pairs.into_inner().unwrap();
+    pairs.sort();
+    println!("{} pairs", pairs.len());
+    for (path1, path2) in &pairs {
+        println!("{}  {}", path1.display(), path2.display());
+    }
+
+    if path1.is_dir() {
+        let paired: HashSet<&PathBuf> = pairs
+            .iter()
+            .flat_map(|(path1, path2)| [path1, path2])
+            .collect();
+        let not_compared = Mutex::new(Vec::new());
+        for root in [path1, path2] {
+            walk_files(root, num_jobs, &options.walk, |relative| {
+                let path = root.join(relative);
+                if options.walk.is_metric_file(&path) && !paired.contains(&path) {
+                    not_compared.lock().unwrap().push(path);
+                }
+            });
+        }
+        let mut not_compared = not_compared.into_inner().unwrap();
+        not_compared.sort();
+        if !not_compared.is_empty() {
+            println!("\n{} files not compared", not_compared.len());
+            for path in not_compared {
+                println!("          {}", path.display());
+            }
+        }
+    }
+}
+
+fn globs_or_exit(matches: &ArgMatches, name: &str) -> Vec<Glob> {
+    matches
+        .values_of(name)
+        .map(|values| {
+            values
+                .map(|value| {
+                    Glob::new(value).unwrap_or_else(|err| {
+                        eprintln!("The --{name} glob `{value}` is not valid: {err}");
+                        process::exit(1);
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn pair_args<'a, 'b>() -> [Arg<'a, 'b>; 2] {
+    [
+        Arg::with_name("first-json")
+            .help("Old json file")
+            .required(true)
+            .takes_value(true),
+        Arg::with_name("second-json")
+            .help("New json file")
+            .required(true)
+            .takes_value(true),
+    ]
+}
+
+fn baseline_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("baseline")
+        .help("File of the accepted diffs")
+        .long("baseline")
+        .default_value("metrics-baseline.json")
+        .takes_value(true)
+}
+
+#[inline(always)]
+// Whether a path exists, telling which one does not
+fn exists(path: &Path, which_path: &str) -> bool {
+    if !(path.exists()) {
+        eprintln!(
+            "The {which_path} path `{}` is not correct",
+            path.to_str().unwrap()
+        );
+    }
+    path.exists()
+}
+
+fn exist_or_exit(path: &Path, which_path: &str) {
+    if !exists(path, which_path) {
+        process::exit(1);
+    }
+}
+
+/// Compares two metric files or directories on `num_jobs` consumers,
+/// returning whether the producer of the pairs failed, or `None` for
+/// a dry run. The error is the exit code of a comparison which cannot be
+/// done or went on, since the caller has temporary files to remove.
+fn compare_paths(
+    path1: PathBuf,
+    path2: PathBuf,
+    options: &Arc<Options>,
+    summary: &Arc<Mutex<Summary>>,
+    num_jobs: usize,
+    queue_size: usize,
+    dry_run: bool,
+) -> Result<Option<bool>, i32> {
+    if !exists(&path1, "first") || !exists(&path2, "second") {
+        return Err(1);
+    }
+
+    if (path1.is_dir() && !path2.is_dir()) || (!path1.is_dir() && path2.is_dir()) {
+        eprintln!("Both the paths should be a directory or a file",);
+        return Err(1);
+    }
+
+    if options.ndjson && path1.is_dir() {
+        eprintln!("Both the paths should be a file when comparing NDJSON streams");
+        return Err(1);
+    }
+
+    if dry_run {
+        self::dry_run(&path1, &path2, options, num_jobs);
+        return Ok(None);
+    }
+
+    // Bound the queue, so the producer waits for the consumers instead of
+    // holding every pair (or every NDJSON document) in memory
+    let (sender, receiver) = bounded(queue_size);
+
+    let producer = {
+        let sender = sender.clone();
+        let options = options.clone();
+        let summary = summary.clone();
+
+        thread::Builder::new()
+            .name(String::from("Producer"))
+            .spawn(move || produce(path1, path2, &options, num_jobs, &sender, &summary))
+            .unwrap()
+    };
+
+    let mut receivers = Vec::with_capacity(num_jobs);
+    for i in 0..num_jobs {
+        let receiver = receiver.clone();
+        let options = options.clone();
+        let summary = summary.clone();
+
+        let thread = thread::Builder::new()
+            .name(format!("Consumer {}", i))
+            .spawn(move || {
+                consumer(receiver, options, summary);
+            })
+            .unwrap();
+
+        receivers.push(thread);
+    }
+
+    // The pairs already sent are still compared
+    let producer_failed = producer.join().is_err();
+    if producer_failed && options.fail_fast {
+        return Err(1);
+    }
+
+    // Poison the receiver, now that the producer is finished.
+    for _ in 0..num_jobs {
+        sender.send(None).unwrap();
+    }
+
+    for receiver in receivers {
+        if receiver.join().is_err() {
+            return Err(1);
+        }
+    }
+
+    Ok(Some(producer_failed))
+}
+
+// The command line interface, also used to generate the shell completions
+fn app(authors: &str) -> App<'_, '_> {
+    App::new("json-minimal-tests")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .version(crate_version!())
+        .author(authors)
+        .about(
+            "Find the minimal tests from a source code using the differences
+between the metrics of the two JSON files passed in input.",
+        )
+        .arg(
+            Arg::with_name("config")
+                .help(
+                    "File setting the long options, json-minimal-tests.toml \
+                     in the current directory by default",
+                )
+                .long("config")
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("profile")
+                .help(
+                    "Preset of options for a workflow: strict compares everything and \
+                     fails on corrupt files, grammar-bump pairs the spaces by name and \
+                     narrows the snippets, metrics-dev reports every diff",
+                )
+                .long("profile")
+                .possible_values(Profile::NAMES)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .help("Output directory")
+                .short("o")
+                .long("output")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .help(
+                    "Format of the reports; html-legacy HTML-escapes the paths, \
+                     the values and the code, unlike the first releases",
+                )
+                .long("format")
+                .possible_values(Format::NAMES)
+                .default_value("html")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("single-report")
+                .help(
+                    "Write the reports of all the source files in this self-contained \
+                     HTML page, instead of one page for each",
+                )
+                .long("single-report")
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("theme")
+                .help("Colors of the styled reports, following the system theme with auto")
+                .long("theme")
+                .possible_values(Theme::NAMES)
+                .default_value("auto")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("highlight-bounds")
+                .help("Mark the first and the last line of each snippet in the styled reports")
+                .long("highlight-bounds"),
+        )
+        .arg(
+            Arg::with_name("severity-rules")
+                .help(
+                    "File of `glob = low|medium|high` lines giving the severity of \
+                     the metrics, e.g. `cyclomatic.* = high`, to sort the diffs by it",
+                )
+                .long("severity-rules")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("suppressions")
+                .help(
+                    "File of diff fingerprints, one per line, whose diffs are \
+                     left out of the reports",
+                )
+                .long("suppressions")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("min-severity")
+                .help("Leave out the diffs less severe than this")
+                .long("min-severity")
+                .possible_values(Severity::NAMES)
+                .requires("severity-rules")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("naming")
+                .help(
+                    "Naming of the reports: the source path flattened with underscores, \
+                     followed by a hash of the path, or mirrored in subdirectories",
+                )
+                .long("naming")
+                .possible_values(Naming::NAMES)
+                .default_value("flat")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("on-existing")
+                .help(
+                    "What is done with a report already in the output directory: \
+                     overwrite it, skip the new one, fail the pair or number the new one",
+                )
+                .long("on-existing")
+                .possible_values(OnExisting::NAMES)
+                .default_value("overwrite")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("pair-by")
+                .help(
+                    "Pair the metric files of two directories by relative path, \
+                     or by the name of the source file inside them",
+                )
+                .long("pair-by")
+                .possible_values(PairBy::NAMES)
+                .default_value("path")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("group-by")
+                .help(
+                    "Write a report for each source file, or a single report \
+                     with a section for each metric, listing where it changed",
+                )
+                .long("group-by")
+                .possible_values(GroupBy::NAMES)
+                .default_value("file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("top")
+                .help(
+                    "Report only this number of minimal tests, the ones with \
+                     the largest change or the most diffs of the whole run",
+                )
+                .long("top")
+                .value_name("N")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-snippets-per-file")
+                .help(
+                    "Report at most this number of minimal tests for each source file, \
+                     the ones with the largest change",
+                )
+                .long("max-snippets-per-file")
+                .value_name("N")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-output-mb")
+                .help(
+                    "Size of all the reports, in megabytes, over which the minimal \
+                     tests with the smallest change are left out",
+                )
+                .long("max-output-mb")
+                .value_name("N")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("sort-by")
+                .help("What ranks the minimal tests kept by --top, delta by default")
+                .long("sort-by")
+                .possible_values(SortBy::NAMES)
+                .requires("top")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("db")
+                .help(
+                    "Append the run and its diffs to a SQLite database, \
+                     created when missing, with the sqlite3 shell",
+                )
+                .long("db")
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("match-spaces")
+                .help(
+                    "Match the child spaces by index, aligning them by name and kind when \
+                     their number differs, or by name, kind and start line wherever they are",
+                )
+                .long("match-spaces")
+                .possible_values(SpaceMatching::NAMES)
+                .default_value("index")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("validate")
+                .help("Check that the JSON files follow the rust-code-analysis schema")
+                .long("validate"),
+        )
+        .arg(
+            Arg::with_name("keep-going")
+                .help("Compare the other pairs when a pair cannot be compared, the default")
+                .long("keep-going")
+                .overrides_with("fail-fast"),
+        )
+        .arg(
+            Arg::with_name("fail-fast")
+                .help("Stop at the first pair which cannot be compared")
+                .long("fail-fast")
+                .overrides_with("keep-going"),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .help("Exit with an error when some input files are corrupt")
+                .long("strict"),
+        )
+        .arg(
+            Arg::with_name("schema")
+                .help(
+                    "JSON descriptor of the keys of the metric files, \
+                     when they are not produced by rust-code-analysis",
+                )
+                .long("schema")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ignore-space")
+                .help(
+                    "Space to exclude from the comparison, given as a JSON pointer \
+                     (e.g. /spaces/0/spaces/12) or as a space name",
+                )
+                .long("ignore-space")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("ignore-path")
+                .help(
+                    "Sub-tree to exclude from the comparison, given as a JSON pointer \
+                     whose segments can be * (e.g. /spaces/*/metrics/halstead)",
+                )
+                .long("ignore-path")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("compare-rules")
+                .help(
+                    "File of `glob = exact|abs N|rel N%|ignore|integer` lines giving \
+                     how the values of the metrics are compared, e.g. `halstead.* = rel 1%`",
+                )
+                .long("compare-rules")
+                .value_name("FILE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("include-halstead")
+                .help("Compare the Halstead metrics derived from the others, ignored by default")
+                .long("include-halstead"),
+        )
+        .arg(
+            Arg::with_name("include-mi")
+                .help("Compare the maintainability index, ignored by default")
+                .long("include-mi"),
+        )
+        .arg(
+            Arg::with_name("report-positions")
+                .help(
+                    "Report the start and end line changes of the spaces \
+                     in their own section, instead of ignoring them",
+                )
+                .long("report-positions"),
+        )
+        .arg(
+            Arg::with_name("report-missing")
+                .help(
+                    "Report the metrics missing from one of the metric files, \
+                     with <absent> as their value on that side",
+                )
+                .long("report-missing"),
+        )
+        .arg(
+            Arg::with_name("no-default-ignores")
+                .help(
+                    "Compare everything ignored by default: positions, names, kinds, \
+                     averages and derived metrics",
+                )
+                .long("no-default-ignores"),
+        )
+        .arg(
+            Arg::with_name("space-kind")
+                .help("Only report the diffs of spaces of these kinds (e.g. function,class)")
+                .long("space-kind")
+                .takes_value(true)
+                .use_delimiter(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("minimize")
+                .help(
+                    "Narrow each snippet to the deepest child space \
+                     where the same metric changed",
+                )
+                .long("minimize"),
+        )
+        .arg(
+            Arg::with_name("max-snippet-lines")
+                .help(
+                    "Narrow the snippets longer than this number of lines \
+                     to the smallest child space containing the diff",
+                )
+                .long("max-snippet-lines")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("context")
+                .help("Lines of code shown before and after each snippet")
+                .long("context")
+                .default_value("0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("precision")
+                .help("Decimals of the changes shown next to the numeric diffs")
+                .long("precision")
+                .value_name("N")
+                .default_value("2")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("encoding")
+                .help(
+                    "Encodings tried in turn on the source files which are not UTF-8, \
+                     e.g. latin1,shift_jis, instead of Shift JIS, EUC-KR, GBK then Windows-1252",
+                )
+                .long("encoding")
+                .value_name("LABELS")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("only-unchanged-code")
+                .help(
+                    "Only report the snippets whose code is the same in the old sources, \
+                     where the metrics changed on their own",
+                )
+                .long("only-unchanged-code"),
+        )
+        .arg(
+            Arg::with_name("check-cmd")
+                .help(
+                    "Label the snippets which compile on their own with this command, \
+                     e.g. 'rs=rustc --edition 2021 --emit=metadata {file}'",
+                )
+                .long("check-cmd")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("merge")
+                .help("Merge the snippets whose line ranges overlap or are adjacent")
+                .long("merge"),
+        )
+        .arg(
+            Arg::with_name("merge-distance")
+                .help("Also merge the snippets at most this number of lines apart")
+                .long("merge-distance")
+                .default_value("0")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("include")
+                .help("Only compare the metrics of the source files matching this glob")
+                .long("include")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .help("Skip the metrics of the source files matching this glob")
+                .long("exclude")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("extension")
+                .help("Extension of the metric files of the compared directories, e.g. metrics")
+                .long("extension")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("input-format")
+                .help(
+                    "Format of the metric files, by default JSON unless their \
+                     extension is yaml, yml, msgpack or cbor",
+                )
+                .long("input-format")
+                .possible_values(InputFormat::NAMES)
+                .default_value("auto")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("hidden")
+                .help("Also walk the hidden files and directories, e.g. .metrics")
+                .long("hidden"),
+        )
+        .arg(
+            Arg::with_name("max-depth")
+                .help(
+                    "Only compare the metric files up to this depth of the directories, \
+                     1 for their own files",
+                )
+                .long("max-depth")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("prune")
+                .help("Do not descend the directories whose relative path matches this glob")
+                .long("prune")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("ignore-file")
+                .help(
+                    "Skip the metric files matching the patterns of this file, \
+                     in the format of .gitignore, besides the .gitignore and .ignore files",
+                )
+                .long("ignore-file")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("ndjson")
+                .help(
+                    "Compare two streams of newline-delimited JSON documents, \
+                     pairing the documents by their name",
+                )
+                .long("ndjson"),
+        )
+        .arg(
+            Arg::with_name("repair-truncated")
+                .help("Compare the complete part of truncated JSON files, marking the reports as partial")
+                .long("repair-truncated"),
+        )
+        .arg(
+            Arg::with_name("mmap")
+                .help(
+                    "Map the metric files in memory rather than reading them, \
+                     to hold less memory when comparing large files",
+                )
+                .long("mmap"),
+        )
+        .arg(
+            Arg::with_name("changed-only")
+                .help("Only compare the metrics of the sources changed since a revision")
+                .long("changed-only")
+                .requires("since"),
+        )
+        .arg(
+            Arg::with_name("repo")
+                .help("Git repository containing the sources")
+                .long("repo")
+                .default_value(".")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("since")
+                .help("Git revision the sources are compared against")
+                .long("since")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("prescan")
+                .help("Compare the bytes of all file pairs up front and skip the identical ones")
+                .long("prescan"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .help(
+                    "Also print the pairing decisions, the skipped files and \
+                     the encoding fallbacks, and the time spent on each pair with -vv",
+                )
+                .short("v")
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .help("Do not print the names of the source files with diffs")
+                .short("q")
+                .conflicts_with("verbose"),
+        )
+        .arg(
+            Arg::with_name("log-format")
+                .help(
+                    "Print the pairs compared, the diffs found, the files skipped \
+                     and the errors on stderr as text, or as one JSON object per line",
+                )
+                .long("log-format")
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .help("Only print the file pairs which would be compared and the files left out")
+                .long("dry-run")
+                .conflicts_with_all(&["ndjson", "count", "watch"]),
+        )
+        .arg(
+            Arg::with_name("count")
+                .help("Only print how many file pairs are identical or different")
+                .long("count"),
+        )
+        .arg(
+            Arg::with_name("summary")
+                .help("Print a summary of the diffs found in all files")
+                .long("summary"),
+        )
+        .arg(
+            Arg::with_name("summary-json")
+                .help("Write a summary of the diffs found in all files as JSON")
+                .long("summary-json")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("summary-top")
+                .help("Number of files with the most diffs listed in the summary")
+                .long("summary-top")
+                .default_value("10")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("pr-comment")
+                .help("Write a Markdown comment about the diffs, for a pull request")
+                .long("pr-comment")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("artifacts-url")
+                .help(
+                    "URL where the output directory is uploaded, linked by the comment \
+                     and the notification",
+                )
+                .long("artifacts-url")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("webhook")
+                .help(
+                    "POST a JSON summary to this URL, e.g. a Slack or Teams incoming \
+                     webhook, when the run finds diffs",
+                )
+                .long("webhook")
+                .value_name("URL")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("webhook-threshold")
+                .help("Notify the webhook only from this number of diffs, 1 by default")
+                .long("webhook-threshold")
+                .value_name("N")
+                .takes_value(true)
+                .requires("webhook"),
+        )
+        .arg(
+            Arg::with_name("grammar-diff")
+                .help("Patch of the grammar under test, added to the summaries and the output")
+                .long("grammar-diff")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .help("Compare again the pairs whose files change, until interrupted")
+                .long("watch")
+                .conflicts_with_all(&["ndjson", "count"]),
+        )
+        .arg(
+            Arg::with_name("interactive")
+                .help(
+                    "Browse the minimal tests in the terminal after the comparison, \
+                     to accept, reject or export each of them",
+                )
+                .long("interactive")
+                .conflicts_with_all(&["dry-run", "count", "watch"]),
+        )
+        .arg(
+            Arg::with_name("export-dir")
+                .help("Directory of the minimal tests exported in the interactive review")
+                .long("export-dir")
+                .default_value("minimal-tests")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("wrap")
+                .help(
+                    "Export the minimal tests wrapped in a scaffold which compiles \
+                     on its own: a Rust module, a C file with the includes of its source",
+                )
+                .long("wrap")
+                .requires("interactive"),
+        )
+        .arg(
+            Arg::with_name("emit-fixtures")
+                .help(
+                    "Directory where each minimal test is written as a fixture of \
+                     rust-code-analysis, its code and the expected metrics of its spaces",
+                )
+                .long("emit-fixtures")
+                .value_name("DIR")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("review-baseline")
+                .help("File of the diffs accepted in the interactive review")
+                .long("review-baseline")
+                .default_value("metrics-baseline.json")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("bug-list")
+                .help(
+                    "File the minimal tests rejected in the interactive review are \
+                     appended to, one line each",
+                )
+                .long("bug-list")
+                .value_name("FILE")
+                .takes_value(true)
+                .requires("interactive"),
+        )
+        .arg(
+            Arg::with_name("cache-dir")
+                .help("Directory caching the comparisons, to skip the unchanged pairs")
+                .long("cache-dir")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("queue-size")
+                .help("Maximum number of pairs waiting to be compared")
+                .long("queue-size")
+                .default_value("256")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-increase-vs")
+                .help("Fail when there are more diffs than in the summary JSON of a previous run")
+                .long("max-increase-vs")
+                .takes_value(true),
+        )
+        .subcommand(
+            SubCommand::with_name("from-sources")
+                .about(
+                    "Compute the metrics of two source directories with \
+                     rust-code-analysis-cli, then compare them",
+                )
+                .arg(
+                    Arg::with_name("ref")
+                        .help("Compare the directory with itself at this git revision")
+                        .long("ref")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("git")
+                        .help(
+                            "Compare the directory at two git revisions, \
+                             e.g. main..my-branch",
+                        )
+                        .long("git")
+                        .takes_value(true)
+                        .conflicts_with("ref"),
+                )
+                .arg(
+                    Arg::with_name("rca-cli")
+                        .help("Path of rust-code-analysis-cli")
+                        .long("rca-cli")
+                        .default_value("rust-code-analysis-cli")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("sources")
+                        .help("Old and new source directories, or only one with --ref or --git")
+                        .default_value(".")
+                        .min_values(1)
+                        .max_values(2),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import-diff-log")
+                .about(
+                    "Extract the minimal tests from an assert_json_diff output \
+                     captured in an old log, using the new JSON file it refers to",
+                )
+                .arg(
+                    Arg::with_name("log")
+                        .help("File containing the assert_json_diff output")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .help("New json file")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Serve the reports of an output directory over HTTP, with a searchable index")
+                .arg(
+                    Arg::with_name("output")
+                        .help("Output directory of a previous run")
+                        .short("o")
+                        .long("output")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("port")
+                        .help("Port to listen on")
+                        .long("port")
+                        .default_value("8080")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("address")
+                        .help("Address to listen on, e.g. 0.0.0.0 for the other hosts")
+                        .long("address")
+                        .default_value("127.0.0.1")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("clean")
+                .about(
+                    "Remove the files written by the last run in an output directory, \
+                     as listed in its manifest",
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("Output directory of a previous run")
+                        .short("o")
+                        .long("output")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .help("List the files to remove without removing them")
+                        .long("dry-run"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff-results")
+                .about(
+                    "Compare the JSON reports of two runs, listing the diffs which \
+                     are new, fixed or unchanged",
+                )
+                .arg(
+                    Arg::with_name("old")
+                        .help("JSON report or output directory of the previous run")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .help("JSON report or output directory of the new run")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .help("Write the comparison as JSON")
+                        .long("json"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("trend")
+                .about(
+                    "Chart the number of diffs of each metric over the last runs \
+                     recorded in a results database",
+                )
+                .arg(
+                    Arg::with_name("db")
+                        .help("SQLite database written with --db, read with the sqlite3 shell")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("last")
+                        .help("Number of runs, the most recent ones")
+                        .long("last")
+                        .default_value("20")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("metric")
+                        .help("Only the metrics starting with this name, e.g. cognitive")
+                        .long("metric")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("csv")
+                        .help("Write a CSV time series instead of an HTML page")
+                        .long("csv"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .help("File to write, the standard output by default")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("Write the completion script of a shell to the standard output")
+                .arg(
+                    Arg::with_name("shell")
+                        .help("Shell to complete the command in")
+                        .possible_values(&Shell::variants())
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("accept")
+                .about("Compare two json files and record their diffs as accepted in a baseline")
+                .args(&pair_args())
+                .arg(baseline_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Compare two json files, only reporting the diffs not in the baseline")
+                .args(&pair_args())
+                .arg(baseline_arg()),
+        )
+        .args(&pair_args())
+}
+
+fn main() {
+    let started = SystemTime::now();
+    // The authors in the manifest are separated by colons
+    let authors = env!("CARGO_PKG_AUTHORS").replace(':', "\n");
+    let args: Vec<OsString> = std::env::args_os().collect();
+    // The options of the configuration file come first, but the ones given
+    // on the command line. These can be incomplete without the file, e.g.
+    // --sort-by with the --top of the file, so when they cannot be parsed
+    // alone their long options are looked for
+    let cli_matches = app(&authors).get_matches_from_safe(&args).ok();
+    let config_path = long_option_value(&args, "config")
+        .map(PathBuf::from)
+        .or_else(|| {
+            let path = PathBuf::from(CONFIG_NAME);
+            path.is_file().then_some(path)
+        });
+    let mut layered = match config_path {
+        Some(config_path) => {
+            exist_or_exit(&config_path, "configuration");
+            let config = Config::read(&config_path).unwrap_or_else(|err| {
+                eprintln!("Cannot read the configuration {:?}: {err}", config_path);
+                process::exit(1);
+            });
+            let mut layered = args[..1].to_vec();
+            layered.extend(config.args(|name| match &cli_matches {
+                Some(matches) => matches.occurrences_of(name) > 0,
+                None => is_long_option_given(&args, name),
+            }));
+            layered.extend_from_slice(&args[1..]);
+            layered
+        }
+        None => args,
+    };
+    // The options of a profile come before both, its unknown names
+    // are rejected by the parsing
+    if let Some(profile) = long_option_value(&layered, "profile")
+        .and_then(|name| name.to_str().and_then(Profile::parse))
+    {
+        let profile_args = profile.args(|name| is_long_option_given(&layered, name));
+        layered.splice(1..1, profile_args);
+    }
+    let matches = app(&authors).get_matches_from(layered);
+
+    log::set_level(match matches.occurrences_of("verbose") {
+        _ if matches.is_present("quiet") => Level::Quiet,
+        0 => Level::Normal,
+        1 => Level::Verbose,
+        _ => Level::Timing,
+    });
+    log::set_json(matches.value_of("log-format") == Some("json"));
+    if let Some(completions) = matches.subcommand_matches("completions") {
+        let shell = value_t!(completions.value_of("shell"), Shell).unwrap_or_else(|e| e.exit());
+        app(&authors).gen_completions_to(
+            "json-minimal-tests",
+            shell,
+            &mut std::io::stdout().lock(),
+        );
+        return;
+    }
+    if let Some(serve_matches) = matches.subcommand_matches("serve") {
+        let output_path = PathBuf::from(serve_matches.value_of("output").unwrap());
+        exist_or_exit(&output_path, "output");
+        let port = value_t!(serve_matches.value_of("port"), u16).unwrap_or_else(|e| e.exit());
+        let address = serve_matches.value_of("address").unwrap();
+        if let Err(err) = serve(&output_path, &format!("{address}:{port}")) {
+            eprintln!("Cannot serve the reports: {err}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(clean_matches) = matches.subcommand_matches("clean") {
+        let output_path = PathBuf::from(clean_matches.value_of("output").unwrap());
+        exist_or_exit(&output_path, "output");
+        let dry_run = clean_matches.is_present("dry-run");
+        let cleaning = clean(&output_path, dry_run).unwrap_or_else(|err| {
+            eprintln!("Cannot clean {:?}: {err}", output_path);
+            process::exit(1);
+        });
+        for path in &cleaning.removed {
+            if dry_run {
+                println!("{}", path.display());
+            } else {
+                verbose!("Removed {:?}", path);
+            }
+        }
+        for path in &cleaning.kept {
+            eprintln!("Kept {:?}, changed since the run", path);
+        }
+        if !dry_run {
+            eprintln!("{} files removed", cleaning.removed.len());
+        }
+        return;
+    }
+
+    if let Some(diff_matches) = matches.subcommand_matches("diff-results") {
+        let read = |which| {
+            let path = Path::new(diff_matches.value_of(which).unwrap());
+            exist_or_exit(path, which);
+            ResultSet::read(path).unwrap_or_else(|err| {
+                eprintln!("Cannot read the results {:?}: {err}", path);
+                process::exit(1);
+            })
+        };
+        let (old, new) = (read("old"), read("new"));
+        let results_diff = diff_results(&old, &new);
+        let stdout = std::io::stdout();
+        let result = if diff_matches.is_present("json") {
+            serde_json::to_writer_pretty(stdout.lock(), &results_diff.to_json())
+                .map_err(std::io::Error::from)
+                .and_then(|_| writeln!(stdout.lock()))
+        } else {
+            results_diff.write_text(&mut stdout.lock())
+        };
+        if let Err(err) = result {
+            eprintln!("Cannot write the comparison of the results: {err}");
+            process::exit(1);
+        }
+        // The new diffs are regressions
+        if !results_diff.new.is_empty() {
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(trend_matches) = matches.subcommand_matches("trend") {
+        let db_path = Path::new(trend_matches.value_of("db").unwrap());
+        exist_or_exit(db_path, "database");
+        let last = value_t!(trend_matches.value_of("last"), usize).unwrap_or_else(|e| e.exit());
+        let trend =
+            read_trend(db_path, last, trend_matches.value_of("metric")).unwrap_or_else(|err| {
+                eprintln!("Cannot read the results database {:?}: {err}", db_path);
+                process::exit(1);
+            });
+        let theme = Theme::parse(matches.value_of("theme").unwrap());
+        let write = |writer: &mut dyn Write| {
+            if trend_matches.is_present("csv") {
+                write_trend_csv(writer, &trend)
+            } else {
+                html::write_trend(writer, &trend, theme)
+            }
+        };
+        let result = match trend_matches.value_of("output") {
+            Some(path) => write_atomically(Path::new(path), |file| write(file)),
+            None => write(&mut std::io::stdout().lock()),
+        };
+        if let Err(err) = result {
+            eprintln!("Cannot write the trend: {err}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    // The accept and check subcommands take the pair to compare
+    let pair_matches = matches
+        .subcommand_matches("accept")
+        .or_else(|| matches.subcommand_matches("check"))
+        .unwrap_or(&matches);
+
+    let output_path = if let Some(path) = matches.value_of("output") {
+        let path = PathBuf::from(path);
+        exist_or_exit(&path, "output");
+        Some(path)
+    } else {
+        None
+    };
+
+    let changed_files = if matches.is_present("changed-only") {
+        let repo = PathBuf::from(matches.value_of("repo").unwrap());
+        exist_or_exit(&repo, "repository");
+        match changed_files(&repo, matches.value_of("since").unwrap()) {
+            Ok(changed_files) => Some(changed_files),
+            Err(err) => {
+                eprintln!("Cannot retrieve the changed files: {err}");
+                process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    let schema = match matches.value_of("schema") {
+        Some(path) => Schema::read(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("Cannot read the schema `{path}`: {err}");
+            process::exit(1);
+        }),
+        None => Schema::default(),
+    };
+
+    let encodings = match matches.value_of("encoding") {
+        Some(labels) => Encodings::parse(labels).unwrap_or_else(|label| {
+            eprintln!("Unknown encoding `{label}`");
+            process::exit(1);
+        }),
+        None => Encodings::default(),
+    };
+
+    let baseline = matches.subcommand_matches("check").map(|check| {
+        let path = check.value_of("baseline").unwrap();
+        Baseline::read(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("Cannot read the baseline `{path}`: {err}");
+            process::exit(1);
+        })
+    });
+
+    let compare_rules = matches.value_of("compare-rules").map(|path| {
+        CompareRules::read(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("Cannot read the comparison rules `{path}`: {err}");
+            process::exit(1);
+        })
+    });
+    let severity_rules = matches.value_of("severity-rules").map(|path| {
+        SeverityRules::read(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("Cannot read the severity rules `{path}`: {err}");
+            process::exit(1);
+        })
+    });
+
+    let suppressions = matches.value_of("suppressions").map(|path| {
+        Suppressions::read(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("Cannot read the suppressions `{path}`: {err}");
+            process::exit(1);
+        })
+    });
+
+    // With --only-unchanged-code a pair is filtered after its source files
+    // too, which the cache does not hash, so its pairs are never cached
+    let cache = matches
+        .value_of("cache-dir")
+        .filter(|_| !matches.is_present("only-unchanged-code"))
+        .map(|dir| {
+        // The options which change the outcome of a comparison
+        let values = |name| matches.values_of(name).map(Iterator::collect::<Vec<_>>);
+        let options_key = format!(
+            "{} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {} {} {} {} {} {} {} {} {:?} {:?} {:?} {:?}",
+            crate_version!(),
+            schema,
+            // A pair can be filtered by the accepted diffs
+            matches
+                .subcommand_matches("check")
+                .and_then(|check| std::fs::read(check.value_of("baseline").unwrap()).ok())
+                .map(|baseline| sha256_hex(&baseline)),
+            values("ignore-space"),
+            values("ignore-path"),
+            matches.value_of("match-spaces"),
+            // The values can be compared with tolerances
+            matches
+                .value_of("compare-rules")
+                .and_then(|path| std::fs::read(path).ok())
+                .map(|rules| sha256_hex(&rules)),
+            values("space-kind"),
+            values("include"),
+            values("exclude"),
+            matches.is_present("validate"),
+            matches.is_present("repair-truncated"),
+            matches.is_present("no-default-ignores"),
+            matches.is_present("include-halstead"),
+            matches.is_present("include-mi"),
+            matches.is_present("report-positions"),
+            matches.is_present("report-missing"),
+            matches.is_present("only-unchanged-code"),
+            // A pair can be filtered by the severity of its diffs
+            matches
+                .value_of("severity-rules")
+                .and_then(|path| std::fs::read(path).ok())
+                .map(|rules| sha256_hex(&rules)),
+            matches.value_of("min-severity"),
+            // A pair can be filtered by the fingerprints of its diffs
+            matches
+                .value_of("suppressions")
+                .and_then(|path| std::fs::read(path).ok())
+                .map(|suppressions| sha256_hex(&suppressions)),
+            // The same bytes read as another format are other values
+            matches.value_of("input-format")
+        );
+        Cache::new(PathBuf::from(dir), options_key).unwrap_or_else(|err| {
+            eprintln!("Cannot create the cache directory `{dir}`: {err}");
+            process::exit(1);
+        })
+    });
+
+    // The metrics are computed once the options are checked, right before
+    // the comparison, so that no early exit leaves them behind
+    let source_trees = matches
+        .subcommand_matches("from-sources")
+        .map(|from_sources| {
+            if matches.is_present("watch") {
+                eprintln!("--watch cannot be used with from-sources");
+                process::exit(1);
+            }
+            let cli = from_sources.value_of("rca-cli").unwrap();
+            let mut sources: Vec<PathBuf> = from_sources
+                .values_of("sources")
+                .unwrap()
+                .map(PathBuf::from)
+                .collect();
+            for sources in &sources {
+                exist_or_exit(sources, "sources");
+            }
+            let revision = from_sources.value_of("ref");
+            let range = from_sources.value_of("git").map(|range| {
+                range.split_once("..").unwrap_or_else(|| {
+                    eprintln!("The --git range `{range}` is not in the form <rev1>..<rev2>");
+                    process::exit(1);
+                })
+            });
+            let expected_sources = if revision.is_some() || range.is_some() {
+                1
+            } else {
+                2
+            };
+            if sources.len() != expected_sources {
+                eprintln!("Pass two source directories, or one with --ref or --git");
+                process::exit(1);
+            }
+            // As git does, an empty side of the range is HEAD
+            let or_head = |revision: &str| {
+                if revision.is_empty() {
+                    String::from("HEAD")
+                } else {
+                    revision.to_owned()
+                }
+            };
+            let new_sources = sources.pop().unwrap();
+            let source_trees = match (revision, range) {
+                (Some(revision), _) => SourceTrees::Revision(new_sources, revision.to_owned()),
+                (_, Some((old_revision, new_revision))) => SourceTrees::Revisions(
+                    new_sources,
+                    or_head(old_revision),
+                    or_head(new_revision),
+                ),
+                _ => SourceTrees::Dirs(sources.pop().unwrap(), new_sources),
+            };
+            (cli, source_trees)
+        });
+
+    let single_report = matches.value_of("single-report").map(PathBuf::from);
+    let format = Format::parse(matches.value_of("format").unwrap());
+    if single_report.is_some() && !format.is_html() {
+        eprintln!(
+            "The single report is an HTML page, it cannot be in the {} format",
+            matches.value_of("format").unwrap()
+        );
+        process::exit(1);
+    }
+    let group_by = GroupBy::parse(matches.value_of("group-by").unwrap());
+    if group_by == GroupBy::Metric && (format == Format::Junit || single_report.is_some()) {
+        eprintln!("The diffs of the JUnit and the single report cannot be grouped by metric");
+        process::exit(1);
+    }
+    let top = if matches.is_present("top") {
+        Some(value_t!(matches.value_of("top"), usize).unwrap_or_else(|e| e.exit()))
+    } else {
+        None
+    };
+    let max_snippets_per_file = if matches.is_present("max-snippets-per-file") {
+        Some(
+            value_t!(matches.value_of("max-snippets-per-file"), usize).unwrap_or_else(|e| e.exit()),
+        )
+    } else {
+        None
+    };
+    let max_output = if matches.is_present("max-output-mb") {
+        let megabytes =
+            value_t!(matches.value_of("max-output-mb"), u64).unwrap_or_else(|e| e.exit());
+        Some(megabytes * 1024 * 1024)
+    } else {
+        None
+    };
+    let ignore_files: Vec<PathBuf> = matches
+        .values_of("ignore-file")
+        .map(|values| values.map(PathBuf::from).collect())
+        .unwrap_or_default();
+    for ignore_file in &ignore_files {
+        exist_or_exit(ignore_file, "ignore file");
+    }
+    let max_depth = if matches.is_present("max-depth") {
+        let max_depth = value_t!(matches.value_of("max-depth"), usize).unwrap_or_else(|e| e.exit());
+        if max_depth == 0 {
+            eprintln!("The --max-depth should be at least 1");
+            process::exit(1);
+        }
+        Some(max_depth)
+    } else {
+        None
+    };
+    let input_format = InputFormat::parse(matches.value_of("input-format").unwrap());
+    let mut options = Options {
+        output_path,
+        single_report,
+        format,
+        theme: Theme::parse(matches.value_of("theme").unwrap()),
+        naming: Naming::parse(matches.value_of("naming").unwrap()),
+        on_existing: OnExisting::parse(matches.value_of("on-existing").unwrap()),
+        pair_by: PairBy::parse(matches.value_of("pair-by").unwrap()),
+        group_by,
+        top,
+        results_db: matches.value_of("db").map(PathBuf::from),
+        sort_by: matches
+            .value_of("sort-by")
+            .map(SortBy::parse)
+            .unwrap_or_default(),
+        max_snippets_per_file,
+        max_output,
+        validate: matches.is_present("validate"),
+        ignored_spaces: matches
+            .values_of("ignore-space")
+            .map(|values| values.map(SpaceIgnore::parse).collect())
+            .unwrap_or_default(),
+        ignored_paths: matches
+            .values_of("ignore-path")
+            .map(|values| {
+                values
+                    .map(|value| {
+                        PathIgnore::parse(value).unwrap_or_else(|| {
+                            eprintln!("The --ignore-path `{value}` is not a JSON pointer");
+                            process::exit(1);
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        compare_rules,
+        space_matching: SpaceMatching::parse(matches.value_of("match-spaces").unwrap()),
+        default_ignores: DefaultIgnores {
+            enabled: !matches.is_present("no-default-ignores"),
+            halstead: !matches.is_present("include-halstead"),
+            mi: !matches.is_present("include-mi"),
+        },
+        report_positions: matches.is_present("report-positions"),
+        report_missing: matches.is_present("report-missing"),
+        space_kinds: matches
+            .values_of("space-kind")
+            .map(|values| values.map(str::to_owned).collect())
+            .unwrap_or_default(),
+        minimize: matches.is_present("minimize"),
+        max_snippet_lines: if matches.is_present("max-snippet-lines") {
+            Some(
+                value_t!(matches.value_of("max-snippet-lines"), usize).unwrap_or_else(|e| e.exit()),
+            )
+        } else {
+            None
+        },
+        context: value_t!(matches.value_of("context"), usize).unwrap_or_else(|e| e.exit()),
+        precision: value_t!(matches.value_of("precision"), usize).unwrap_or_else(|e| e.exit()),
+        highlight_bounds: matches.is_present("highlight-bounds"),
+        merge_distance: if matches.is_present("merge")
+            || matches.occurrences_of("merge-distance") > 0
+        {
+            Some(value_t!(matches.value_of("merge-distance"), usize).unwrap_or_else(|e| e.exit()))
+        } else {
+            None
+        },
+        include: globs_or_exit(&matches, "include"),
+        exclude: globs_or_exit(&matches, "exclude"),
+        input_format,
+        walk: WalkOptions {
+            extensions: match matches.values_of("extension") {
+                Some(extensions) => extensions
+                    .map(|extension| extension.trim_start_matches('.').to_owned())
+                    .collect(),
+                None => input_format
+                    .extensions()
+                    .iter()
+                    .map(|extension| extension.to_string())
+                    .collect(),
+            },
+            hidden: matches.is_present("hidden"),
+            ignore_files,
+            max_depth,
+            prune: globs_or_exit(&matches, "prune"),
+        },
+        changed_files,
+        ndjson: matches.is_present("ndjson"),
+        repair_truncated: matches.is_present("repair-truncated"),
+        mmap: matches.is_present("mmap"),
+        fail_fast: matches.is_present("fail-fast"),
+        prescan: matches.is_present("prescan") || matches.is_present("count"),
+        count: matches.is_present("count"),
+        cache,
+        source_root: None,
+        old_source_root: None,
+        encodings,
+        only_unchanged_code: matches.is_present("only-unchanged-code"),
+        baseline,
+        suppressions,
+        severity_rules,
+        min_severity: matches.value_of("min-severity").and_then(Severity::parse),
+        accept: matches.subcommand_matches("accept").is_some(),
+        interactive: matches.is_present("interactive"),
+        emit_fixtures: matches.value_of("emit-fixtures").map(PathBuf::from),
+        check_commands: matches
+            .values_of("check-cmd")
+            .map(|values| {
+                values
+                    .map(|value| {
+                        CheckCommand::parse(value).unwrap_or_else(|| {
+                            eprintln!("The --check-cmd command `{value}` is not valid");
+                            process::exit(1);
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        schema,
+    };
+
+    let summary_top = value_t!(matches.value_of("summary-top"), usize).unwrap_or_else(|e| e.exit());
+    // Read the previous summary before comparing, to fail early
+    let previous_total_diffs = matches.value_of("max-increase-vs").map(|path| {
+        read_total_diffs(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("Cannot read the previous summary `{path}`: {err}");
+            process::exit(1);
+        })
+    });
+    let mut summary = Summary::default();
+    if let Some(path) = matches.value_of("grammar-diff") {
+        let grammar_diff = GrammarDiff::read(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("Cannot read the grammar diff `{path}`: {err}");
+            process::exit(1);
+        });
+        // Keep the grammar change in the output, next to the reports
+        if let Some(output_path) = &options.output_path {
+            if let Err(err) = write_atomically(&output_path.join(GRAMMAR_DIFF_NAME), |file| {
+                file.write_all(grammar_diff.relevant_text().as_bytes())
+            }) {
+                eprintln!("Cannot write the grammar diff: {err}");
+                process::exit(1);
+            }
+            summary.artifacts.push(GRAMMAR_DIFF_NAME.to_owned());
+        }
+        summary.grammar_diff = Some(grammar_diff);
+    }
+    let summary = Arc::new(Mutex::new(summary));
+
+    let queue_size = value_t!(matches.value_of("queue-size"), usize).unwrap_or_else(|e| e.exit());
+
+    let mut generated_metrics = source_trees.map(|(cli, source_trees)| {
+        source_trees.compute(cli).unwrap_or_else(|err| {
+            eprintln!("Cannot compute the metrics: {err}");
+            process::exit(1);
+        })
+    });
+    if let Some(generated_metrics) = &generated_metrics {
+        options.source_root = Some(generated_metrics.sources.clone());
+        options.old_source_root = Some(generated_metrics.old_sources.clone());
+    }
+    let options = Arc::new(options);
+
+    let num_jobs = std::cmp::max(2, num_cpus::get()) - 1;
+
+    let mut producer_failed = false;
+    let mut run = Run {
+        // args panics on an argument which is not UTF-8
+        arguments: std::env::args_os()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+        inputs: Vec::new(),
+        started,
+    };
+    if let Some(import) = matches.subcommand_matches("import-diff-log") {
+        let log_path = PathBuf::from(import.value_of("log").unwrap());
+        let metric_path = PathBuf::from(import.value_of("json").unwrap());
+        exist_or_exit(&log_path, "log");
+        exist_or_exit(&metric_path, "json");
+        run.inputs = vec![log_path.clone(), metric_path.clone()];
+        let status = match import_diff_log(&log_path, &metric_path, &options, &summary) {
+            Ok(status) => status,
+            Err(err) => {
+                log::log(Event::Error {
+                    name: &metric_path.display().to_string(),
+                    context: &format!("Files {:?} {:?}", log_path, metric_path),
+                    message: err.to_string(),
+                });
+                summary.lock().unwrap().add_error(&err);
+                PairStatus::from_error(&err)
+            }
+        };
+        summary
+            .lock()
+            .unwrap()
+            .add_status(metric_path.display().to_string(), status);
+    } else {
+        let (path1, path2) = match &generated_metrics {
+            Some(generated_metrics) => {
+                (generated_metrics.old.clone(), generated_metrics.new.clone())
+            }
+            None => (
+                PathBuf::from(pair_matches.value_of_os("first-json").unwrap()),
+                PathBuf::from(pair_matches.value_of_os("second-json").unwrap()),
+            ),
+        };
+        run.inputs = vec![path1.clone(), path2.clone()];
+        // The generated metrics are removed whatever the outcome
+        let compared = compare_paths(
+            path1,
+            path2,
+            &options,
+            &summary,
+            num_jobs,
+            queue_size,
+            matches.is_present("dry-run"),
+        );
+        // Not left to the drop, which process::exit skips
+        if let Some(generated_metrics) = &mut generated_metrics {
+            generated_metrics.remove();
+        }
+        match compared {
+            Ok(Some(failed)) => producer_failed = failed,
+            // A dry run only lists the pairs
+            Ok(None) => return,
+            Err(code) => process::exit(code),
+        }
+    }
+
+    report_deferred(&options, &summary);
+
+    let mut summary = summary.lock().unwrap();
+    if options.format == Format::Junit {
+        let result = match &options.output_path {
+            Some(output_path) => write_atomically(&output_path.join(JUNIT_NAME), |file| {
+                write_junit(file, &summary)
+            }),
+            None => write_junit(&mut std::io::stdout().lock(), &summary),
+        };
+        if let Err(err) = result {
+            eprintln!("Cannot write the JUnit report: {err}");
+            process::exit(1);
+        }
+        if options.output_path.is_some() {
+            summary.artifacts.push(JUNIT_NAME.to_owned());
+        }
+    }
+    if options.group_by == GroupBy::Metric && !options.interactive {
+        let name = format!("{BY_METRIC_NAME}.{}", options.format.extension());
+        let result = match &options.output_path {
+            Some(output_path) => write_atomically(&output_path.join(&name), |file| {
+                write_by_metric(file, &mut summary.by_metric, &options)
+            }),
+            None => write_by_metric(
+                &mut std::io::stdout().lock(),
+                &mut summary.by_metric,
+                &options,
+            ),
+        };
+        if let Err(err) = result {
+            eprintln!("Cannot write the report grouped by metric: {err}");
+            process::exit(1);
+        }
+        if options.output_path.is_some() {
+            summary.artifacts.push(name);
+        }
+    }
+    if let Some(path) = &options.results_db {
+        match record_run(path, &run, &summary.db_diffs) {
+            Ok(run_id) => verbose!("Run {run_id} recorded in {:?}", path),
+            Err(err) => {
+                eprintln!("Cannot record the run in {:?}: {err}", path);
+                process::exit(1);
+            }
+        }
+    }
+    if let Some(path) = &options.single_report {
+        if let Err(err) = write_atomically(path, |file| {
+            html::write_single(file, &summary.sections, options.theme)
+        }) {
+            eprintln!("Cannot write the single report {:?}: {err}", path);
+            process::exit(1);
+        }
+    }
+    if let Some(output_path) = &options.output_path {
+        if let Err(err) = write_manifest(output_path, &summary.artifacts) {
+            eprintln!("Cannot write the manifest: {err}");
+            process::exit(1);
+        }
+        if let Err(err) = write_run_manifest(output_path, &run, &summary) {
+            eprintln!("Cannot write the manifest of the run: {err}");
+            process::exit(1);
+        }
+    }
+    if matches.is_present("summary") {
+        let stdout = std::io::stdout();
+        if let Err(err) = summary.write_table(&mut stdout.lock(), summary_top) {
+            eprintln!("Cannot write the summary: {err}");
+            process::exit(1);
+        }
+    }
+    if let Some(path) = matches.value_of("summary-json") {
+        let json = serde_json::to_string_pretty(&summary.to_json(summary_top)).unwrap();
+        if let Err(err) = write_atomically(Path::new(path), |file| file.write_all(json.as_bytes()))
+        {
+            eprintln!("Cannot write the summary to `{path}`: {err}");
+            process::exit(1);
+        }
+    }
+    if let Some(path) = matches.value_of("pr-comment") {
+        let result = write_atomically(Path::new(path), |file| {
+            write_pr_comment(
+                file,
+                &summary,
+                summary_top,
+                options.precision,
+                matches.value_of("artifacts-url"),
+            )
+        });
+        if let Err(err) = result {
+            eprintln!("Cannot write the pull request comment to `{path}`: {err}");
+            process::exit(1);
+        }
+    }
+    if let Some(url) = matches.value_of("webhook") {
+        let threshold = if matches.is_present("webhook-threshold") {
+            value_t!(matches.value_of("webhook-threshold"), usize).unwrap_or_else(|e| e.exit())
+        } else {
+            1
+        };
+        if summary.total_diffs() >= threshold {
+            // The reports are linked where they are uploaded, or else where
+            // they have been written
+            let report = matches
+                .value_of("artifacts-url")
+                .map(str::to_owned)
+                .or_else(|| {
+                    options
+                        .output_path
+                        .as_ref()
+                        .map(|output_path| output_path.display().to_string())
+                });
+            // The run is not failed by a notification which cannot be sent
+            if let Err(err) = post_webhook(url, &notification(&summary, report.as_deref())) {
+                eprintln!("Cannot notify the webhook: {err}");
+            }
+        }
+    }
+
+    if let Some(dir) = &options.emit_fixtures {
+        // A code file and an expected metrics file for each minimal test
+        eprintln!(
+            "{} minimal tests written as fixtures to {:?}",
+            summary.fixtures.len() / 2,
+            dir
+        );
+    }
+
+    let mut rejected_tests = 0;
+    if options.interactive {
+        let export_dir = Path::new(matches.value_of("export-dir").unwrap());
+        let review_baseline = matches.value_of("review-baseline").unwrap();
+        let bug_list = matches.value_of("bug-list");
+        let outcome = match review_tests(
+            &summary.review,
+            options.context,
+            export_dir,
+            matches.is_present("wrap"),
+            Path::new(review_baseline),
+            bug_list.map(Path::new),
+            &options.schema,
+        ) {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                eprintln!("Cannot review the minimal tests: {err}");
+                process::exit(1);
+            }
+        };
+        if !outcome.exported.is_empty() {
+            eprintln!(
+                "{} minimal tests exported to {:?}",
+                outcome.exported.len(),
+                export_dir
+            );
+        }
+        if !outcome.accepted.is_empty() {
+            eprintln!(
+                "{} diffs accepted in `{review_baseline}`",
+                outcome.accepted.len()
+            );
+        }
+        for (source_filename, lines_range) in &outcome.rejected {
+            eprintln!(
+                "{source_filename}: minimal test at lines ({}, {}) rejected",
+                lines_range.start_line + 1,
+                lines_range.end_line
+            );
+        }
+        if let (Some(bug_list), false) = (bug_list, outcome.rejected.is_empty()) {
+            eprintln!(
+                "{} minimal tests added to the bug list `{bug_list}`",
+                outcome.rejected.len()
+            );
+        }
+        rejected_tests = outcome.rejected.len();
+    }
+
+    if let Some(accept) = matches.subcommand_matches("accept") {
+        let path = accept.value_of("baseline").unwrap();
+        if let Err(err) = summary.accepted.write(Path::new(path)) {
+            eprintln!("Cannot write the baseline `{path}`: {err}");
+            process::exit(1);
+        }
+        eprintln!("{} diffs accepted in `{path}`", summary.accepted.len());
+    }
+    let failed_pairs = summary.failed_pairs();
+    if failed_pairs > 0 {
+        eprintln!("{failed_pairs} pairs could not be compared");
+    }
+    // The reports of the pairs compared before are written anyway
+    if summary.stopped {
+        process::exit(1);
+    }
+    // A panic is a bug, the comparison is not complete
+    if producer_failed
+        || summary
+            .pairs
+            .iter()
+            .any(|(_, status)| matches!(status, PairStatus::Panicked))
+    {
+        eprintln!("The comparison is incomplete because of an internal error");
+        process::exit(1);
+    }
+
+    if rejected_tests > 0 {
+        eprintln!("{rejected_tests} minimal tests rejected");
+        process::exit(1);
+    }
+
+    if options.baseline.is_some() && summary.total_diffs() > 0 {
+        eprintln!("{} diffs are not in the baseline", summary.total_diffs());
+        process::exit(1);
+    }
+
+    if let Some(previous) = previous_total_diffs {
+        let total = summary.total_diffs();
+        if total > previous {
+            eprintln!("The number of diffs grew from {previous} to {total}");
+            process::exit(1);
+        }
+    }
+
+    if !summary.corrupt.is_empty() {
+        eprintln!("{} corrupt input files found", summary.corrupt.len());
+        if matches.is_present("strict") {
+            process::exit(1);
+        }
+    }
+
+    if matches.is_present("watch") {
+        let path1 = PathBuf::from(pair_matches.value_of_os("first-json").unwrap());
+        let path2 = PathBuf::from(pair_matches.value_of_os("second-json").unwrap());
+        watch(&path1, &path2, &options, num_jobs);
+    }
+}
@@ -0,0 +1,224 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+
+use crate::atomic::{write_atomically, TEMP_PREFIX};
+use crate::sha256::Sha256;
+use crate::summary::Summary;
+
+const MANIFEST_NAME: &str = "manifest.sha256";
+const RUN_MANIFEST_NAME: &str = "manifest.json";
+
+fn file_sha256(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::default();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finish_hex())
+}
+
+/// Writes the checksums of the reports generated in the output directory,
+/// in the format read by `sha256sum --check`.
+pub(crate) fn write_manifest(output_path: &Path, reports: &[String]) -> std::io::Result<()> {
+    let mut reports: Vec<&String> = reports.iter().collect();
+    reports.sort();
+    reports.dedup();
+    write_atomically(&output_path.join(MANIFEST_NAME), |manifest| {
+        for report in reports {
+            let hash = file_sha256(&output_path.join(report))?;
+            writeln!(manifest, "{hash}  {report}")?;
+        }
+        Ok(())
+    })
+}
+
+/// Formats a time as a UTC timestamp in the RFC 3339 format,
+/// e.g. `2024-03-01T12:30:05Z`.
+pub(crate) fn format_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+    // The civil date of a number of days since the epoch, by eras of
+    // 400 years starting on March 1st
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// What a comparison was run on and how, to reproduce and audit it.
+pub(crate) struct Run {
+    // The command line, the program included
+    pub(crate) arguments: Vec<String>,
+    // The compared files or directories
+    pub(crate) inputs: Vec<PathBuf>,
+    pub(crate) started: SystemTime,
+}
+
+/// Writes a `manifest.json` in the output directory with the version of
+/// the tool, the command line, the inputs, when the comparison started and
+/// finished, and the outcome of each compared pair.
+pub(crate) fn write_run_manifest(
+    output_path: &Path,
+    run: &Run,
+    summary: &Summary,
+) -> std::io::Result<()> {
+    let mut pairs: Vec<_> = summary
+        .pairs
+        .iter()
+        .map(|(name, status)| json!({ "name": name, "status": status.name() }))
+        .collect();
+    pairs.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+    // The files are sorted by name
+    let reports: Vec<_> = summary
+        .reports
+        .iter()
+        .map(|(source, report)| {
+            let file = summary.files.iter().find(|file| &file.name == source);
+            json!({
+                "source": source,
+                "report": report,
+                "diffs": file.map_or(0, |file| file.diffs),
+                "metrics": file.map(|file| &file.metrics),
+            })
+        })
+        .collect();
+    let inputs: Vec<PathBuf> = run
+        .inputs
+        .iter()
+        .map(|input| input.canonicalize().unwrap_or_else(|_| input.clone()))
+        .collect();
+    let manifest = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "arguments": run.arguments,
+        "working_directory": std::env::current_dir().ok(),
+        "inputs": inputs,
+        "started": format_timestamp(run.started),
+        "finished": format_timestamp(SystemTime::now()),
+        "pairs": pairs,
+        "reports": reports,
+        "failed_pairs": summary.failed_pairs(),
+        "total_diffs": summary.total_diffs(),
+    });
+    write_atomically(&output_path.join(RUN_MANIFEST_NAME), |file| {
+        file.write_all(serde_json::to_string_pretty(&manifest).unwrap().as_bytes())
+    })
+}
+
+/// What `clean` did, or would do on a dry run, to an output directory.
+#[derive(Default)]
+pub(crate) struct Cleaning {
+    pub(crate) removed: Vec<PathBuf>,
+    // The files listed in the manifest which changed since the run
+    pub(crate) kept: Vec<PathBuf>,
+}
+
+// The artifacts listed in a manifest, with their checksums, skipping the
+// paths which would lead out of the output directory
+fn read_manifest(output_path: &Path) -> std::io::Result<Vec<(String, PathBuf)>> {
+    let manifest = std::fs::read_to_string(output_path.join(MANIFEST_NAME)).map_err(|err| {
+        if err.kind() == std::io::ErrorKind::NotFound {
+            std::io::Error::new(
+                err.kind(),
+                format!("no {MANIFEST_NAME}, the directory holds no run of the tool"),
+            )
+        } else {
+            err
+        }
+    })?;
+    Ok(manifest
+        .lines()
+        .filter_map(|line| line.split_once("  "))
+        .filter(|(_, artifact)| {
+            Path::new(artifact)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        })
+        .map(|(hash, artifact)| (hash.to_owned(), PathBuf::from(artifact)))
+        .collect())
+}
+
+/// Removes the files written by the last run in an output directory: the
+/// artifacts listed in its `manifest.sha256`, the manifests, the temporary
+/// files left by a killed run and the directories left empty. The other
+/// files, and the artifacts changed since the run, are left as they are.
+pub(crate) fn clean(output_path: &Path, dry_run: bool) -> std::io::Result<Cleaning> {
+    let artifacts = read_manifest(output_path)?;
+    let mut cleaning = Cleaning::default();
+    let mut dirs = vec![output_path.to_path_buf()];
+    for (hash, artifact) in artifacts {
+        let path = output_path.join(&artifact);
+        if !path.is_file() {
+            continue;
+        }
+        if file_sha256(&path)? != hash {
+            cleaning.kept.push(path);
+            continue;
+        }
+        if !dry_run {
+            std::fs::remove_file(&path)?;
+        }
+        cleaning.removed.push(path);
+        for dir in artifact.ancestors().skip(1) {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+            dirs.push(output_path.join(dir));
+        }
+    }
+    dirs.sort();
+    dirs.dedup();
+    for dir in &dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with(TEMP_PREFIX) {
+                if !dry_run {
+                    std::fs::remove_file(entry.path())?;
+                }
+                cleaning.removed.push(entry.path());
+            }
+        }
+    }
+    for name in [RUN_MANIFEST_NAME, MANIFEST_NAME] {
+        let path = output_path.join(name);
+        if path.is_file() {
+            if !dry_run {
+                std::fs::remove_file(&path)?;
+            }
+            cleaning.removed.push(path);
+        }
+    }
+    if !dry_run {
+        // The deepest directories first, the output directory stays
+        for dir in dirs.iter().skip(1).rev() {
+            let _ = std::fs::remove_dir(dir);
+        }
+    }
+    Ok(cleaning)
+}
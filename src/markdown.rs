@@ -0,0 +1,135 @@
+use std::io::Write;
+
+use crate::fingerprint::fingerprint;
+use crate::html::snippet_lines;
+use crate::pr_comment::escape_cell;
+use crate::report::{sorted_snippets, Report, ReportWriter};
+use crate::summary::format_change;
+use crate::{LinesRange, SnippetDiff};
+
+/// The report in Markdown, e.g. to be pasted in an issue.
+#[derive(Default)]
+pub(crate) struct MarkdownReport {
+    // Whether the heading of the snippets has been written
+    snippets_started: bool,
+}
+
+fn write_diffs_table(
+    writer: &mut dyn Write,
+    report: &Report,
+    diffs: &[SnippetDiff],
+) -> std::io::Result<()> {
+    let (source_filename, options) = (&report.snippets.source_filename, report.options);
+    writeln!(
+        writer,
+        "| Path | Old | New | Change | Fingerprint |\n|---|---|---|---|---|"
+    )?;
+    for diff in diffs {
+        writeln!(
+            writer,
+            "| `{}` | {} | {} | {} | `{}` |",
+            escape_cell(&diff.path),
+            escape_cell(&diff.old),
+            escape_cell(&diff.new),
+            format_change(diff, options.precision).unwrap_or_default(),
+            fingerprint(source_filename, diff, &options.schema)
+        )?;
+    }
+    writeln!(writer)
+}
+
+fn write_code(
+    writer: &mut dyn Write,
+    report: &Report,
+    lines_range: &LinesRange,
+) -> std::io::Result<()> {
+    let (before, lines, after) =
+        snippet_lines(report.source_file, lines_range, report.options.context);
+    writeln!(writer, "```")?;
+    for line in before.iter().chain(&lines).chain(&after) {
+        writeln!(writer, "{line}")?;
+    }
+    writeln!(writer, "```\n")
+}
+
+impl ReportWriter for MarkdownReport {
+    fn write_header(&mut self, writer: &mut dyn Write, report: &Report) -> std::io::Result<()> {
+        let snippets = report.snippets;
+        writeln!(writer, "# {}\n", snippets.source_filename)?;
+        if snippets.partial {
+            writeln!(
+                writer,
+                "> **Partial report:** the metrics have been recovered from a truncated JSON file\n"
+            )?;
+        }
+        if let Some((shift, moved)) = snippets.line_shift {
+            writeln!(
+                writer,
+                "> **Global line shift detected:** the lines of {moved} spaces moved by {shift:+}, which is not reported as diffs\n"
+            )?;
+        }
+        if snippets.omitted_snippets > 0 {
+            writeln!(
+                writer,
+                "> **Minimal tests left out:** the ones with the largest change are reported, … and {} more\n",
+                snippets.omitted_snippets
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_global_metrics(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+    ) -> std::io::Result<()> {
+        writeln!(writer, "## Global metrics\n")?;
+        write_diffs_table(writer, report, &report.snippets.global_metrics)
+    }
+
+    fn write_snippet(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+        lines_range: &LinesRange,
+        diffs: &[SnippetDiff],
+    ) -> std::io::Result<()> {
+        if !self.snippets_started {
+            writeln!(writer, "## Minimal tests\n")?;
+            self.snippets_started = true;
+        }
+        writeln!(
+            writer,
+            "### Lines ({}, {})\n",
+            lines_range.start_line + 1,
+            lines_range.end_line
+        )?;
+        write_diffs_table(writer, report, diffs)?;
+        write_code(writer, report, lines_range)
+    }
+
+    fn finish(&mut self, writer: &mut dyn Write, report: &Report) -> std::io::Result<()> {
+        let (snippets, options) = (report.snippets, report.options);
+        if !snippets.space_changes.is_empty() {
+            writeln!(writer, "## Added and removed spaces\n")?;
+            write_diffs_table(writer, report, &snippets.space_changes)?;
+        }
+        if !snippets.positions.is_empty() {
+            writeln!(writer, "## Positions\n")?;
+            for (lines_range, diffs) in sorted_snippets(&snippets.positions, options) {
+                writeln!(
+                    writer,
+                    "### Moved space - lines ({}, {})\n",
+                    lines_range.start_line + 1,
+                    lines_range.end_line
+                )?;
+                write_diffs_table(writer, report, diffs)?;
+                write_code(writer, report, lines_range)?;
+            }
+        }
+        if !snippets.filtered.is_empty() {
+            writeln!(writer, "_{} diffs filtered out_", snippets.filtered.len())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use crate::{LinesRange, SnippetDiff};
+
+/// Coalesces the line ranges which overlap or are at most `distance` lines
+/// apart, grouping their diffs under a single snippet.
+pub(crate) fn merge_ranges(
+    snippets_data: HashMap<LinesRange, Vec<SnippetDiff>>,
+    distance: usize,
+) -> HashMap<LinesRange, Vec<SnippetDiff>> {
+    let mut ranges: Vec<(LinesRange, Vec<SnippetDiff>)> = snippets_data.into_iter().collect();
+    ranges.sort_by_key(|(range, _)| (range.start_line, range.end_line));
+
+    let mut merged: Vec<(LinesRange, Vec<SnippetDiff>)> = Vec::with_capacity(ranges.len());
+    for (range, diffs) in ranges {
+        match merged.last_mut() {
+            Some((last, last_diffs)) if range.start_line <= last.end_line + distance => {
+                last.end_line = last.end_line.max(range.end_line);
+                last_diffs.extend(diffs);
+            }
+            _ => merged.push((range, diffs)),
+        }
+    }
+    merged.into_iter().collect()
+}
+
+/// Maps each merged line range to the smallest range enclosing the old
+/// line ranges of the snippets it merged.
+pub(crate) fn merge_old_ranges<'a, I: IntoIterator<Item = &'a LinesRange>>(
+    old_ranges: &HashMap<LinesRange, LinesRange>,
+    merged: I,
+) -> HashMap<LinesRange, LinesRange> {
+    merged
+        .into_iter()
+        .filter_map(|range| {
+            // A merged range encloses whole original ranges
+            old_ranges
+                .iter()
+                .filter(|(original, _)| {
+                    range.start_line <= original.start_line && original.end_line <= range.end_line
+                })
+                .map(|(_, old)| old.clone())
+                .reduce(|merged_old, old| LinesRange {
+                    start_line: merged_old.start_line.min(old.start_line),
+                    end_line: merged_old.end_line.max(old.end_line),
+                })
+                .map(|old| (range.clone(), old))
+        })
+        .collect()
+}
@@ -0,0 +1,354 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{DeserializeSeed, Deserializer, Error, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::{Map, Number, Value};
+
+use crate::schema::Schema;
+
+/// A metric file deserialized into owned structs, for the code which keeps
+/// its spaces apart from the parsed JSON, e.g. the expected metrics of the
+/// minimal tests. The comparison itself walks the JSON through the views
+/// of `space.rs`, since it diffs the values as they are.
+///
+/// The keys of the fields come from the schema, so the structs are read
+/// with a seed holding it rather than derived; the fields which are not in
+/// the schema are kept apart, as `#[serde(flatten)]` would.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MetricsFile {
+    // The unit of the whole source file, named after it
+    pub(crate) root: Space,
+}
+
+/// A space, e.g. a function, a struct or the unit of a file.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Space {
+    // None for the anonymous spaces, e.g. the closures
+    pub(crate) name: Option<String>,
+    pub(crate) kind: Option<String>,
+    // Counted from 1, the end line included
+    pub(crate) start_line: u64,
+    pub(crate) end_line: u64,
+    pub(crate) metrics: Metrics,
+    pub(crate) spaces: Vec<Space>,
+    // The fields outside of the schema, e.g. `source_hash`
+    pub(crate) other: Map<String, Value>,
+}
+
+/// The metrics of a space by family, e.g. `sum` of `cyclomatic`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Metrics {
+    // The families made of numbers, None where the metric is not a number,
+    // e.g. a NaN average, which serde_json writes as null
+    pub(crate) families: BTreeMap<String, BTreeMap<String, Option<Number>>>,
+    // The metrics of another shape, e.g. of another tool, as they are
+    pub(crate) other: Map<String, Value>,
+}
+
+impl MetricsFile {
+    pub(crate) fn from_value(json: &Value, schema: &Schema) -> serde_json::Result<Self> {
+        Ok(Self {
+            root: SpaceSeed { schema }.deserialize(json)?,
+        })
+    }
+
+    /// Returns the space at some indices in the tree of the spaces, e.g.
+    /// `[1, 0]` for the first child of the second child of the root.
+    pub(crate) fn space(&self, path: &[usize]) -> Option<&Space> {
+        path.iter()
+            .try_fold(&self.root, |space, &index| space.spaces.get(index))
+    }
+}
+
+/// Reads a metric file of rust-code-analysis.
+impl<'de> Deserialize<'de> for MetricsFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self {
+            root: SpaceSeed {
+                schema: &Schema::default(),
+            }
+            .deserialize(deserializer)?,
+        })
+    }
+}
+
+impl Space {
+    /// Moves the lines of the space and of its children up by `offset`.
+    pub(crate) fn relocate(&mut self, offset: u64) {
+        self.start_line = self.start_line.saturating_sub(offset);
+        self.end_line = self.end_line.saturating_sub(offset);
+        for space in &mut self.spaces {
+            space.relocate(offset);
+        }
+    }
+
+    /// Returns the JSON of the space, with the keys of the schema.
+    pub(crate) fn to_value(&self, schema: &Schema) -> Value {
+        let mut object = self.other.clone();
+        object.insert(schema.name.clone(), Value::from(self.name.clone()));
+        object.insert(schema.kind.clone(), Value::from(self.kind.clone()));
+        object.insert(schema.start_line.clone(), Value::from(self.start_line));
+        object.insert(schema.end_line.clone(), Value::from(self.end_line));
+        object.insert(schema.metrics.clone(), self.metrics.to_value());
+        object.insert(
+            schema.spaces.clone(),
+            Value::Array(
+                self.spaces
+                    .iter()
+                    .map(|space| space.to_value(schema))
+                    .collect(),
+            ),
+        );
+        Value::Object(object)
+    }
+}
+
+impl Metrics {
+    fn to_value(&self) -> Value {
+        let mut object = self.other.clone();
+        for (family, metrics) in &self.families {
+            let metrics = metrics
+                .iter()
+                .map(|(name, number)| {
+                    (
+                        name.clone(),
+                        number.clone().map_or(Value::Null, Value::Number),
+                    )
+                })
+                .collect();
+            object.insert(family.clone(), Value::Object(metrics));
+        }
+        Value::Object(object)
+    }
+}
+
+// The numbers of a family of metrics, when it is made of numbers only
+fn family(value: &Value) -> Option<BTreeMap<String, Option<Number>>> {
+    value
+        .as_object()?
+        .iter()
+        .map(|(name, value)| match value {
+            Value::Number(number) => Some((name.clone(), Some(number.clone()))),
+            Value::Null => Some((name.clone(), None)),
+            _ => None,
+        })
+        .collect()
+}
+
+// Reads a space with the keys of a schema
+struct SpaceSeed<'a> {
+    schema: &'a Schema,
+}
+
+impl<'de> DeserializeSeed<'de> for SpaceSeed<'_> {
+    type Value = Space;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Space, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SpaceSeed<'_> {
+    type Value = Space;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a space")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Space, A::Error> {
+        let schema = self.schema;
+        let (mut name, mut kind, mut start_line, mut end_line) = (None, None, None, None);
+        let (mut metrics, mut spaces) = (None, None);
+        let mut other = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if key == schema.name {
+                name = map.next_value::<Option<String>>()?;
+            } else if key == schema.kind {
+                kind = map.next_value::<Option<String>>()?;
+            } else if key == schema.start_line {
+                start_line = Some(map.next_value::<u64>()?);
+            } else if key == schema.end_line {
+                end_line = Some(map.next_value::<u64>()?);
+            } else if key == schema.metrics {
+                let value: Map<String, Value> = map.next_value()?;
+                let mut typed = Metrics::default();
+                for (family_name, value) in value {
+                    match family(&value) {
+                        Some(family) => {
+                            typed.families.insert(family_name, family);
+                        }
+                        None => {
+                            typed.other.insert(family_name, value);
+                        }
+                    }
+                }
+                metrics = Some(typed);
+            } else if key == schema.spaces {
+                spaces = Some(map.next_value_seed(SpacesSeed { schema })?);
+            } else {
+                other.insert(key, map.next_value()?);
+            }
+        }
+        let missing = |field: &str| A::Error::missing_field_owned(field);
+        Ok(Space {
+            name,
+            kind,
+            start_line: start_line.ok_or_else(|| missing(&schema.start_line))?,
+            end_line: end_line.ok_or_else(|| missing(&schema.end_line))?,
+            metrics: metrics.ok_or_else(|| missing(&schema.metrics))?,
+            spaces: spaces.unwrap_or_default(),
+            other,
+        })
+    }
+}
+
+// The children of a space
+struct SpacesSeed<'a> {
+    schema: &'a Schema,
+}
+
+impl<'de> DeserializeSeed<'de> for SpacesSeed<'_> {
+    type Value = Vec<Space>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Vec<Space>, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SpacesSeed<'_> {
+    type Value = Vec<Space>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of spaces")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<Space>, A::Error> {
+        let mut spaces = Vec::new();
+        while let Some(space) = seq.next_element_seed(SpaceSeed {
+            schema: self.schema,
+        })? {
+            spaces.push(space);
+        }
+        Ok(spaces)
+    }
+}
+
+// The keys of the schema are not 'static, as Error::missing_field wants
+trait MissingField {
+    fn missing_field_owned(field: &str) -> Self;
+}
+
+impl<E: Error> MissingField for E {
+    fn missing_field_owned(field: &str) -> Self {
+        E::custom(format_args!("missing field `{field}`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn unit() -> Value {
+        json!({
+            "name": "a.rs",
+            "kind": "unit",
+            "start_line": 1,
+            "end_line": 9,
+            "metrics": { "nargs": { "sum": 0, "average": null } },
+            "source_hash": "f00d",
+            "spaces": [{
+                "name": null,
+                "kind": "closure",
+                "start_line": 3,
+                "end_line": 5,
+                "metrics": {
+                    "cyclomatic": { "sum": 2.0 },
+                    "tool": { "rating": "A" },
+                    "tags": ["x"]
+                },
+                "spaces": []
+            }]
+        })
+    }
+
+    #[test]
+    fn typed_fields() {
+        let file: MetricsFile = serde_json::from_value(unit()).unwrap();
+        let root = &file.root;
+        assert_eq!(root.name.as_deref(), Some("a.rs"));
+        assert_eq!((root.start_line, root.end_line), (1, 9));
+        assert_eq!(root.other["source_hash"], "f00d");
+        assert_eq!(root.metrics.families["nargs"]["average"], None);
+        let closure = &root.spaces[0];
+        assert_eq!(
+            (closure.name.as_deref(), closure.kind.as_deref()),
+            (None, Some("closure"))
+        );
+        assert_eq!(
+            closure.metrics.families["cyclomatic"]["sum"],
+            Number::from_f64(2.0)
+        );
+        // The metrics which are not numbers are kept apart
+        assert_eq!(closure.metrics.other["tool"], json!({ "rating": "A" }));
+        assert_eq!(closure.metrics.other["tags"], json!(["x"]));
+    }
+
+    #[test]
+    fn back_to_json() {
+        let json = unit();
+        let file = MetricsFile::from_value(&json, &Schema::default()).unwrap();
+        assert_eq!(file.root.to_value(&Schema::default()), json);
+    }
+
+    #[test]
+    fn schema_keys() {
+        let schema = Schema {
+            start_line: "from".to_owned(),
+            spaces: "children".to_owned(),
+            ..Schema::default()
+        };
+        let json = json!({
+            "name": "a.py", "kind": "module", "from": 1, "end_line": 2,
+            "metrics": {}, "children": []
+        });
+        let file = MetricsFile::from_value(&json, &schema).unwrap();
+        assert_eq!(file.root.start_line, 1);
+        assert_eq!(file.root.to_value(&schema), json);
+    }
+
+    #[test]
+    fn layout_errors() {
+        let error = |json: Value| {
+            MetricsFile::from_value(&json, &Schema::default())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error(json!({ "start_line": 1, "metrics": {} })),
+            "missing field `end_line`"
+        );
+        assert!(
+            error(json!({ "start_line": "1", "end_line": 2, "metrics": {} }))
+                .starts_with("invalid type: string \"1\", expected u64")
+        );
+        assert!(error(json!([])).starts_with("invalid type: sequence, expected a space"));
+        assert!(error(json!({
+            "start_line": 1, "end_line": 2, "metrics": {}, "spaces": [1]
+        }))
+        .starts_with("invalid type: integer `1`, expected a space"));
+    }
+
+    #[test]
+    fn lookup_and_relocate() {
+        let file = MetricsFile::from_value(&unit(), &Schema::default()).unwrap();
+        assert_eq!(file.space(&[0]), file.root.spaces.first());
+        assert_eq!(file.space(&[0, 0]), None);
+        let mut unit = file.root;
+        unit.relocate(2);
+        assert_eq!((unit.start_line, unit.end_line), (0, 7));
+        let closure = &unit.spaces[0];
+        assert_eq!((closure.start_line, closure.end_line), (1, 3));
+    }
+}
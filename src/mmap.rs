@@ -0,0 +1,91 @@
+use std::fs::File;
+use std::ops::Deref;
+use std::path::Path;
+
+/// The bytes of a file, either read into a buffer or mapped in memory.
+pub(crate) enum FileBytes {
+    Read(Vec<u8>),
+    #[cfg(unix)]
+    Mapped(Mmap),
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileBytes::Read(bytes) => bytes,
+            #[cfg(unix)]
+            FileBytes::Mapped(mmap) => mmap,
+        }
+    }
+}
+
+/// A read-only private mapping of a whole file.
+#[cfg(unix)]
+pub(crate) struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+#[cfg(unix)]
+impl Mmap {
+    fn map(file: &File, len: usize) -> std::io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        // SAFETY: a new mapping of `len` bytes of an open file, checked
+        // for failure; it outlives the file descriptor, which can be closed
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self { ptr, len })
+    }
+}
+
+#[cfg(unix)]
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the mapping is readable for `len` bytes until it is dropped
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        // SAFETY: the mapping is not used after being dropped
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+/// Reads a file, mapping it in memory with `mmap` rather than copying
+/// it into a buffer, so that a large metric file is only held once by
+/// the page cache. The file must not be truncated while it is mapped.
+/// The empty files and the platforms other than Unix are always read.
+pub(crate) fn read_file(path: &Path, mmap: bool) -> std::io::Result<FileBytes> {
+    #[cfg(unix)]
+    if mmap {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len > 0 {
+            return Mmap::map(&file, len).map(FileBytes::Mapped);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = mmap;
+    std::fs::read(path).map(FileBytes::Read)
+}
@@ -0,0 +1,165 @@
+use serde_json::{Map, Value};
+
+use crate::binary::{float, map_key, utf8, Cursor};
+
+struct Decoder<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl Decoder<'_> {
+    fn int(&mut self, len: usize) -> Result<i64, String> {
+        let n = self.cursor.uint(len)?;
+        let shift = 64 - 8 * len as u32;
+        Ok((n << shift) as i64 >> shift)
+    }
+
+    fn string(&mut self, len: usize) -> Result<String, String> {
+        let start = self.cursor.pos;
+        let bytes = self.cursor.take(len)?.to_vec();
+        utf8(start, bytes)
+    }
+
+    fn array(&mut self, len: usize, depth: usize) -> Result<Value, String> {
+        let mut items = Vec::with_capacity(len.min(self.cursor.remaining()));
+        for _ in 0..len {
+            items.push(self.value(depth + 1)?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn map(&mut self, len: usize, depth: usize) -> Result<Value, String> {
+        let mut map = Map::new();
+        for _ in 0..len {
+            let key = map_key(self.value(depth + 1)?);
+            map.insert(key, self.value(depth + 1)?);
+        }
+        Ok(Value::Object(map))
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, String> {
+        self.cursor.check_depth(depth)?;
+        let marker = self.cursor.take(1)?[0];
+        Ok(match marker {
+            0x00..=0x7f => Value::from(marker),
+            0x80..=0x8f => self.map(usize::from(marker & 0x0f), depth)?,
+            0x90..=0x9f => self.array(usize::from(marker & 0x0f), depth)?,
+            0xa0..=0xbf => Value::String(self.string(usize::from(marker & 0x1f))?),
+            0xc0 => Value::Null,
+            0xc2 => Value::Bool(false),
+            0xc3 => Value::Bool(true),
+            // Binary data, as serde_json writes bytes
+            0xc4..=0xc6 => {
+                let len = self.cursor.uint(1 << (marker - 0xc4))? as usize;
+                let bytes = self.cursor.take(len)?;
+                Value::Array(bytes.iter().map(|&b| Value::from(b)).collect())
+            }
+            0xca => float(f64::from(f32::from_bits(self.cursor.uint(4)? as u32))),
+            0xcb => float(f64::from_bits(self.cursor.uint(8)?)),
+            0xcc..=0xcf => Value::from(self.cursor.uint(1 << (marker - 0xcc))?),
+            0xd0..=0xd3 => Value::from(self.int(1 << (marker - 0xd0))?),
+            0xd9..=0xdb => {
+                let len = self.cursor.uint(1 << (marker - 0xd9))? as usize;
+                Value::String(self.string(len)?)
+            }
+            0xdc | 0xdd => {
+                let len = self.cursor.uint(2 << (marker - 0xdc))? as usize;
+                self.array(len, depth)?
+            }
+            0xde | 0xdf => {
+                let len = self.cursor.uint(2 << (marker - 0xde))? as usize;
+                self.map(len, depth)?
+            }
+            0xe0..=0xff => Value::from(marker as i8),
+            _ => {
+                self.cursor.pos -= 1;
+                return Err(self.cursor.error("extension types are not supported"));
+            }
+        })
+    }
+}
+
+/// Decodes a MessagePack document into the JSON value with the same
+/// structure. The keys of the maps which are not strings are written as
+/// JSON, e.g. `1`.
+pub(crate) fn parse_msgpack(bytes: &[u8]) -> Result<Value, String> {
+    let mut decoder = Decoder {
+        cursor: Cursor::new(bytes),
+    };
+    let value = decoder.value(0)?;
+    decoder.cursor.end(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn values() {
+        assert_eq!(
+            parse_msgpack(&[0x94, 0x01, 0xff, 0xd0, 0x80, 0xa1, b'a']).unwrap(),
+            json!([1, -1, -128, "a"])
+        );
+        assert_eq!(
+            parse_msgpack(&[0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]).unwrap(),
+            json!(1.5)
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(
+            parse_msgpack(&[0x92, 0x01]).unwrap_err(),
+            "byte 2: unexpected end of the file"
+        );
+        assert_eq!(
+            parse_msgpack(&[0xd9, 0x05, b'a']).unwrap_err(),
+            "byte 2: unexpected end of the file"
+        );
+        // The length of the array reserves no memory
+        assert_eq!(
+            parse_msgpack(&[0xdd, 0xff, 0xff, 0xff, 0xff]).unwrap_err(),
+            "byte 5: unexpected end of the file"
+        );
+        assert_eq!(
+            parse_msgpack(&[0xc0, 0xc0]).unwrap_err(),
+            "byte 1: unexpected data after the document"
+        );
+    }
+
+    #[test]
+    fn too_deep() {
+        let mut bytes = vec![0x91; 200];
+        bytes.push(0x00);
+        assert_eq!(
+            parse_msgpack(&bytes).unwrap_err(),
+            "byte 129: too deeply nested"
+        );
+    }
+
+    #[test]
+    fn not_a_number() {
+        for bytes in [
+            &[0xca, 0x7f, 0xc0, 0x00, 0x00][..],
+            &[0xcb, 0x7f, 0xf0, 0, 0, 0, 0, 0, 0],
+        ] {
+            assert_eq!(parse_msgpack(bytes).unwrap(), Value::Null);
+        }
+    }
+
+    #[test]
+    fn non_string_keys() {
+        assert_eq!(
+            parse_msgpack(&[0x82, 0x01, 0x02, 0xc3, 0x03]).unwrap(),
+            json!({ "1": 2, "true": 3 })
+        );
+    }
+
+    #[test]
+    fn extension_types() {
+        assert_eq!(
+            parse_msgpack(&[0x91, 0xd4, 0x01, 0x00]).unwrap_err(),
+            "byte 1: extension types are not supported"
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde_json::Value;
+
+use crate::input::{parse_metrics, InputFormat};
+use crate::ndjson::document_name;
+use crate::schema::Schema;
+use crate::walk::{walk_files, WalkOptions};
+
+/// Indexes the metric files of a directory by the name of their root space.
+/// The names found in more than one file are left out, since their
+/// files cannot be paired.
+fn index_by_name(
+    root: &Path,
+    num_threads: usize,
+    walk_options: &WalkOptions,
+    input_format: InputFormat,
+    schema: &Schema,
+) -> HashMap<String, PathBuf> {
+    let files: Mutex<HashMap<String, Vec<PathBuf>>> = Mutex::new(HashMap::new());
+    walk_files(root, num_threads, walk_options, |relative| {
+        if !walk_options.is_metric_file(&relative) {
+            return;
+        }
+        let path = root.join(relative);
+        let json: Value = match std::fs::read(&path) {
+            Ok(bytes) => match parse_metrics(&bytes, input_format, &path) {
+                Ok(json) => json,
+                Err(err) => {
+                    eprintln!("Cannot read the name of {:?}: {}", path, err);
+                    return;
+                }
+            },
+            Err(err) => {
+                eprintln!("Cannot read the name of {:?}: {}", path, err);
+                return;
+            }
+        };
+        match document_name(&json, schema) {
+            Some(name) => files
+                .lock()
+                .unwrap()
+                .entry(name.to_owned())
+                .or_default()
+                .push(path),
+            None => eprintln!("{:?} has no name", path),
+        }
+    });
+
+    let mut index = HashMap::new();
+    for (name, mut paths) in files.into_inner().unwrap() {
+        if paths.len() > 1 {
+            paths.sort();
+            eprintln!(
+                "`{}` is the name of several files in {:?}: {:?}",
+                name, root, paths
+            );
+        } else if let Some(path) = paths.pop() {
+            index.insert(name, path);
+        }
+    }
+    index
+}
+
+/// Pairs the metric files of two directories whose root spaces have
+/// the same name, e.g. the same source file, wherever they are and
+/// however they are named, calling `on_pair` for each pair.
+pub(crate) fn pair_by_name<F: Fn(PathBuf, PathBuf)>(
+    root1: &Path,
+    root2: &Path,
+    num_threads: usize,
+    walk_options: &WalkOptions,
+    input_format: InputFormat,
+    schema: &Schema,
+    on_pair: F,
+) {
+    let mut index1 = index_by_name(root1, num_threads, walk_options, input_format, schema);
+    let index2 = index_by_name(root2, num_threads, walk_options, input_format, schema);
+    let mut names: Vec<&String> = index2.keys().collect();
+    names.sort();
+    for name in names {
+        match index1.remove(name) {
+            Some(path1) => on_pair(path1, index2[name].clone()),
+            None => eprintln!("`{}` is only in {:?}", name, root2),
+        }
+    }
+    let mut names: Vec<&String> = index1.keys().collect();
+    names.sort();
+    for name in names {
+        eprintln!("`{}` is only in {:?}", name, root1);
+    }
+}
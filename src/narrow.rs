@@ -0,0 +1,38 @@
+use crate::space::Space;
+
+/// Descends from a changed space into its children while `descend` holds,
+/// as long as exactly one child has the same metric changed: that child is
+/// the smallest space still enclosing the diff.
+///
+/// `space1` and `space2` are the old and the new version of the space,
+/// the narrowed old and new spaces are returned, with the indices of the
+/// children descended into.
+pub(crate) fn narrow_space<'a, F: Fn(Space<'a>) -> bool>(
+    mut space1: Space<'a>,
+    mut space2: Space<'a>,
+    metric_path: &str,
+    descend: F,
+) -> (Space<'a>, Space<'a>, Vec<usize>) {
+    let mut path = Vec::new();
+    while descend(space2) {
+        let (children1, children2) = match (space1.spaces(), space2.spaces()) {
+            (Some(children1), Some(children2)) => (children1, children2),
+            _ => break,
+        };
+        let mut changed = children1
+            .zip(children2)
+            .enumerate()
+            .filter(|(_, (child1, child2))| {
+                child1.metric(metric_path) != child2.metric(metric_path)
+            });
+        match (changed.next(), changed.next()) {
+            (Some((index, (child1, child2))), None) => {
+                space1 = child1;
+                space2 = child2;
+                path.push(index);
+            }
+            _ => break,
+        }
+    }
+    (space1, space2, path)
+}
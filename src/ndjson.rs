@@ -0,0 +1,48 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use serde_json::{Deserializer, Value};
+
+use crate::repair::repair_truncated;
+use crate::schema::Schema;
+use crate::space::Space;
+
+/// Calls `on_document` for each JSON document of a stream, either separated
+/// by newlines or simply concatenated one after the other.
+///
+/// When `repair` is set, a truncated last document is salvaged and passed
+/// to `on_document` marked as partial.
+pub(crate) fn for_each_document<F: FnMut(Value, bool)>(
+    path: &Path,
+    repair: bool,
+    mut on_document: F,
+) -> std::io::Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut documents = Deserializer::from_reader(reader).into_iter::<Value>();
+    while let Some(document) = documents.next() {
+        match document {
+            Ok(document) => on_document(document, false),
+            Err(err) => {
+                if repair && err.is_eof() {
+                    let mut file = File::open(path)?;
+                    file.seek(SeekFrom::Start(documents.byte_offset() as u64))?;
+                    let mut rest = Vec::new();
+                    file.read_to_end(&mut rest)?;
+                    if let Some(document) = repair_truncated(&rest) {
+                        on_document(document, true);
+                        break;
+                    }
+                }
+                eprintln!("Cannot parse {:?}: {}", path, err);
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the source file name a document refers to.
+pub(crate) fn document_name<'a>(document: &'a Value, schema: &'a Schema) -> Option<&'a str> {
+    Space::new(document, schema).name()
+}
@@ -1,12 +1,39 @@
 use std::fs::{self, File};
-use std::io::{Error, ErrorKind, Read};
+use std::io::{Error, Read};
 use std::path::Path;
 
-use encoding_rs::{CoderResult, SHIFT_JIS};
+use encoding_rs::{
+    DecoderResult, Encoding, EUC_KR, GBK, SHIFT_JIS, UTF_16BE, UTF_16LE, WINDOWS_1252,
+};
+
+/// The encodings tried in turn on a source file which is not UTF-8, the
+/// first which decodes it without errors being used.
+#[derive(Debug)]
+pub(crate) struct Encodings(Vec<&'static Encoding>);
+
+impl Default for Encodings {
+    // Windows-1252 decodes any byte, so it comes last
+    fn default() -> Self {
+        Self(vec![SHIFT_JIS, EUC_KR, GBK, WINDOWS_1252])
+    }
+}
+
+impl Encodings {
+    /// Parses a comma-separated list of encoding labels, e.g.
+    /// `latin1,shift_jis`, returning the first unknown label as the error.
+    pub(crate) fn parse(labels: &str) -> Result<Self, &str> {
+        labels
+            .split(',')
+            .map(str::trim)
+            .map(|label| Encoding::for_label(label.as_bytes()).ok_or(label))
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
 
 // https://github.com/mozilla/rust-code-analysis/blob/master/src/tools.rs#L44
 pub(crate) fn read_file_with_eol(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
-    let file_size = fs::metadata(&path).map_or(1024 * 1024, |m| m.len() as usize);
+    let file_size = fs::metadata(path).map_or(1024 * 1024, |m| m.len() as usize);
     if file_size <= 3 {
         // this file is very likely almost empty... so nothing to do on it
         return Ok(None);
@@ -15,25 +42,24 @@ pub(crate) fn read_file_with_eol(path: &Path) -> std::io::Result<Option<Vec<u8>>
     let mut file = File::open(path)?;
 
     let mut start = vec![0; 64.min(file_size)];
-    let start = if file.read_exact(&mut start).is_ok() {
+    let (start, utf16) = if file.read_exact(&mut start).is_ok() {
         // Skip the bom if one
-        if start[..2] == [b'\xFE', b'\xFF'] || start[..2] == [b'\xFF', b'\xFE'] {
-            &start[2..]
+        if start[..2] == [b'\xFE', b'\xFF'] {
+            (&start[2..], Some(UTF_16BE))
+        } else if start[..2] == [b'\xFF', b'\xFE'] {
+            (&start[2..], Some(UTF_16LE))
         } else if start[..3] == [b'\xEF', b'\xBB', b'\xBF'] {
-            &start[3..]
+            (&start[3..], None)
         } else {
-            &start
+            (&start[..], None)
         }
     } else {
         return Ok(None);
     };
 
-    // so start contains more or less 64 chars
-    let mut head = String::from_utf8_lossy(start).into_owned();
-    // The last char could be wrong because we were in the middle of an utf-8 sequence
-    head.pop();
-    // now check if there is an invalid char
-    if head.contains('\u{FFFD}') {
+    // A NUL byte out of UTF-16 means a binary file, the other encodings
+    // are decoded later
+    if utf16.is_none() && start.contains(&0) {
         return Ok(None);
     }
 
@@ -42,35 +68,58 @@ pub(crate) fn read_file_with_eol(path: &Path) -> std::io::Result<Option<Vec<u8>>
 
     file.read_to_end(&mut data)?;
 
+    if let Some(utf16) = utf16 {
+        data = utf16
+            .decode_without_bom_handling(&data)
+            .0
+            .into_owned()
+            .into_bytes();
+    }
+
     remove_blank_lines(&mut data);
 
     Ok(Some(data))
 }
 
-pub(crate) fn encode_to_utf8(buf: &[u8]) -> std::io::Result<String> {
-    let mut decoder = SHIFT_JIS.new_decoder();
-
-    let mut buffer_bytes = [0u8; 4096];
-    let buffer_str = match std::str::from_utf8_mut(&mut buffer_bytes[..]) {
-        Ok(buffer_str) => buffer_str,
-        Err(_) => {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "Cannot convert to str the temporary buffer.",
-            ))
+// Decodes the input by chunks, the output growing as they are decoded,
+// so that neither is limited in size. None when it is malformed.
+fn decode(buf: &[u8], encoding: &'static Encoding) -> Option<String> {
+    const CHUNK_SIZE: usize = 64 * 1024;
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut decoded = String::with_capacity(buf.len());
+    let mut chunks = buf.chunks(CHUNK_SIZE).peekable();
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let mut read = 0;
+        loop {
+            let (result, chunk_read) =
+                decoder.decode_to_string_without_replacement(&chunk[read..], &mut decoded, last);
+            read += chunk_read;
+            match result {
+                DecoderResult::InputEmpty => break,
+                DecoderResult::OutputFull => decoded.reserve(
+                    decoder
+                        .max_utf8_buffer_length_without_replacement(chunk.len() - read)
+                        .unwrap_or(CHUNK_SIZE),
+                ),
+                DecoderResult::Malformed(..) => return None,
+            }
         }
-    };
-
-    let (result, _, _, _) = decoder.decode_to_str(buf, buffer_str, true);
-
-    if let CoderResult::InputEmpty = result {
-        Ok(buffer_str.to_owned())
-    } else {
-        Err(Error::new(
-            ErrorKind::Other,
-            "Cannot complete the conversion process.",
-        ))
     }
+    Some(decoded)
+}
+
+/// Decodes a source file which is not UTF-8 with the first of the
+/// encodings which decodes it without errors.
+pub(crate) fn encode_to_utf8(
+    buf: &[u8],
+    encodings: &Encodings,
+) -> std::io::Result<(String, &'static Encoding)> {
+    encodings
+        .0
+        .iter()
+        .find_map(|&encoding| decode(buf, encoding).map(|decoded| (decoded, encoding)))
+        .ok_or_else(|| Error::other("Cannot complete the conversion process."))
 }
 
 fn remove_blank_lines(data: &mut Vec<u8>) {
@@ -0,0 +1,71 @@
+use std::io::{Error, ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+use serde_json::{json, Value};
+
+use crate::summary::Summary;
+
+/// Returns the notification of a run, with a `text` shown by the Slack and
+/// Teams incoming webhooks and the counts for the other receivers.
+pub(crate) fn notification(summary: &Summary, report: Option<&str>) -> Value {
+    let total_diffs = summary.total_diffs();
+    let mut text = format!(
+        "json-minimal-tests found {total_diffs} diffs in {} files",
+        summary.files.len()
+    );
+    let failed_pairs = summary.failed_pairs();
+    if failed_pairs > 0 {
+        text.push_str(&format!(", {failed_pairs} pairs failed"));
+    }
+    if let Some(report) = report {
+        text.push_str(&format!(": {report}"));
+    }
+    let metrics: serde_json::Map<String, Value> = summary
+        .metrics
+        .iter()
+        .map(|(name, stats)| (name.clone(), json!(stats.spaces)))
+        .collect();
+    json!({
+        "text": text,
+        "total_diffs": total_diffs,
+        "files_with_diffs": summary.files.len(),
+        "failed_pairs": failed_pairs,
+        "metrics": metrics,
+        "report": report,
+    })
+}
+
+/// POSTs a notification to a webhook with curl, which handles the proxies
+/// and the certificates of the system.
+pub(crate) fn post_webhook(url: &str, notification: &Value) -> std::io::Result<()> {
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time", "30"])
+        .args(["--header", "Content-Type: application/json"])
+        .args(["--data-binary", "@-"])
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => Error::new(
+                ErrorKind::NotFound,
+                "cannot find `curl`, install it to notify a webhook",
+            ),
+            _ => err,
+        })?;
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(notification.to_string().as_bytes())?;
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::other(format!(
+            "curl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
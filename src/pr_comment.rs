@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::summary::Summary;
+
+// Metrics listed in the comment, the ones changed in the most spaces
+const TOP_METRICS: usize = 10;
+
+/// Escapes the characters which would break a cell of a Markdown table.
+pub(crate) fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// Writes a short Markdown comment about the diffs, to be posted on
+/// a pull request: the counts, the files with the most severe diffs
+/// and the metrics changed the most, linking the reports when the URL
+/// of the uploaded output directory is known.
+pub(crate) fn write_pr_comment<W: Write>(
+    writer: &mut W,
+    summary: &Summary,
+    top: usize,
+    precision: usize,
+    artifacts_url: Option<&str>,
+) -> std::io::Result<()> {
+    let artifacts_url = artifacts_url.map(|url| url.trim_end_matches('/'));
+    writeln!(writer, "### Metric diffs\n")?;
+    writeln!(
+        writer,
+        "**{} diffs** in **{} files**",
+        summary.total_diffs(),
+        summary.files.len()
+    )?;
+
+    let mut statuses: BTreeMap<&str, usize> = BTreeMap::new();
+    for (_, status) in &summary.pairs {
+        *statuses.entry(status.name()).or_default() += 1;
+    }
+    if !statuses.is_empty() {
+        let statuses: Vec<String> = statuses
+            .iter()
+            .map(|(status, count)| format!("{count} {status}"))
+            .collect();
+        writeln!(
+            writer,
+            "\n{} pairs compared: {}",
+            summary.pairs.len(),
+            statuses.join(", ")
+        )?;
+    }
+
+    let warnings = [
+        (summary.corrupt.len(), "corrupt inputs"),
+        (summary.partial.len(), "partial reports"),
+        (summary.stale.len(), "stale sources"),
+    ];
+    for (count, warning) in warnings {
+        if count > 0 {
+            writeln!(writer, "\n:warning: {count} {warning}")?;
+        }
+    }
+
+    let regressions = summary.top_regressions(top);
+    if !regressions.is_empty() {
+        writeln!(
+            writer,
+            "\n#### Top regressions\n\n| Severity | Diffs | File |\n|---:|---:|---|"
+        )?;
+        for file in regressions {
+            let name = escape_cell(&file.name);
+            let report = summary.reports.get(&file.name);
+            let name = match (artifacts_url, report) {
+                (Some(url), Some(report)) => format!("[{name}]({url}/{report})"),
+                _ => format!("`{name}`"),
+            };
+            writeln!(
+                writer,
+                "| {:.2} | {} | {} |",
+                file.severity, file.diffs, name
+            )?;
+        }
+    }
+
+    if !summary.metrics.is_empty() {
+        let mut metrics: Vec<_> = summary.metrics.iter().collect();
+        metrics.sort_by(|a, b| b.1.spaces.cmp(&a.1.spaces).then_with(|| a.0.cmp(b.0)));
+        writeln!(
+            writer,
+            "\n#### Metrics\n\n| Metric | Spaces | Delta |\n|---|---:|---:|"
+        )?;
+        for (name, stats) in metrics.into_iter().take(TOP_METRICS) {
+            writeln!(
+                writer,
+                "| `{}` | {} | {:+.precision$} |",
+                escape_cell(name),
+                stats.spaces,
+                stats.delta
+            )?;
+        }
+    }
+
+    if let Some(url) = artifacts_url {
+        writeln!(
+            writer,
+            "\n[All the reports]({url}) ({} files)",
+            summary.artifacts.len()
+        )?;
+    }
+    writeln!(
+        writer,
+        "\n<sub>The severity of a file sums the relative changes of its metrics.</sub>"
+    )
+}
@@ -0,0 +1,101 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+#[derive(Default)]
+pub(crate) struct Prescan {
+    pub(crate) identical: Vec<(PathBuf, PathBuf)>,
+    pub(crate) different: Vec<(PathBuf, PathBuf)>,
+}
+
+// Fills the buffer as much as the file allows, returning the bytes read
+fn read_chunk(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+// Compares the bytes of two files chunk by chunk, stopping at the first
+// difference, after their lengths
+fn same_bytes(path1: &Path, path2: &Path) -> std::io::Result<bool> {
+    if std::fs::metadata(path1)?.len() != std::fs::metadata(path2)?.len() {
+        return Ok(false);
+    }
+    let mut file1 = BufReader::new(File::open(path1)?);
+    let mut file2 = BufReader::new(File::open(path2)?);
+    let mut buffer1 = vec![0; 64 * 1024];
+    let mut buffer2 = vec![0; 64 * 1024];
+    loop {
+        let read1 = read_chunk(&mut file1, &mut buffer1)?;
+        let read2 = read_chunk(&mut file2, &mut buffer2)?;
+        if buffer1[..read1] != buffer2[..read2] {
+            return Ok(false);
+        }
+        if read1 == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+// Whether a file is empty or only holds whitespace, stopping at the
+// first other byte
+fn is_blank(path: &Path) -> std::io::Result<bool> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = read_chunk(&mut file, &mut buffer)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        if !buffer[..read].iter().all(u8::is_ascii_whitespace) {
+            return Ok(false);
+        }
+    }
+}
+
+fn is_identical(path1: &Path, path2: &Path) -> bool {
+    // Unreadable files are considered different, so that the error
+    // is reported when they are compared, and so are blank files,
+    // which are truncated inputs rather than pairs without diffs
+    same_bytes(path1, path2).unwrap_or(false) && !is_blank(path1).unwrap_or(true)
+}
+
+/// Compares the bytes of the files of each pair on `num_jobs` threads and
+/// partitions the pairs into identical and different ones, before any
+/// JSON parsing.
+pub(crate) fn prescan(pairs: Vec<(PathBuf, PathBuf)>, num_jobs: usize) -> Prescan {
+    let chunk_size = pairs.len().div_ceil(num_jobs.max(1)).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = pairs
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut prescan = Prescan::default();
+                    for (path1, path2) in chunk {
+                        if is_identical(path1, path2) {
+                            prescan.identical.push((path1.clone(), path2.clone()));
+                        } else {
+                            prescan.different.push((path1.clone(), path2.clone()));
+                        }
+                    }
+                    prescan
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .fold(Prescan::default(), |mut total, handle| {
+                let prescan = handle.join().unwrap();
+                total.identical.extend(prescan.identical);
+                total.different.extend(prescan.different);
+                total
+            })
+    })
+}
@@ -0,0 +1,47 @@
+use serde_json::Value;
+
+/// Tries to salvage a truncated JSON document, cutting it after its last
+/// complete element and closing the arrays and objects left open.
+pub(crate) fn repair_truncated(text: &[u8]) -> Option<Value> {
+    let mut stack = Vec::new();
+    let mut in_string = false;
+    let mut escape = false;
+    // Position where the text can be cut and the containers open there
+    let mut cut: Option<(usize, Vec<u8>)> = None;
+
+    for (i, &c) in text.iter().enumerate() {
+        if in_string {
+            if escape {
+                escape = false;
+            } else if c == b'\\' {
+                escape = true;
+            } else if c == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                stack.push(if c == b'{' { b'}' } else { b']' });
+                cut = Some((i + 1, stack.clone()));
+            }
+            b'}' | b']' => {
+                stack.pop();
+                if stack.is_empty() {
+                    // The document is complete, nothing to repair
+                    return serde_json::from_slice(&text[..=i]).ok();
+                }
+                cut = Some((i + 1, stack.clone()));
+            }
+            // The element before a comma is complete
+            b',' if !stack.is_empty() => cut = Some((i, stack.clone())),
+            _ => {}
+        }
+    }
+
+    let (cut, stack) = cut?;
+    let mut repaired = text[..cut].to_vec();
+    repaired.extend(stack.iter().rev());
+    serde_json::from_slice(&repaired).ok()
+}
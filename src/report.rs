@@ -0,0 +1,81 @@
+use std::cmp::Reverse;
+use std::io::Write;
+
+use crate::html::diff_severity;
+use crate::{CodeSnippets, LinesRange, Options, SnippetDiff};
+
+/// What the report of a source file is written from.
+pub(crate) struct Report<'a> {
+    // Name of the report, e.g. src_lib.rs.html
+    pub(crate) output_filename: &'a str,
+    // The source file as it is, each writer escapes it for its format
+    pub(crate) source_file: &'a str,
+    pub(crate) snippets: &'a CodeSnippets,
+    pub(crate) options: &'a Options,
+}
+
+/// A format of the reports of the source files.
+///
+/// [`write_report`] calls `write_header` first, `write_global_metrics`
+/// when the whole file has diffs, `write_snippet` for each minimal test
+/// and `finish` last, to write the sections left, e.g. the added and
+/// removed spaces, and to close the report. To add a format, implement
+/// this trait, add a variant to `Format` with the extension of its files
+/// and return the writer for it from `write_report` in main.rs.
+pub(crate) trait ReportWriter {
+    fn write_header(&mut self, writer: &mut dyn Write, report: &Report) -> std::io::Result<()>;
+
+    fn write_global_metrics(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+    ) -> std::io::Result<()>;
+
+    fn write_snippet(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+        lines_range: &LinesRange,
+        diffs: &[SnippetDiff],
+    ) -> std::io::Result<()>;
+
+    fn finish(&mut self, writer: &mut dyn Write, report: &Report) -> std::io::Result<()>;
+}
+
+/// Returns the snippets in the order they are reported: the most severe
+/// first when there are severity rules, then in the order of their lines.
+pub(crate) fn sorted_snippets<'a>(
+    snippets_data: impl IntoIterator<Item = (&'a LinesRange, &'a Vec<SnippetDiff>)>,
+    options: &Options,
+) -> Vec<(&'a LinesRange, &'a Vec<SnippetDiff>)> {
+    let mut snippets_data: Vec<_> = snippets_data.into_iter().collect();
+    snippets_data.sort_by_key(|(lines_range, diffs)| {
+        (
+            Reverse(
+                diffs
+                    .iter()
+                    .filter_map(|diff| diff_severity(diff, options))
+                    .max(),
+            ),
+            lines_range.start_line,
+            lines_range.end_line,
+        )
+    });
+    snippets_data
+}
+
+/// Writes a report in the format of a report writer.
+pub(crate) fn write_report(
+    writer: &mut dyn Write,
+    report_writer: &mut dyn ReportWriter,
+    report: &Report,
+) -> std::io::Result<()> {
+    report_writer.write_header(writer, report)?;
+    if !report.snippets.global_metrics.is_empty() {
+        report_writer.write_global_metrics(writer, report)?;
+    }
+    for (lines_range, diffs) in sorted_snippets(&report.snippets.snippets_data, report.options) {
+        report_writer.write_snippet(writer, report, lines_range, diffs)?;
+    }
+    report_writer.finish(writer, report)
+}
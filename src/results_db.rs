@@ -0,0 +1,348 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{Error, ErrorKind, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+
+use crate::csv::escape_field;
+use crate::fingerprint::fingerprint;
+use crate::html::space_path;
+use crate::manifest::{format_timestamp, Run};
+use crate::schema::Schema;
+use crate::summary::metric_name;
+use crate::{CodeSnippets, LinesRange, SnippetDiff};
+
+// The tables are created by the first run recording its diffs
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    version TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    started TEXT NOT NULL,
+    finished TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS diffs (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    file TEXT NOT NULL,
+    space TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    path TEXT NOT NULL,
+    old TEXT NOT NULL,
+    new TEXT NOT NULL,
+    start_line INTEGER,
+    end_line INTEGER,
+    fingerprint TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS diffs_run_metric ON diffs (run_id, metric);
+";
+
+/// A diff of a run, as recorded in the results database.
+pub(crate) struct DbDiff {
+    file: String,
+    space: String,
+    metric: String,
+    diff: SnippetDiff,
+    // The lines of the minimal test or of the space, when the diff has one
+    lines_range: Option<LinesRange>,
+    fingerprint: String,
+}
+
+/// Returns the reported diffs of a source file, to be recorded.
+pub(crate) fn db_diffs(snippets: &CodeSnippets, schema: &Schema) -> Vec<DbDiff> {
+    let without_lines = snippets
+        .global_metrics
+        .iter()
+        .chain(&snippets.space_changes)
+        .map(|diff| (None, diff));
+    let with_lines = snippets
+        .snippets_data
+        .iter()
+        .chain(&snippets.positions)
+        .flat_map(|(lines_range, diffs)| diffs.iter().map(move |diff| (Some(lines_range), diff)));
+    without_lines
+        .chain(with_lines)
+        .map(|(lines_range, diff)| DbDiff {
+            file: snippets.source_filename.clone(),
+            space: space_path(&diff.path).to_owned(),
+            metric: metric_name(&diff.path, schema).to_owned(),
+            diff: diff.clone(),
+            lines_range: lines_range.cloned(),
+            fingerprint: fingerprint(&snippets.source_filename, diff, schema),
+        })
+        .collect()
+}
+
+// A text literal of any content, written as the hexadecimal digits of its
+// bytes, so that no quote, newline or NUL of a value reaches the script
+fn quote(text: &str) -> String {
+    let mut literal = String::from("CAST(X'");
+    for byte in text.bytes() {
+        write!(literal, "{byte:02X}").unwrap();
+    }
+    literal.push_str("' AS TEXT)");
+    literal
+}
+
+// Decodes a text read with hex(), the reverse of quote
+fn unhex(digits: &str) -> Option<String> {
+    let bytes = (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Appends a run and its diffs to a SQLite database, created when it does
+/// not exist, and returns the id of the run. The statements run in a
+/// single transaction of the `sqlite3` command line shell, so a run is
+/// recorded either with all its diffs or not at all.
+pub(crate) fn record_run(path: &Path, run: &Run, diffs: &[DbDiff]) -> std::io::Result<u64> {
+    let mut script = String::from(SCHEMA);
+    script.push_str("BEGIN;\n");
+    script.push_str(&format!(
+        "INSERT INTO runs (version, arguments, started, finished) VALUES ({}, {}, {}, {});\n",
+        quote(env!("CARGO_PKG_VERSION")),
+        quote(&serde_json::to_string(&run.arguments).unwrap()),
+        quote(&format_timestamp(run.started)),
+        quote(&format_timestamp(SystemTime::now()))
+    ));
+    for db_diff in diffs {
+        let (start_line, end_line) = match &db_diff.lines_range {
+            Some(lines_range) => (
+                (lines_range.start_line + 1).to_string(),
+                lines_range.end_line.to_string(),
+            ),
+            None => ("NULL".to_owned(), "NULL".to_owned()),
+        };
+        script.push_str(&format!(
+            "INSERT INTO diffs VALUES ((SELECT max(id) FROM runs), {}, {}, {}, {}, {}, {}, {start_line}, {end_line}, {});\n",
+            quote(&db_diff.file),
+            quote(&db_diff.space),
+            quote(&db_diff.metric),
+            quote(&db_diff.diff.path),
+            quote(&db_diff.diff.old),
+            quote(&db_diff.diff.new),
+            quote(&db_diff.fingerprint)
+        ));
+    }
+    script.push_str("COMMIT;\nSELECT max(id) FROM runs;\n");
+
+    sqlite3(path, &[], &script)?
+        .trim()
+        .parse()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "sqlite3 returned no run id"))
+}
+
+// Runs a script with the sqlite3 shell on a database, returning its output
+fn sqlite3(path: &Path, args: &[&str], script: &str) -> std::io::Result<String> {
+    let mut child = Command::new("sqlite3")
+        .arg("-bail")
+        .args(args)
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => Error::new(
+                ErrorKind::NotFound,
+                "cannot find `sqlite3`, install the SQLite command line shell \
+                 (e.g. the sqlite3 package) to use a results database",
+            ),
+            _ => err,
+        })?;
+    // The shell reads the whole script before it is waited for
+    child.stdin.take().unwrap().write_all(script.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Error::other(format!(
+            "sqlite3 failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The number of diffs of each metric in the last runs of a database.
+pub(crate) struct Trend {
+    // Id and start time of each run, the oldest first
+    pub(crate) runs: Vec<(u64, String)>,
+    // Number of diffs of a metric in each run
+    pub(crate) counts: BTreeMap<String, Vec<usize>>,
+}
+
+// Separates the fields of the rows read from the shell, which are numbers
+// or hexadecimal digits
+const SEPARATOR: &str = ",";
+
+/// Reads the number of diffs of each metric in the last runs of a
+/// database, optionally only the metrics starting with a prefix, e.g.
+/// `cognitive`.
+pub(crate) fn read_trend(path: &Path, last: usize, metric: Option<&str>) -> std::io::Result<Trend> {
+    let metric_filter = metric
+        .map(|metric| {
+            let metric = quote(metric);
+            format!(" AND substr(d.metric, 1, length({metric})) = {metric}")
+        })
+        .unwrap_or_default();
+    let script = format!(
+        "SELECT r.id, hex(r.started), hex(coalesce(d.metric, '')), count(d.metric)
+FROM (SELECT * FROM runs ORDER BY id DESC LIMIT {last}) r
+LEFT JOIN diffs d ON d.run_id = r.id{metric_filter}
+GROUP BY r.id, d.metric
+ORDER BY r.id;
+"
+    );
+    let output = sqlite3(
+        path,
+        &["-readonly", "-list", "-separator", SEPARATOR],
+        &script,
+    )?;
+    let invalid = || Error::new(ErrorKind::InvalidData, "not a results database");
+    let mut runs: Vec<(u64, String)> = Vec::new();
+    let mut rows = Vec::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split(SEPARATOR).collect();
+        let [id, started, metric, count] = fields[..] else {
+            return Err(invalid());
+        };
+        let id: u64 = id.parse().map_err(|_| invalid())?;
+        if runs.last().map(|(last_id, _)| *last_id) != Some(id) {
+            runs.push((id, unhex(started).ok_or_else(invalid)?));
+        }
+        // A run without diffs has a row with no metric
+        if !metric.is_empty() {
+            rows.push((
+                runs.len() - 1,
+                unhex(metric).ok_or_else(invalid)?,
+                count.parse().map_err(|_| invalid())?,
+            ));
+        }
+    }
+    let mut counts: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (run, metric, count) in rows {
+        counts.entry(metric).or_insert_with(|| vec![0; runs.len()])[run] = count;
+    }
+    Ok(Trend { runs, counts })
+}
+
+/// Writes the trend as a CSV time series, a row for each run and a column
+/// for each metric.
+pub(crate) fn write_trend_csv<W: Write + ?Sized>(
+    writer: &mut W,
+    trend: &Trend,
+) -> std::io::Result<()> {
+    write!(writer, "run,started")?;
+    for metric in trend.counts.keys() {
+        write!(writer, ",{}", escape_field(metric))?;
+    }
+    writeln!(writer)?;
+    for (i, (id, started)) in trend.runs.iter().enumerate() {
+        write!(writer, "{id},{started}")?;
+        for counts in trend.counts.values() {
+            write!(writer, ",{}", counts[i])?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The database tests need the sqlite3 shell, they pass without it
+    fn has_sqlite3() -> bool {
+        Command::new("sqlite3").arg("-version").output().is_ok()
+    }
+
+    fn db_diff(file: &str, metric: &str, old: &str, new: &str) -> DbDiff {
+        DbDiff {
+            file: file.to_owned(),
+            space: String::from(".spaces[0]"),
+            metric: metric.to_owned(),
+            diff: SnippetDiff {
+                path: format!(".spaces[0].metrics.{metric}"),
+                old: old.to_owned(),
+                new: new.to_owned(),
+            },
+            lines_range: Some(LinesRange {
+                start_line: 0,
+                end_line: 2,
+            }),
+            fingerprint: String::from("f00d"),
+        }
+    }
+
+    #[test]
+    fn quote_any_text() {
+        assert_eq!(quote("a'b"), "CAST(X'612762' AS TEXT)");
+        for text in ["", "it's", "a\x1fb", "line\nbreak", "nul\0"] {
+            let quoted = quote(text);
+            let digits = quoted
+                .strip_prefix("CAST(X'")
+                .and_then(|quoted| quoted.strip_suffix("' AS TEXT)"))
+                .unwrap();
+            assert_eq!(unhex(digits), Some(text.to_owned()));
+        }
+        assert_eq!(unhex("6"), None);
+        assert_eq!(unhex("zz"), None);
+    }
+
+    #[test]
+    fn record_and_read_back() {
+        if !has_sqlite3() {
+            return;
+        }
+        let path = std::env::temp_dir().join(format!(
+            "json-minimal-tests-db-{}.sqlite",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let run = Run {
+            arguments: vec![String::from("json-minimal-tests"), String::from("it's")],
+            inputs: Vec::new(),
+            started: SystemTime::now(),
+        };
+        let diffs = [
+            db_diff("it's.rs", "cyclomatic.sum", "1.0", "2.0"),
+            db_diff("a\x1fb.rs", "odd'\x1fname", "'x'", "y\nz"),
+            db_diff("b.rs", "cyclomatic.sum", "3.0", "4.0"),
+        ];
+        assert_eq!(record_run(&path, &run, &diffs).unwrap(), 1);
+        assert_eq!(record_run(&path, &run, &diffs[..1]).unwrap(), 2);
+
+        let rows = sqlite3(
+            &path,
+            &["-readonly"],
+            "SELECT hex(file), hex(metric), hex(old), hex(new), start_line, end_line \
+             FROM diffs WHERE run_id = 1 ORDER BY rowid;",
+        )
+        .unwrap();
+        let rows: Vec<Vec<String>> = rows
+            .lines()
+            .map(|row| {
+                row.split('|')
+                    .map(|field| unhex(field).unwrap_or_else(|| field.to_owned()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1],
+            ["a\x1fb.rs", "odd'\x1fname", "'x'", "y\nz", "1", "2"]
+        );
+
+        let trend = read_trend(&path, 20, None).unwrap();
+        assert_eq!(
+            trend.runs.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(trend.counts["cyclomatic.sum"], [2, 1]);
+        assert_eq!(trend.counts["odd'\x1fname"], [1, 0]);
+        let trend = read_trend(&path, 1, Some("odd'")).unwrap();
+        assert_eq!(trend.runs.len(), 1);
+        assert!(trend.counts.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,649 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::atomic::write_atomically;
+use crate::baseline::Baseline;
+use crate::fixtures::{expected_file_name, test_file_name};
+use crate::html::snippet_lines;
+use crate::schema::Schema;
+use crate::wrap::wrap;
+use crate::{LinesRange, SnippetDiff};
+
+/// The minimal tests of a source file, kept for the interactive review.
+pub(crate) struct ReviewFile {
+    pub(crate) source_file: String,
+    pub(crate) language: String,
+    // In the order of the reports
+    pub(crate) tests: Vec<(LinesRange, Vec<SnippetDiff>)>,
+    // The expected metrics of each test, exported with its code
+    pub(crate) expected: HashMap<LinesRange, Value>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Decision {
+    Accepted,
+    Rejected,
+}
+
+/// What the review decided, already written to the baseline and the bug
+/// list as it was decided.
+#[derive(Default)]
+pub(crate) struct ReviewOutcome {
+    // The diffs of the accepted minimal tests, with their source file
+    pub(crate) accepted: Vec<(String, SnippetDiff)>,
+    // The source file and the lines of the rejected minimal tests
+    pub(crate) rejected: Vec<(String, LinesRange)>,
+    pub(crate) exported: Vec<PathBuf>,
+}
+
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Char(char),
+    Other,
+}
+
+fn parse_key(bytes: &[u8]) -> Key {
+    match bytes {
+        b"\x1b[A" | b"k" => Key::Up,
+        b"\x1b[B" | b"j" => Key::Down,
+        b"\x1b[D" | b"h" => Key::Left,
+        b"\x1b[C" | b"l" | b"\t" => Key::Right,
+        b"\x1b[5~" => Key::PageUp,
+        b"\x1b[6~" | b" " => Key::PageDown,
+        [byte] if byte.is_ascii() => Key::Char(*byte as char),
+        _ => Key::Other,
+    }
+}
+
+// The terminal in raw mode on the alternate screen, restored when dropped,
+// even by a panic
+#[cfg(unix)]
+struct Terminal {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl Terminal {
+    fn new() -> std::io::Result<Self> {
+        // SAFETY: isatty only checks the file descriptors
+        if unsafe {
+            libc::isatty(libc::STDIN_FILENO) == 0 || libc::isatty(libc::STDOUT_FILENO) == 0
+        } {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "the interactive mode needs a terminal",
+            ));
+        }
+        // SAFETY: termios is a plain C struct filled by tcgetattr, whose
+        // result is checked before it is used
+        let original = unsafe {
+            let mut original = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            original
+        };
+        let mut raw = original;
+        // SAFETY: raw is a valid termios copied from the current one
+        unsafe {
+            libc::cfmakeraw(&mut raw);
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        print!("\x1b[?1049h\x1b[?25l");
+        std::io::stdout().flush()?;
+        Ok(Self { original })
+    }
+
+    // The rows and the columns of the terminal
+    fn size(&self) -> (usize, usize) {
+        // SAFETY: winsize is a plain C struct filled by the ioctl
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+        if result != 0 || size.ws_row == 0 || size.ws_col == 0 {
+            (24, 80)
+        } else {
+            (usize::from(size.ws_row), usize::from(size.ws_col))
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = std::io::stdout().flush();
+        // SAFETY: the termios read when the terminal was set up
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+// A line of a pane, written in a style, e.g. `\x1b[1m` for bold
+struct Line {
+    style: &'static str,
+    text: String,
+}
+
+impl Line {
+    fn new(style: &'static str, text: impl Into<String>) -> Self {
+        Self {
+            style,
+            text: text.into(),
+        }
+    }
+}
+
+// Writes a line cut or padded to a width
+fn write_cell(frame: &mut String, line: Option<&Line>, width: usize) {
+    let (style, text) = line.map_or(("", ""), |line| (line.style, line.text.as_str()));
+    let text: String = text
+        .chars()
+        .map(|c| if c == '\t' { ' ' } else { c })
+        .take(width)
+        .collect();
+    let padding = width - text.chars().count();
+    let _ = write!(frame, "{style}{text}{}\x1b[0m", " ".repeat(padding));
+}
+
+struct Review<'a> {
+    files: Vec<(&'a String, &'a ReviewFile)>,
+    context: usize,
+    export_dir: &'a Path,
+    // Whether the exported tests are wrapped in a scaffold
+    wrap: bool,
+    // Where the accepted and the rejected tests are written as decided
+    baseline: &'a Path,
+    bug_list: Option<&'a Path>,
+    // The layout of the metric files, to name their metrics
+    schema: &'a Schema,
+    file: usize,
+    test: usize,
+    // First line of the code shown in the right pane
+    scroll: usize,
+    decisions: HashMap<(usize, usize), Decision>,
+    outcome: ReviewOutcome,
+    message: String,
+}
+
+impl<'a> Review<'a> {
+    fn new(
+        files: &'a BTreeMap<String, ReviewFile>,
+        context: usize,
+        export_dir: &'a Path,
+        wrap: bool,
+        baseline: &'a Path,
+        bug_list: Option<&'a Path>,
+        schema: &'a Schema,
+    ) -> Self {
+        Self {
+            files: files.iter().collect(),
+            context,
+            export_dir,
+            wrap,
+            baseline,
+            bug_list,
+            schema,
+            file: 0,
+            test: 0,
+            scroll: 0,
+            decisions: HashMap::new(),
+            outcome: ReviewOutcome::default(),
+            message: String::new(),
+        }
+    }
+
+    fn current(&self) -> (&str, &ReviewFile, &(LinesRange, Vec<SnippetDiff>)) {
+        let (source_filename, file) = self.files[self.file];
+        (source_filename, file, &file.tests[self.test])
+    }
+
+    fn decide(&mut self, decision: Decision) {
+        let key = (self.file, self.test);
+        let previous = self.decisions.get(&key).copied();
+        // Deciding the same again undoes the decision, and another one
+        // replaces it, so the previous one is taken back from its file
+        let undo = previous == Some(decision);
+        let written = previous
+            .map_or(Ok(()), |previous| self.write_decision(previous, false))
+            .and_then(|_| {
+                if undo {
+                    Ok(())
+                } else {
+                    self.write_decision(decision, true)
+                }
+            });
+        if let Err(err) = written {
+            self.message = format!("Cannot record the decision: {err}");
+            return;
+        }
+        if undo {
+            self.decisions.remove(&key);
+            self.message = String::from("Decision undone");
+        } else {
+            self.decisions.insert(key, decision);
+            self.message = match (decision, self.bug_list) {
+                (Decision::Accepted, _) => format!("Accepted in {}", self.baseline.display()),
+                (Decision::Rejected, Some(bug_list)) => {
+                    format!("Rejected in {}", bug_list.display())
+                }
+                (Decision::Rejected, None) => String::from("Rejected"),
+            };
+            self.next_test();
+        }
+    }
+
+    // Adds the current minimal test to the file of a decision, its diffs
+    // to the baseline or its lines to the bug list, or removes it from there
+    fn write_decision(&self, decision: Decision, add: bool) -> std::io::Result<()> {
+        let (source_filename, _, (lines_range, diffs)) = self.current();
+        match (decision, self.bug_list) {
+            (Decision::Accepted, _) => {
+                let accepted: Vec<(String, SnippetDiff)> = diffs
+                    .iter()
+                    .map(|diff| (source_filename.to_owned(), diff.clone()))
+                    .collect();
+                if add {
+                    accept_into(self.baseline, &accepted)
+                } else {
+                    remove_from(self.baseline, &accepted)
+                }
+            }
+            (Decision::Rejected, Some(bug_list)) => {
+                let entry = bug_entry(source_filename, lines_range, diffs, self.schema);
+                if add {
+                    append_bug(bug_list, &entry)
+                } else {
+                    remove_bug(bug_list, &entry)
+                }
+            }
+            (Decision::Rejected, None) => Ok(()),
+        }
+    }
+
+    fn next_test(&mut self) {
+        if self.test + 1 < self.files[self.file].1.tests.len() {
+            self.test += 1;
+        } else if self.file + 1 < self.files.len() {
+            self.file += 1;
+            self.test = 0;
+        }
+        self.scroll = 0;
+    }
+
+    fn previous_test(&mut self) {
+        if self.test > 0 {
+            self.test -= 1;
+        } else if self.file > 0 {
+            self.file -= 1;
+            self.test = self.files[self.file].1.tests.len() - 1;
+        }
+        self.scroll = 0;
+    }
+
+    // Writes the code of the current minimal test in the export directory,
+    // e.g. src_lib_12-15.rs for the lines 12 to 15 of src/lib.rs, and its
+    // expected metrics next to it
+    fn export(&mut self) {
+        let (source_filename, file, (lines_range, _)) = self.current();
+        let name = test_file_name(source_filename, lines_range);
+        let (_, lines, _) = snippet_lines(&file.source_file, lines_range, 0);
+        let code = if self.wrap {
+            wrap(&lines, &file.source_file, &file.language)
+        } else {
+            let mut code = lines.join("\n");
+            code.push('\n');
+            code
+        };
+        let path = self.export_dir.join(&name);
+        let expected = file.expected.get(lines_range).map(|expected| {
+            (
+                self.export_dir.join(expected_file_name(&name)),
+                serde_json::to_string_pretty(expected).unwrap() + "\n",
+            )
+        });
+        let written = std::fs::create_dir_all(self.export_dir)
+            .and_then(|_| std::fs::write(&path, code))
+            .and_then(|_| match &expected {
+                Some((expected_path, expected)) => std::fs::write(expected_path, expected),
+                None => Ok(()),
+            });
+        match written {
+            Ok(()) => {
+                self.message = format!("Exported to {}", path.display());
+                if !self.outcome.exported.contains(&path) {
+                    self.outcome.exported.push(path);
+                }
+            }
+            Err(err) => self.message = format!("Cannot export to {}: {err}", path.display()),
+        }
+    }
+
+    fn right_pane(&self) -> Vec<Line> {
+        let (source_filename, file, (lines_range, diffs)) = self.current();
+        let decision = match self.decisions.get(&(self.file, self.test)) {
+            Some(Decision::Accepted) => " [accepted]",
+            Some(Decision::Rejected) => " [rejected]",
+            None => "",
+        };
+        let mut lines = vec![Line::new(
+            "\x1b[1m",
+            format!(
+                "{source_filename}: test {} of {}, lines {}-{}{decision}",
+                self.test + 1,
+                file.tests.len(),
+                lines_range.start_line + 1,
+                lines_range.end_line
+            ),
+        )];
+        for diff in diffs {
+            let change = diff
+                .delta()
+                .map(|delta| format!(" ({delta:+})"))
+                .unwrap_or_default();
+            lines.push(Line::new(
+                "\x1b[33m",
+                format!(
+                    "  {}: {} -> {}{change}",
+                    diff.metric_name_in(self.schema),
+                    diff.old,
+                    diff.new
+                ),
+            ));
+        }
+        lines.push(Line::new("", ""));
+        // The code of the test between dimmed lines of context
+        let (before, code, after) = snippet_lines(&file.source_file, lines_range, self.context);
+        let first_line = lines_range.start_line.min(lines_range.end_line) + 1 - before.len();
+        let styles = before
+            .iter()
+            .map(|line| ("\x1b[2m", line))
+            .chain(code.iter().map(|line| ("", line)))
+            .chain(after.iter().map(|line| ("\x1b[2m", line)));
+        for (i, (style, line)) in styles.enumerate().skip(self.scroll) {
+            lines.push(Line::new(style, format!("{:>5} | {line}", first_line + i)));
+        }
+        lines
+    }
+
+    fn draw(&self, rows: usize, columns: usize) -> String {
+        let left_width = (columns / 3).clamp(10, 40).min(columns.saturating_sub(3));
+        let right_width = columns.saturating_sub(left_width + 3);
+        let height = rows.saturating_sub(1);
+        // The list scrolls to keep the selected file in view
+        let first_file = (self.file + 1).saturating_sub(height);
+        let left: Vec<Line> = self
+            .files
+            .iter()
+            .enumerate()
+            .skip(first_file)
+            .map(|(i, (source_filename, file))| {
+                let decided = (0..file.tests.len())
+                    .filter(|test| self.decisions.contains_key(&(i, *test)))
+                    .count();
+                let style = if i == self.file { "\x1b[7m" } else { "" };
+                Line::new(
+                    style,
+                    format!(" {source_filename} ({decided}/{})", file.tests.len()),
+                )
+            })
+            .collect();
+        let right = self.right_pane();
+        let mut frame = String::from("\x1b[H");
+        for row in 0..height {
+            write_cell(&mut frame, left.get(row), left_width);
+            frame.push_str(" | ");
+            write_cell(&mut frame, right.get(row), right_width);
+            frame.push_str("\r\n");
+        }
+        let status = if self.message.is_empty() {
+            "up/down file  left/right test  a accept  r reject  e export  q quit"
+        } else {
+            &self.message
+        };
+        write_cell(&mut frame, Some(&Line::new("\x1b[7m", status)), columns);
+        frame
+    }
+
+    fn finish(mut self) -> ReviewOutcome {
+        let mut decisions: Vec<_> = self.decisions.into_iter().collect();
+        decisions.sort_by_key(|(key, _)| *key);
+        for ((file, test), decision) in decisions {
+            let (source_filename, review_file) = self.files[file];
+            let (lines_range, diffs) = &review_file.tests[test];
+            match decision {
+                Decision::Accepted => self.outcome.accepted.extend(
+                    diffs
+                        .iter()
+                        .map(|diff| (source_filename.clone(), diff.clone())),
+                ),
+                Decision::Rejected => self
+                    .outcome
+                    .rejected
+                    .push((source_filename.clone(), lines_range.clone())),
+            }
+        }
+        self.outcome
+    }
+}
+
+/// Browses the minimal tests of the source files in the terminal, the
+/// files on the left and the diffs and the code of a test on the right,
+/// until `q` is pressed. Each test can be accepted, its diffs added to
+/// `baseline` right away, rejected, its lines appended to `bug_list` if
+/// any, or exported to a file of its own in `export_dir`, wrapped in a
+/// scaffold compiling on its own with `wrap`.
+#[cfg(unix)]
+pub(crate) fn review_tests(
+    files: &BTreeMap<String, ReviewFile>,
+    context: usize,
+    export_dir: &Path,
+    wrap: bool,
+    baseline: &Path,
+    bug_list: Option<&Path>,
+    schema: &Schema,
+) -> std::io::Result<ReviewOutcome> {
+    let mut review = Review::new(files, context, export_dir, wrap, baseline, bug_list, schema);
+    if review.files.is_empty() {
+        return Ok(review.outcome);
+    }
+    let terminal = Terminal::new()?;
+    let mut stdin = std::io::stdin();
+    let mut buffer = [0; 16];
+    loop {
+        let (rows, columns) = terminal.size();
+        print!("{}", review.draw(rows, columns));
+        std::io::stdout().flush()?;
+        let len = stdin.read(&mut buffer)?;
+        if len == 0 {
+            break;
+        }
+        review.message.clear();
+        match parse_key(&buffer[..len]) {
+            Key::Up if review.file > 0 => {
+                review.file -= 1;
+                review.test = 0;
+                review.scroll = 0;
+            }
+            Key::Down if review.file + 1 < review.files.len() => {
+                review.file += 1;
+                review.test = 0;
+                review.scroll = 0;
+            }
+            Key::Left => review.previous_test(),
+            Key::Right => review.next_test(),
+            Key::PageUp => review.scroll = review.scroll.saturating_sub(rows / 2),
+            Key::PageDown => review.scroll += rows / 2,
+            Key::Char('a') => review.decide(Decision::Accepted),
+            Key::Char('r') => review.decide(Decision::Rejected),
+            Key::Char('e') => review.export(),
+            Key::Char('q') | Key::Char('\x03') => break,
+            _ => {}
+        }
+    }
+    drop(terminal);
+    Ok(review.finish())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn review_tests(
+    _files: &BTreeMap<String, ReviewFile>,
+    _context: usize,
+    _export_dir: &Path,
+    _wrap: bool,
+    _baseline: &Path,
+    _bug_list: Option<&Path>,
+    _schema: &Schema,
+) -> std::io::Result<ReviewOutcome> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "the interactive mode needs a Unix terminal",
+    ))
+}
+
+/// Adds the accepted diffs to a baseline file, creating it if needed.
+pub(crate) fn accept_into(path: &Path, accepted: &[(String, SnippetDiff)]) -> std::io::Result<()> {
+    let mut baseline = if path.exists() {
+        Baseline::read(path)?
+    } else {
+        Baseline::default()
+    };
+    for (source_filename, diff) in accepted {
+        baseline.accept(source_filename, diff);
+    }
+    baseline.write(path)
+}
+
+/// Removes the diffs accepted by mistake from a baseline file.
+pub(crate) fn remove_from(path: &Path, accepted: &[(String, SnippetDiff)]) -> std::io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut baseline = Baseline::read(path)?;
+    for (source_filename, diff) in accepted {
+        baseline.remove(source_filename, diff);
+    }
+    baseline.write(path)
+}
+
+// The line of a rejected minimal test in the bug list, e.g.
+// `src/lib.rs:12-15 cyclomatic.sum, nargs.total` for the lines 12 to 15
+fn bug_entry(
+    source_filename: &str,
+    lines_range: &LinesRange,
+    diffs: &[SnippetDiff],
+    schema: &Schema,
+) -> String {
+    let mut metrics: Vec<&str> = diffs
+        .iter()
+        .map(|diff| diff.metric_name_in(schema))
+        .collect();
+    metrics.dedup();
+    format!(
+        "{source_filename}:{}-{} {}",
+        lines_range.start_line + 1,
+        lines_range.end_line,
+        metrics.join(", ")
+    )
+}
+
+fn append_bug(path: &Path, entry: &str) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{entry}")
+}
+
+// Removes the last line of a rejected minimal test from the bug list
+fn remove_bug(path: &Path, entry: &str) -> std::io::Result<()> {
+    let bug_list = std::fs::read_to_string(path)?;
+    let mut lines: Vec<&str> = bug_list.lines().collect();
+    if let Some(last) = lines.iter().rposition(|line| *line == entry) {
+        lines.remove(last);
+    }
+    write_atomically(path, |file| {
+        lines.iter().try_for_each(|line| writeln!(file, "{line}"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review_file(tests: Vec<(LinesRange, Vec<SnippetDiff>)>) -> ReviewFile {
+        ReviewFile {
+            source_file: String::from("fn f() {}\nfn g() {}\n"),
+            language: String::from("Rust"),
+            tests,
+            expected: HashMap::new(),
+        }
+    }
+
+    fn test(start_line: usize, end_line: usize, path: &str) -> (LinesRange, Vec<SnippetDiff>) {
+        (
+            LinesRange {
+                start_line,
+                end_line,
+            },
+            vec![SnippetDiff {
+                path: path.to_owned(),
+                old: String::from("1.0"),
+                new: String::from("2.0"),
+            }],
+        )
+    }
+
+    #[test]
+    fn decisions_written_right_away() {
+        let dir =
+            std::env::temp_dir().join(format!("json-minimal-tests-review-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let baseline = dir.join("baseline.json");
+        let bug_list = dir.join("bugs.txt");
+        let files = BTreeMap::from([(
+            String::from("a.rs"),
+            review_file(vec![
+                test(0, 1, ".spaces[0].metrics.cyclomatic.sum"),
+                test(1, 2, ".spaces[1].metrics.nargs.total"),
+            ]),
+        )]);
+        let schema = Schema::default();
+        let mut review = Review::new(&files, 0, &dir, false, &baseline, Some(&bug_list), &schema);
+        let accepted = || Baseline::read(&baseline).unwrap().len();
+        let bugs = || std::fs::read_to_string(&bug_list).unwrap();
+
+        // Accepting appends to the baseline, accepting again undoes it
+        review.decide(Decision::Accepted);
+        assert_eq!(accepted(), 1);
+        review.previous_test();
+        review.decide(Decision::Accepted);
+        assert_eq!(accepted(), 0);
+
+        // Rejecting appends to the bug list, rejecting again undoes it
+        review.decide(Decision::Rejected);
+        assert_eq!(bugs(), "a.rs:1-1 cyclomatic.sum\n");
+        review.previous_test();
+        review.decide(Decision::Rejected);
+        assert_eq!(bugs(), "");
+
+        // Accepting a rejected test takes it back from the bug list
+        review.decide(Decision::Rejected);
+        review.decide(Decision::Rejected);
+        assert_eq!(bugs(), "a.rs:1-1 cyclomatic.sum\na.rs:2-2 nargs.total\n");
+        review.decide(Decision::Accepted);
+        assert_eq!(bugs(), "a.rs:1-1 cyclomatic.sum\n");
+        assert_eq!(accepted(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use serde_json::Value;
+
+/// The keys of the fields of a space in a metric file, so that the
+/// metrics of tools other than rust-code-analysis can be compared.
+///
+/// The structural fields locate a space in the source, the metrics are
+/// compared, and the children of a space are spaces with the same layout.
+#[derive(Clone, Debug)]
+pub struct Schema {
+    pub name: String,
+    pub kind: String,
+    pub start_line: String,
+    pub end_line: String,
+    pub metrics: String,
+    pub spaces: String,
+}
+
+impl Default for Schema {
+    /// The layout of the metric files of rust-code-analysis.
+    fn default() -> Self {
+        Self {
+            name: "name".to_owned(),
+            kind: "kind".to_owned(),
+            start_line: "start_line".to_owned(),
+            end_line: "end_line".to_owned(),
+            metrics: "metrics".to_owned(),
+            spaces: "spaces".to_owned(),
+        }
+    }
+}
+
+impl Schema {
+    /// Reads a schema descriptor, a JSON object mapping the fields of a
+    /// space to their keys, e.g. `{ "spaces": "children" }`.
+    /// The fields left out keep the keys of rust-code-analysis.
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let descriptor: Value = serde_json::from_slice(&std::fs::read(path)?)?;
+        let descriptor = descriptor
+            .as_object()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "a schema should be an object"))?;
+        let mut schema = Self::default();
+        for (field, key) in descriptor {
+            let key = key.as_str().filter(|key| !key.is_empty()).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("the key of `{field}` should be a non-empty string"),
+                )
+            })?;
+            let slot = match field.as_str() {
+                "name" => &mut schema.name,
+                "kind" => &mut schema.kind,
+                "start_line" => &mut schema.start_line,
+                "end_line" => &mut schema.end_line,
+                "metrics" => &mut schema.metrics,
+                "spaces" => &mut schema.spaces,
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("unknown field `{field}`"),
+                    ))
+                }
+            };
+            *slot = key.to_owned();
+        }
+        Ok(schema)
+    }
+
+    /// Returns the fields which locate a space rather than measuring it.
+    pub fn structural_fields(&self) -> [&str; 4] {
+        [&self.start_line, &self.end_line, &self.name, &self.kind]
+    }
+}
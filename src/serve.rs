@@ -0,0 +1,338 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::html::{write_index, IndexEntry, IndexFilter, Theme};
+
+// The content types of the files of an output directory
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    ("html", "text/html; charset=utf-8"),
+    ("json", "application/json"),
+    ("csv", "text/csv; charset=utf-8"),
+    ("md", "text/markdown; charset=utf-8"),
+    ("toml", "application/toml"),
+    ("xml", "application/xml"),
+    ("sha256", "text/plain; charset=utf-8"),
+];
+
+// A client which does not send or read its request in time is dropped,
+// not to hold a thread forever
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// The bytes of the request line and of the headers read at most
+const MAX_HEAD: u64 = 8 * 1024;
+
+fn hex(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+// Decodes the `%XX` escapes of a URL path or query, and the `+` of a
+// query, which are spaces
+fn percent_decode(text: &str, is_query: bool) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    decoded.push(high << 4 | low);
+                    i += 3;
+                    continue;
+                }
+                _ => decoded.push(b'%'),
+            },
+            b'+' if is_query => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// Escapes a relative path to link it, keeping its slashes
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+fn parse_filter(query: &str) -> IndexFilter {
+    let mut filter = IndexFilter::default();
+    for pair in query.split('&') {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value, true);
+        match name {
+            "q" => filter.search = value,
+            "metric" => filter.metric = value,
+            "path" => filter.path = value,
+            _ => {}
+        }
+    }
+    filter
+}
+
+// The reports listed by the manifest of the run, or else the HTML files
+// of the output directory
+fn index_entries(output_path: &Path) -> Vec<IndexEntry> {
+    let manifest: Option<Value> = std::fs::read(output_path.join("manifest.json"))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+    if let Some(reports) = manifest
+        .as_ref()
+        .and_then(|manifest| manifest["reports"].as_array())
+    {
+        return reports
+            .iter()
+            .filter_map(|report| {
+                Some(IndexEntry {
+                    source: report["source"].as_str()?.to_owned(),
+                    report: percent_encode(report["report"].as_str()?),
+                    diffs: report["diffs"].as_u64().unwrap_or(0) as usize,
+                    metrics: report["metrics"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|metric| metric.as_str().map(str::to_owned))
+                        .collect(),
+                })
+            })
+            .collect();
+    }
+    let mut entries = Vec::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(read_dir) = std::fs::read_dir(output_path.join(&dir)) else {
+            continue;
+        };
+        for entry in read_dir.flatten() {
+            let relative = dir.join(entry.file_name());
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                dirs.push(relative);
+            } else if relative
+                .extension()
+                .is_some_and(|extension| extension == "html")
+            {
+                let report = relative.to_string_lossy().replace('\\', "/");
+                entries.push(IndexEntry {
+                    source: report.clone(),
+                    report: percent_encode(&report),
+                    diffs: 0,
+                    metrics: Vec::new(),
+                });
+            }
+        }
+    }
+    entries.sort_by(|a, b| a.source.cmp(&b.source));
+    entries
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+    head_only: bool,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    if !head_only {
+        stream.write_all(body)?;
+    }
+    stream.flush()
+}
+
+fn handle(mut stream: TcpStream, output_path: &Path, theme: Theme) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_HEAD));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are not needed, but they must end before the limit
+    let mut header = request_line.clone();
+    while header.ends_with('\n') && header != "\r\n" && header != "\n" {
+        header.clear();
+        reader.read_line(&mut header)?;
+    }
+    if !header.ends_with('\n') {
+        let (status, body): (_, &[u8]) = if reader.get_ref().limit() == 0 {
+            (
+                "431 Request Header Fields Too Large",
+                b"Request header too large\n",
+            )
+        } else {
+            ("400 Bad Request", b"Incomplete request\n")
+        };
+        return respond(&mut stream, status, "text/plain", body, false);
+    }
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+    let head_only = method == "HEAD";
+    if method != "GET" && !head_only {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Method not allowed\n",
+            false,
+        );
+    }
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = percent_decode(path, false);
+    if path == "/" || path == "/index.html" {
+        let mut page = Vec::new();
+        write_index(
+            &mut page,
+            &index_entries(output_path),
+            &parse_filter(query),
+            theme,
+        )?;
+        return respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            &page,
+            head_only,
+        );
+    }
+    // Only the files inside the output directory are served
+    let relative = Path::new(path.trim_start_matches('/'));
+    let is_inside = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    let file_path = output_path.join(relative);
+    let content = if is_inside {
+        std::fs::read(&file_path).ok()
+    } else {
+        None
+    };
+    match content {
+        Some(content) => {
+            let extension = file_path
+                .extension()
+                .map(|extension| extension.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let content_type = CONTENT_TYPES
+                .iter()
+                .find(|(name, _)| *name == extension)
+                .map_or("application/octet-stream", |(_, content_type)| content_type);
+            respond(&mut stream, "200 OK", content_type, &content, head_only)
+        }
+        None => respond(
+            &mut stream,
+            "404 Not Found",
+            "text/plain",
+            b"Not found\n",
+            head_only,
+        ),
+    }
+}
+
+/// Serves the reports of an output directory over HTTP, with an index
+/// of the reports at `/` which can be searched and filtered, until the
+/// process is interrupted. Each connection is handled by a thread of its
+/// own and answered with a single response.
+pub(crate) fn serve(output_path: &Path, address: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    eprintln!(
+        "Serving {:?} on http://{}/",
+        output_path,
+        listener.local_addr()?
+    );
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Cannot accept a connection: {err}");
+                continue;
+            }
+        };
+        let output_path = output_path.to_path_buf();
+        std::thread::spawn(move || {
+            if let Err(err) = handle(stream, &output_path, Theme::default()) {
+                eprintln!("Cannot answer a request: {err}");
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Answers a single request on a port of its own
+    fn request(output_path: &Path, request: &[u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let output_path = output_path.to_path_buf();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            handle(stream, &output_path, Theme::default())
+        });
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request).unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        server.join().unwrap().unwrap();
+        response
+    }
+
+    #[test]
+    fn reports_only() {
+        let dir =
+            std::env::temp_dir().join(format!("json-minimal-tests-serve-{}", std::process::id()));
+        let output_path = dir.join("output");
+        std::fs::create_dir_all(&output_path).unwrap();
+        std::fs::write(output_path.join("a.rs.html"), "<p>report</p>").unwrap();
+        std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+
+        let response = request(&output_path, b"GET /a.rs.html HTTP/1.1\r\nHost: x\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/html; charset=utf-8\r\n"));
+        assert!(response.ends_with("\r\n\r\n<p>report</p>"));
+
+        for path in [
+            "/../secret.txt",
+            "/%2e%2e/secret.txt",
+            "/x/../../secret.txt",
+        ] {
+            let response = request(
+                &output_path,
+                format!("GET {path} HTTP/1.1\r\n\r\n").as_bytes(),
+            );
+            assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"), "{path}");
+            assert!(!response.contains("secret"), "{path}");
+        }
+
+        let response = request(&output_path, b"POST / HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn bounded_head() {
+        let output_path = Path::new("unused");
+        // The headers go on past the limit, which is all read
+        let mut huge = b"GET / HTTP/1.1\r\nCookie: ".to_vec();
+        huge.resize(MAX_HEAD as usize, b'x');
+        let response = request(output_path, &huge);
+        assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+
+        let response = request(output_path, b"GET / HTTP/1.1\r\nHost: x");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+}
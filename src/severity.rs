@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use crate::glob::{read_glob_rules, Glob};
+
+/// How much a diff matters when triaging a regression, from the rules
+/// of the metric it changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    pub(crate) const NAMES: &'static [&'static str] = &["low", "medium", "high"];
+
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        match name {
+            "low" => Some(Severity::Low),
+            "medium" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        }
+    }
+}
+
+/// The severity of the metrics, given as globs over their names.
+#[derive(Debug)]
+pub(crate) struct SeverityRules {
+    rules: Vec<(Glob, Severity)>,
+}
+
+impl SeverityRules {
+    /// Reads the rules from a file with a `glob = severity` rule per line,
+    /// e.g. `cyclomatic.* = high`.
+    pub(crate) fn read(path: &Path) -> std::io::Result<Self> {
+        let rules = read_glob_rules(path, |severity| {
+            Severity::parse(severity).ok_or_else(|| {
+                format!(
+                    "the severity should be one of {}",
+                    Severity::NAMES.join(", ")
+                )
+            })
+        })?;
+        Ok(Self { rules })
+    }
+
+    /// Returns the severity of the first rule matching a metric name,
+    /// medium when none does.
+    pub(crate) fn severity(&self, metric_name: &str) -> Severity {
+        self.rules
+            .iter()
+            .find(|(glob, _)| glob.is_match(metric_name))
+            .map_or(Severity::Medium, |(_, severity)| *severity)
+    }
+}
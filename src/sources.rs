@@ -0,0 +1,207 @@
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn check_output(output: Output, command: &str) -> std::io::Result<Output> {
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(Error::other(format!(
+            "{command} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> std::io::Result<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    let output = check_output(output, "git")?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Writes the metrics of all the sources of a directory into another one,
+/// running rust-code-analysis-cli from the sources directory, so that the
+/// metric files of two source trees have the same relative paths.
+fn compute_metrics(cli: &str, sources: &Path, output: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(output)?;
+    let result = Command::new(cli)
+        .current_dir(sources)
+        .args(["--metrics", "--output-format", "json", "--output"])
+        .arg(output)
+        .args(["--paths", "."])
+        .output();
+    match result {
+        Ok(result) => check_output(result, cli).map(drop),
+        Err(err) if err.kind() == ErrorKind::NotFound => Err(Error::new(
+            ErrorKind::NotFound,
+            format!("cannot find `{cli}`, install rust-code-analysis-cli or pass its path"),
+        )),
+        Err(err) => Err(err),
+    }
+}
+
+/// The source trees whose metrics are compared: two directories, or
+/// a directory and a git revision, or two revisions of the same directory.
+pub(crate) enum SourceTrees {
+    Dirs(PathBuf, PathBuf),
+    Revision(PathBuf, String),
+    Revisions(PathBuf, String, String),
+}
+
+impl SourceTrees {
+    /// Computes the metrics of the two trees with rust-code-analysis-cli.
+    pub(crate) fn compute(&self, cli: &str) -> std::io::Result<GeneratedMetrics> {
+        match self {
+            SourceTrees::Dirs(old, new) => GeneratedMetrics::from_dirs(cli, old, new),
+            SourceTrees::Revision(sources, revision) => {
+                GeneratedMetrics::from_revision(cli, sources, revision)
+            }
+            SourceTrees::Revisions(sources, old_revision, new_revision) => {
+                GeneratedMetrics::from_revisions(cli, sources, old_revision, new_revision)
+            }
+        }
+    }
+}
+
+/// The metrics computed for an old and a new source tree,
+/// in a temporary directory removed with its worktrees when dropped,
+/// e.g. when the computation fails half-way.
+pub(crate) struct GeneratedMetrics {
+    dir: PathBuf,
+    pub(crate) old: PathBuf,
+    pub(crate) new: PathBuf,
+    // The old and the new sources, which the metric files name relatively
+    pub(crate) old_sources: PathBuf,
+    pub(crate) sources: PathBuf,
+    // The git repository and the worktrees checked out from its revisions
+    repo: PathBuf,
+    worktrees: Vec<PathBuf>,
+    // Whether a killed run with the same process id left the directory,
+    // and maybe its worktrees registered in the repository
+    is_leftover: bool,
+    // Whether the metrics and the worktrees have been removed already
+    is_removed: bool,
+}
+
+impl GeneratedMetrics {
+    fn new(old_sources: &Path, sources: &Path) -> Self {
+        let dir = std::env::temp_dir().join(format!("json-minimal-tests-{}", std::process::id()));
+        let is_leftover = dir.exists();
+        if is_leftover {
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+        Self {
+            is_leftover,
+            is_removed: false,
+            old: dir.join("old"),
+            new: dir.join("new"),
+            dir,
+            old_sources: old_sources.to_path_buf(),
+            sources: sources.to_path_buf(),
+            repo: PathBuf::new(),
+            worktrees: Vec::new(),
+        }
+    }
+
+    /// Computes the metrics of two source directories.
+    fn from_dirs(cli: &str, old: &Path, new: &Path) -> std::io::Result<Self> {
+        let metrics = Self::new(&old.canonicalize()?, &new.canonicalize()?);
+        compute_metrics(cli, old, &metrics.old)?;
+        compute_metrics(cli, new, &metrics.new)?;
+        Ok(metrics)
+    }
+
+    /// Computes the metrics of a source directory and of the same
+    /// directory at a git revision, checked out in a temporary worktree.
+    fn from_revision(cli: &str, sources: &Path, revision: &str) -> std::io::Result<Self> {
+        let sources = sources.canonicalize()?;
+        let mut metrics = Self::new(&sources, &sources);
+        let old = metrics.checkout(&sources, revision, "worktree")?;
+        compute_metrics(cli, &old, &metrics.old)?;
+        compute_metrics(cli, &sources, &metrics.new)?;
+        metrics.old_sources = old;
+        Ok(metrics)
+    }
+
+    /// Computes the metrics of a source directory at two git revisions,
+    /// each one checked out in a temporary worktree.
+    fn from_revisions(
+        cli: &str,
+        sources: &Path,
+        old_revision: &str,
+        new_revision: &str,
+    ) -> std::io::Result<Self> {
+        let sources = sources.canonicalize()?;
+        let mut metrics = Self::new(&sources, &sources);
+        let old = metrics.checkout(&sources, old_revision, "worktree-old")?;
+        let new = metrics.checkout(&sources, new_revision, "worktree-new")?;
+        compute_metrics(cli, &old, &metrics.old)?;
+        compute_metrics(cli, &new, &metrics.new)?;
+        metrics.old_sources = old;
+        metrics.sources = new;
+        Ok(metrics)
+    }
+
+    /// Checks out a revision of the repository containing the sources,
+    /// returning the directory of the sources in the new worktree.
+    fn checkout(&mut self, sources: &Path, revision: &str, name: &str) -> std::io::Result<PathBuf> {
+        self.repo = PathBuf::from(git(sources, &["rev-parse", "--show-toplevel"])?);
+        let prefix = git(sources, &["rev-parse", "--show-prefix"])?;
+        // The worktrees of the removed directory are still registered
+        if self.is_leftover {
+            git(&self.repo, &["worktree", "prune"])?;
+        }
+        // A revision starting with `-` is not taken for an option, and
+        // the worktree is checked out from the commit it names
+        let commit = git(
+            &self.repo,
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                "--end-of-options",
+                &format!("{revision}^{{commit}}"),
+            ],
+        )
+        .map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("`{revision}` is not a commit of {:?}", self.repo),
+            )
+        })?;
+        let worktree = self.dir.join(name);
+        git(
+            &self.repo,
+            &[
+                "worktree",
+                "add",
+                "--detach",
+                &worktree.to_string_lossy(),
+                &commit,
+            ],
+        )?;
+        self.worktrees.push(worktree.clone());
+        Ok(worktree.join(prefix))
+    }
+
+    /// Removes the metrics and the worktrees, once.
+    pub(crate) fn remove(&mut self) {
+        if self.is_removed {
+            return;
+        }
+        self.is_removed = true;
+        for worktree in &self.worktrees {
+            let _ = git(
+                &self.repo,
+                &["worktree", "remove", "--force", &worktree.to_string_lossy()],
+            );
+        }
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+impl Drop for GeneratedMetrics {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
@@ -0,0 +1,121 @@
+use serde_json::{Map, Value};
+
+use crate::schema::Schema;
+use crate::LinesRange;
+
+/// A space of a metric file produced by rust-code-analysis, e.g.
+/// a function, a struct or the whole unit at the root of the file.
+///
+/// It is a typed view over the parsed JSON, which is kept as it is
+/// to be compared and reported, reading its fields where the schema says.
+/// The owned spaces of `metrics_file.rs` are only read where they outlive
+/// the JSON, e.g. for the expected metrics of the minimal tests.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Space<'a> {
+    value: &'a Value,
+    schema: &'a Schema,
+}
+
+/// The metrics of a space, grouped by family, e.g. `cyclomatic.sum`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Metrics<'a>(&'a Map<String, Value>);
+
+impl<'a> Space<'a> {
+    pub(crate) fn new(value: &'a Value, schema: &'a Schema) -> Self {
+        Space { value, schema }
+    }
+
+    /// The JSON of the space, with its children.
+    pub(crate) fn value(self) -> &'a Value {
+        self.value
+    }
+
+    /// The source file name for the root space,
+    /// the name of the function or the type otherwise.
+    pub(crate) fn name(self) -> Option<&'a str> {
+        self.value.get(&self.schema.name).and_then(Value::as_str)
+    }
+
+    pub(crate) fn kind(self) -> Option<&'a str> {
+        self.value.get(&self.schema.kind).and_then(Value::as_str)
+    }
+
+    fn line(self, field: &str) -> Option<usize> {
+        self.value
+            .get(field)
+            .and_then(Value::as_u64)
+            .filter(|line| *line > 0)
+            .map(|line| line as usize)
+    }
+
+    /// The first line of the space, starting from 1.
+    pub(crate) fn start_line(self) -> Option<usize> {
+        self.line(&self.schema.start_line)
+    }
+
+    /// The last line of the space, included.
+    pub(crate) fn end_line(self) -> Option<usize> {
+        self.line(&self.schema.end_line)
+    }
+
+    /// The lines of the space, as a 0-based range of the source lines.
+    pub(crate) fn lines_range(self) -> Option<LinesRange> {
+        Some(LinesRange {
+            start_line: self.start_line()? - 1,
+            end_line: self.end_line()?,
+        })
+    }
+
+    /// The number of lines of the space.
+    pub(crate) fn lines(self) -> usize {
+        (self.end_line().unwrap_or(0) + 1).saturating_sub(self.start_line().unwrap_or(0))
+    }
+
+    /// The hash of the source lines of the space, when the metric file has it.
+    pub(crate) fn source_hash(self) -> Option<&'a str> {
+        self.value.get("source_hash").and_then(Value::as_str)
+    }
+
+    pub(crate) fn metrics(self) -> Option<Metrics<'a>> {
+        self.value
+            .get(&self.schema.metrics)
+            .and_then(Value::as_object)
+            .map(Metrics)
+    }
+
+    /// Returns the value at a metric path relative to the space,
+    /// e.g. `.metrics.cyclomatic.sum`.
+    pub(crate) fn metric(self, metric_path: &str) -> Option<&'a Value> {
+        let path = metric_path
+            .strip_prefix('.')?
+            .strip_prefix(self.schema.metrics.as_str())?
+            .strip_prefix('.')?;
+        self.metrics()?.get(path)
+    }
+
+    /// The children of the space, `None` when the space has no `spaces` array.
+    pub(crate) fn spaces(self) -> Option<impl Iterator<Item = Space<'a>>> {
+        let schema = self.schema;
+        self.value
+            .get(&schema.spaces)
+            .and_then(Value::as_array)
+            .map(move |spaces| spaces.iter().map(move |value| Space::new(value, schema)))
+    }
+
+    pub(crate) fn child(self, index: usize) -> Option<Space<'a>> {
+        self.value
+            .get(&self.schema.spaces)?
+            .get(index)
+            .map(|value| Space::new(value, self.schema))
+    }
+}
+
+impl<'a> Metrics<'a> {
+    /// Returns the value of a metric from its dotted path,
+    /// e.g. `cyclomatic.sum`.
+    pub(crate) fn get(self, path: &str) -> Option<&'a Value> {
+        let mut keys = path.split('.').filter(|key| !key.is_empty());
+        let family = self.0.get(keys.next()?)?;
+        keys.try_fold(family, |value, key| value.get(key))
+    }
+}
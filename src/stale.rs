@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::sha256::sha256_hex;
+use crate::space::Space;
+use crate::LinesRange;
+
+/// Returns the hash of some source lines as expected in the `source_hash`
+/// field of a space: the SHA-256 of the lines joined by `\n`.
+pub(crate) fn lines_hash(lines: &[&str]) -> String {
+    sha256_hex(lines.join("\n").as_bytes())
+}
+
+/// Returns the line range of a space when it has a `source_hash` field.
+pub(crate) fn space_hash(space: Space) -> Option<(LinesRange, String)> {
+    let hash = space.source_hash()?;
+    Some((space.lines_range()?, hash.to_owned()))
+}
+
+fn sidecar_path(metric_path: &Path) -> PathBuf {
+    let mut path = metric_path.as_os_str().to_owned();
+    path.push(".hashes");
+    PathBuf::from(path)
+}
+
+/// Reads the hashes stored next to a metric file, in a `<file>.hashes` JSON
+/// object mapping line ranges to hashes, e.g. `{ "12-15": "4f2a..." }`.
+pub(crate) fn read_sidecar(metric_path: &Path) -> HashMap<LinesRange, String> {
+    let json: Value = match std::fs::read(sidecar_path(metric_path))
+        .ok()
+        .and_then(|buffer| serde_json::from_slice(&buffer).ok())
+    {
+        Some(json) => json,
+        None => return HashMap::new(),
+    };
+    json.as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(range, hash)| {
+            let (start_line, end_line) = range.split_once('-')?;
+            let start_line: usize = start_line.trim().parse().ok()?;
+            Some((
+                LinesRange {
+                    start_line: start_line.checked_sub(1)?,
+                    end_line: end_line.trim().parse().ok()?,
+                },
+                hash.as_str()?.to_owned(),
+            ))
+        })
+        .collect()
+}
+
+/// Returns the line ranges whose source does not match the expected hash,
+/// meaning that the metrics were computed on a different source.
+pub(crate) fn stale_ranges(
+    source_file: &str,
+    source_hashes: &HashMap<LinesRange, String>,
+) -> Vec<LinesRange> {
+    let lines: Vec<&str> = source_file.lines().collect();
+    let mut stale: Vec<LinesRange> = source_hashes
+        .iter()
+        .filter(|(range, hash)| {
+            lines
+                .get(range.start_line..range.end_line)
+                .is_none_or(|lines| lines_hash(lines) != **hash)
+        })
+        .map(|(range, _)| range.clone())
+        .collect();
+    stale.sort_by_key(|range| (range.start_line, range.end_line));
+    stale
+}
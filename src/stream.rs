@@ -0,0 +1,203 @@
+use std::fmt;
+
+use serde::de::{DeserializeSeed, Deserializer, Error, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Number, Value};
+
+use crate::schema::Schema;
+use crate::space::Space;
+
+/// Parses a new metric file against the old one, comparing the metrics of
+/// each space with the ones of the old space at the same place as soon as
+/// they are parsed. The metrics equal on both sides produce no diffs, so
+/// they are not kept: the new file never exists as a whole in memory, and
+/// the old one shrinks by as much once parsed.
+///
+/// The metrics are only left out of both files where the spaces are paired
+/// by their index, those of the spaces paired otherwise, e.g. after a space
+/// was added, are restored from the old file, where they are the same.
+pub(crate) fn parse_against(
+    buffer: &[u8],
+    old: &mut Value,
+    schema: &Schema,
+) -> serde_json::Result<Value> {
+    let mut path = Vec::new();
+    let mut dropped = Vec::new();
+    let mut deserializer = serde_json::Deserializer::from_slice(buffer);
+    let mut new = SpaceSeed {
+        old: Some(old),
+        path: &mut path,
+        dropped: &mut dropped,
+        schema,
+        is_spaces: false,
+    }
+    .deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    for path in dropped {
+        if is_paired_by_index(old, &new, &path, schema) {
+            if let Some(Value::Object(space)) = space_at_mut(old, &path, schema) {
+                space.remove(&schema.metrics);
+            }
+        } else {
+            let metrics = space_at_mut(old, &path, schema)
+                .and_then(|space| space.get(&schema.metrics))
+                .cloned();
+            if let (Some(Value::Object(space)), Some(metrics)) =
+                (space_at_mut(&mut new, &path, schema), metrics)
+            {
+                space.insert(schema.metrics.clone(), metrics);
+            }
+        }
+    }
+    Ok(new)
+}
+
+// Whether the spaces at a path are compared with each other whatever the
+// alignment: at each level the two lists of children have the same length
+// and the two spaces the same name, kind and lines
+fn is_paired_by_index(old: &Value, new: &Value, path: &[usize], schema: &Schema) -> bool {
+    let (mut old, mut new) = (Space::new(old, schema), Space::new(new, schema));
+    for &index in path {
+        let same_length = match (
+            old.value().get(&schema.spaces).and_then(Value::as_array),
+            new.value().get(&schema.spaces).and_then(Value::as_array),
+        ) {
+            (Some(old_spaces), Some(new_spaces)) => old_spaces.len() == new_spaces.len(),
+            _ => false,
+        };
+        match (old.child(index), new.child(index)) {
+            (Some(old_child), Some(new_child)) if same_length => {
+                old = old_child;
+                new = new_child;
+            }
+            _ => return false,
+        }
+        if (old.name(), old.kind(), old.start_line(), old.end_line())
+            != (new.name(), new.kind(), new.start_line(), new.end_line())
+        {
+            return false;
+        }
+    }
+    true
+}
+
+fn space_at_mut<'a>(
+    mut value: &'a mut Value,
+    path: &[usize],
+    schema: &Schema,
+) -> Option<&'a mut Value> {
+    for &index in path {
+        value = value.get_mut(&schema.spaces)?.get_mut(index)?;
+    }
+    Some(value)
+}
+
+// Parses a space, or a list of spaces with `is_spaces`, next to the old
+// one at the same path, if any. The other values are parsed as they are.
+struct SpaceSeed<'a, 's> {
+    old: Option<&'a Value>,
+    // The indices of the space among the children of its ancestors
+    path: &'s mut Vec<usize>,
+    // The paths of the spaces whose metrics were left out
+    dropped: &'s mut Vec<Vec<usize>>,
+    schema: &'a Schema,
+    is_spaces: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for SpaceSeed<'_, '_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for SpaceSeed<'_, '_> {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a metric file")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut object = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let old = self.old.and_then(|old| old.get(&key));
+            let value = if self.is_spaces {
+                map.next_value()?
+            } else if key == self.schema.spaces {
+                map.next_value_seed(SpaceSeed {
+                    old,
+                    path: &mut *self.path,
+                    dropped: &mut *self.dropped,
+                    schema: self.schema,
+                    is_spaces: true,
+                })?
+            } else if key == self.schema.metrics {
+                let metrics: Value = map.next_value()?;
+                if old == Some(&metrics) {
+                    self.dropped.push(self.path.clone());
+                    continue;
+                }
+                metrics
+            } else {
+                map.next_value()?
+            };
+            object.insert(key, value);
+        }
+        Ok(Value::Object(object))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::new();
+        if !self.is_spaces {
+            while let Some(value) = seq.next_element()? {
+                values.push(value);
+            }
+            return Ok(Value::Array(values));
+        }
+        let old_spaces = self.old.and_then(Value::as_array);
+        loop {
+            self.path.push(values.len());
+            let space = seq.next_element_seed(SpaceSeed {
+                old: old_spaces.and_then(|old_spaces| old_spaces.get(values.len())),
+                path: &mut *self.path,
+                dropped: &mut *self.dropped,
+                schema: self.schema,
+                is_spaces: false,
+            });
+            self.path.pop();
+            match space? {
+                Some(space) => values.push(space),
+                None => return Ok(Value::Array(values)),
+            }
+        }
+    }
+
+    fn visit_bool<E: Error>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E: Error>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_u64<E: Error>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_f64<E: Error>(self, value: f64) -> Result<Value, E> {
+        Ok(Number::from_f64(value).map_or(Value::Null, Value::Number))
+    }
+
+    fn visit_str<E: Error>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_owned()))
+    }
+
+    fn visit_string<E: Error>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+}
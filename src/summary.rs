@@ -0,0 +1,484 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::{Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::baseline::Baseline;
+use crate::by_metric::MetricGroups;
+use crate::error::ExtractionError;
+use crate::grammar_diff::GrammarDiff;
+use crate::log::verbose;
+use crate::results_db::DbDiff;
+use crate::review::ReviewFile;
+use crate::schema::Schema;
+use crate::top::DeferredReport;
+use crate::{CodeSnippets, LinesRange, SnippetDiff};
+
+#[derive(Default)]
+pub(crate) struct MetricStats {
+    // Number of spaces where the metric changed
+    pub(crate) spaces: usize,
+    // Sum of the numeric differences between the new and the old values
+    pub(crate) delta: f64,
+}
+
+/// The diffs found in a source file.
+pub(crate) struct FileStats {
+    pub(crate) name: String,
+    pub(crate) diffs: usize,
+    // Sum of the severities of the diffs
+    pub(crate) severity: f64,
+    // Names of the metrics which differ
+    pub(crate) metrics: BTreeSet<String>,
+}
+
+/// Outcome of the comparison of a pair of metric files.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum PairStatus {
+    Reported,
+    Identical,
+    // Every diff was filtered out, or the source was not selected
+    Filtered,
+    SourceMissing,
+    ParseError,
+    // An empty or whitespace-only input file
+    TooSmall,
+    ReadError,
+    OutputError,
+    // The comparison panicked
+    Panicked,
+}
+
+impl PairStatus {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            PairStatus::Reported => "reported",
+            PairStatus::Identical => "identical",
+            PairStatus::Filtered => "filtered",
+            PairStatus::SourceMissing => "source-missing",
+            PairStatus::ParseError => "parse-error",
+            PairStatus::TooSmall => "too-small",
+            PairStatus::ReadError => "read-error",
+            PairStatus::OutputError => "output-error",
+            PairStatus::Panicked => "panicked",
+        }
+    }
+
+    /// Whether the pair could not be compared.
+    pub(crate) fn is_error(self) -> bool {
+        matches!(
+            self,
+            PairStatus::ParseError
+                | PairStatus::TooSmall
+                | PairStatus::ReadError
+                | PairStatus::OutputError
+                | PairStatus::Panicked
+        )
+    }
+
+    pub(crate) fn from_error(error: &ExtractionError) -> Self {
+        match error {
+            ExtractionError::Read { .. } => PairStatus::ReadError,
+            ExtractionError::Empty { .. } => PairStatus::TooSmall,
+            ExtractionError::Output(_) => PairStatus::OutputError,
+            ExtractionError::Parse { .. }
+            | ExtractionError::Decode { .. }
+            | ExtractionError::Schema { .. }
+            | ExtractionError::MissingSpace { .. }
+            | ExtractionError::MissingLine { .. }
+            | ExtractionError::MissingName
+            | ExtractionError::Layout(_) => PairStatus::ParseError,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Summary {
+    pub(crate) metrics: BTreeMap<String, MetricStats>,
+    // The metric stats of each language
+    pub(crate) languages: BTreeMap<String, BTreeMap<String, MetricStats>>,
+    pub(crate) files: Vec<FileStats>,
+    // Empty or whitespace-only input files
+    pub(crate) corrupt: Vec<PathBuf>,
+    // Source files whose reports come from repaired JSON files
+    pub(crate) partial: Vec<String>,
+    // Source files which differ from the ones the metrics were computed on
+    pub(crate) stale: Vec<String>,
+    // A pair failed with --fail-fast, the pairs left are skipped
+    pub(crate) stopped: bool,
+    // Name of each compared pair, the new file or the document name,
+    // and the outcome of its comparison
+    pub(crate) pairs: Vec<(String, PairStatus)>,
+    // The diffs found, when accepting them
+    pub(crate) accepted: Baseline,
+    // Failure message of each pair with diffs, in the junit format
+    pub(crate) failures: BTreeMap<String, String>,
+    // Number of diffs and report sections of each source file,
+    // for the single report
+    pub(crate) sections: BTreeMap<String, (usize, String)>,
+    // The diffs of all the source files by metric, for --group-by metric
+    pub(crate) by_metric: MetricGroups,
+    // The snippets of each source file, waiting for the top ones of the run
+    // or for the output budget
+    pub(crate) deferred: Vec<DeferredReport>,
+    // Source file and lines of the snippets left out by --max-output-mb
+    pub(crate) omitted: Vec<(String, LinesRange)>,
+    // The diffs of the run, for the results database
+    pub(crate) db_diffs: Vec<DbDiff>,
+    // Minimal tests of each source file, for the interactive review
+    pub(crate) review: BTreeMap<String, ReviewFile>,
+    // Report written for each source file
+    pub(crate) reports: BTreeMap<String, String>,
+    report_names: HashSet<String>,
+    // Source files whose report name was taken, with the name
+    // and the one used instead
+    pub(crate) collisions: Vec<(String, String, String)>,
+    // Files written in the output directory
+    pub(crate) artifacts: Vec<String>,
+    // Files of the minimal tests written with --emit-fixtures
+    pub(crate) fixtures: Vec<PathBuf>,
+    // The grammar change under test
+    pub(crate) grammar_diff: Option<GrammarDiff>,
+}
+
+/// Returns the metric name of a diff path, e.g. `cyclomatic.sum`
+/// for `.spaces[0].metrics.cyclomatic.sum`, or the last key of the path
+/// outside of the metrics, e.g. `start_line` for `.spaces[0].start_line`
+/// and `spaces` for the added space `.spaces[0].spaces[3]`.
+pub(crate) fn metric_name<'a>(path: &'a str, schema: &Schema) -> &'a str {
+    path.rsplit_once(&format!(".{}.", schema.metrics))
+        .map(|(_, name)| name)
+        .unwrap_or_else(|| {
+            let key = path.rsplit('.').next().unwrap_or(path);
+            key.split('[').next().unwrap_or(key)
+        })
+}
+
+/// Returns the change of a numeric diff with `precision` decimals,
+/// followed by its percentage of the old value when this is not zero,
+/// e.g. `+2.00 (+12.50%)`.
+pub(crate) fn format_change(diff: &SnippetDiff, precision: usize) -> Option<String> {
+    let old = diff.old.parse::<f64>().ok()?;
+    let delta = diff.delta()?;
+    Some(if old == 0. {
+        format!("{delta:+.precision$}")
+    } else {
+        format!(
+            "{delta:+.precision$} ({:+.precision$}%)",
+            delta / old.abs() * 100.
+        )
+    })
+}
+
+/// Returns how much a diff matters: the relative change of a number,
+/// e.g. 0.5 from 2 to 3, at least as much as its absolute change below 1.
+/// Any other change counts as 1.
+pub(crate) fn severity(diff: &SnippetDiff) -> f64 {
+    match (diff.old.parse::<f64>(), diff.new.parse::<f64>()) {
+        (Ok(old), Ok(new)) => (new - old).abs() / old.abs().max(1.),
+        _ => 1.,
+    }
+}
+
+/// Reads the total number of diffs from a summary written by a previous run.
+pub(crate) fn read_total_diffs(path: &Path) -> std::io::Result<usize> {
+    let json: Value = serde_json::from_slice(&std::fs::read(path)?)?;
+    json.get("total_diffs")
+        .and_then(Value::as_u64)
+        .map(|total| total as usize)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "missing `total_diffs`"))
+}
+
+impl Summary {
+    pub(crate) fn add(&mut self, snippets: &CodeSnippets, schema: &Schema) {
+        let diffs = snippets
+            .global_metrics
+            .iter()
+            .chain(snippets.snippets_data.values().flatten())
+            .chain(snippets.positions.values().flatten())
+            .chain(&snippets.space_changes);
+        let language = self.languages.entry(snippets.language.clone()).or_default();
+        let mut count = 0;
+        let mut file_severity = 0.;
+        let mut file_metrics = BTreeSet::new();
+        for diff in diffs {
+            let name = metric_name(&diff.path, schema);
+            file_metrics.insert(name.to_owned());
+            let delta = diff.delta().unwrap_or(0.);
+            for stats in [
+                self.metrics.entry(name.to_owned()).or_default(),
+                language.entry(name.to_owned()).or_default(),
+            ] {
+                stats.spaces += 1;
+                stats.delta += delta;
+            }
+            count += 1;
+            file_severity += severity(diff);
+        }
+        if count > 0 {
+            self.files.push(FileStats {
+                name: snippets.source_filename.clone(),
+                diffs: count,
+                severity: file_severity,
+                metrics: file_metrics,
+            });
+        }
+        if snippets.partial {
+            self.partial.push(snippets.source_filename.clone());
+        }
+    }
+
+    /// Returns the name of the report of a source file, adding a numeric
+    /// suffix to the given name when it is taken by another source file,
+    /// or by a file of a previous run for which `exists` is true.
+    pub(crate) fn reserve_report_name(
+        &mut self,
+        source_filename: &str,
+        name: String,
+        exists: impl Fn(&str) -> bool,
+    ) -> String {
+        // A source file compared again keeps its report
+        if let Some(reserved) = self.reports.get(source_filename) {
+            return reserved.clone();
+        }
+        let (stem, extension) = name.rsplit_once('.').unwrap_or((&name, ""));
+        let mut reserved = name.clone();
+        let mut suffix = 2;
+        while self.report_names.contains(&reserved) || exists(&reserved) {
+            reserved = format!("{stem}-{suffix}.{extension}");
+            suffix += 1;
+        }
+        if reserved != name {
+            // The versions of the reports of previous runs are not collisions
+            if self.report_names.contains(&name) {
+                eprintln!(
+                    "{source_filename}: the report name `{name}` is taken, using `{reserved}`"
+                );
+                self.collisions
+                    .push((source_filename.to_owned(), name, reserved.clone()));
+            } else {
+                verbose!("{source_filename}: the report `{name}` exists, using `{reserved}`");
+            }
+        }
+        self.report_names.insert(reserved.clone());
+        self.reports
+            .insert(source_filename.to_owned(), reserved.clone());
+        reserved
+    }
+
+    pub(crate) fn add_error(&mut self, error: &ExtractionError) {
+        if let ExtractionError::Empty { path } = error {
+            self.corrupt.push(path.clone());
+        }
+    }
+
+    pub(crate) fn add_status(&mut self, name: String, status: PairStatus) {
+        self.pairs.push((name, status));
+    }
+
+    /// Returns the number of pairs which could not be compared.
+    pub(crate) fn failed_pairs(&self) -> usize {
+        self.pairs
+            .iter()
+            .filter(|(_, status)| status.is_error())
+            .count()
+    }
+
+    pub(crate) fn total_diffs(&self) -> usize {
+        self.files.iter().map(|file| file.diffs).sum()
+    }
+
+    fn top_files(&self, top: usize) -> Vec<&FileStats> {
+        let mut files: Vec<&FileStats> = self.files.iter().collect();
+        files.sort_by(|a, b| b.diffs.cmp(&a.diffs).then_with(|| a.name.cmp(&b.name)));
+        files.truncate(top);
+        files
+    }
+
+    /// Returns the files with the most severe diffs.
+    pub(crate) fn top_regressions(&self, top: usize) -> Vec<&FileStats> {
+        let mut files: Vec<&FileStats> = self.files.iter().collect();
+        files.sort_by(|a, b| {
+            b.severity
+                .total_cmp(&a.severity)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        files.truncate(top);
+        files
+    }
+
+    pub(crate) fn write_table<W: Write>(&self, writer: &mut W, top: usize) -> std::io::Result<()> {
+        if let Some(grammar_diff) = &self.grammar_diff {
+            grammar_diff.write_header(writer)?;
+        }
+        writeln!(
+            writer,
+            "{} diffs in {} files",
+            self.total_diffs(),
+            self.files.len()
+        )?;
+        if !self.corrupt.is_empty() {
+            writeln!(writer, "\nCorrupt inputs")?;
+            for path in &self.corrupt {
+                writeln!(writer, "          {}", path.display())?;
+            }
+        }
+        if !self.partial.is_empty() {
+            writeln!(writer, "\nPartial reports")?;
+            for name in &self.partial {
+                writeln!(writer, "          {name}")?;
+            }
+        }
+        if !self.stale.is_empty() {
+            writeln!(writer, "\nStale sources")?;
+            for name in &self.stale {
+                writeln!(writer, "          {name}")?;
+            }
+        }
+        if !self.collisions.is_empty() {
+            writeln!(writer, "\nReport name collisions")?;
+            for (name, _, renamed) in &self.collisions {
+                writeln!(writer, "          {name} -> {renamed}")?;
+            }
+        }
+        if !self.omitted.is_empty() {
+            writeln!(writer, "\nLeft out by the output budget")?;
+            for (name, lines_range) in &self.omitted {
+                writeln!(
+                    writer,
+                    "          {name} ({}, {})",
+                    lines_range.start_line + 1,
+                    lines_range.end_line
+                )?;
+            }
+        }
+        if self.metrics.is_empty() {
+            return Ok(());
+        }
+
+        let width = self.metrics.keys().map(String::len).max().unwrap_or(0);
+        writeln!(
+            writer,
+            "\n{:width$}  {:>8}  {:>12}",
+            "Metric", "Spaces", "Delta"
+        )?;
+        for (name, stats) in &self.metrics {
+            writeln!(
+                writer,
+                "{:width$}  {:>8}  {:>+12.2}",
+                name, stats.spaces, stats.delta
+            )?;
+        }
+
+        if self.languages.len() > 1 {
+            self.write_language_matrix(writer, width)?;
+        }
+
+        writeln!(writer, "\nFiles with the most diffs")?;
+        for file in self.top_files(top) {
+            writeln!(writer, "{:>8}  {}", file.diffs, file.name)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the spaces and the delta of each metric for each language,
+    /// a row for each metric and a column for each language.
+    fn write_language_matrix<W: Write>(&self, writer: &mut W, width: usize) -> std::io::Result<()> {
+        // Wide enough for the spaces and the delta, e.g. `12 / +3.50`
+        let cell = |stats: Option<&MetricStats>| {
+            stats.map_or_else(
+                || String::from("-"),
+                |stats| format!("{} / {:+.2}", stats.spaces, stats.delta),
+            )
+        };
+        let column_width = self
+            .languages
+            .iter()
+            .flat_map(|(language, metrics)| {
+                std::iter::once(language.len())
+                    .chain(metrics.values().map(|stats| cell(Some(stats)).len()))
+            })
+            .max()
+            .unwrap_or(0);
+
+        writeln!(writer, "\nSpaces / delta by language")?;
+        write!(writer, "{:width$}", "Metric")?;
+        for language in self.languages.keys() {
+            write!(writer, "  {language:>column_width$}")?;
+        }
+        writeln!(writer)?;
+        for name in self.metrics.keys() {
+            write!(writer, "{name:width$}")?;
+            for metrics in self.languages.values() {
+                write!(writer, "  {:>column_width$}", cell(metrics.get(name)))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn to_json(&self, top: usize) -> Value {
+        let metrics_json =
+            |metrics: &BTreeMap<String, MetricStats>| -> serde_json::Map<String, Value> {
+                metrics
+                    .iter()
+                    .map(|(name, stats)| {
+                        (
+                            name.clone(),
+                            json!({ "spaces": stats.spaces, "delta": stats.delta }),
+                        )
+                    })
+                    .collect()
+            };
+        let languages: serde_json::Map<String, Value> = self
+            .languages
+            .iter()
+            .map(|(language, metrics)| (language.clone(), Value::Object(metrics_json(metrics))))
+            .collect();
+        let top_files: Vec<Value> = self
+            .top_files(top)
+            .into_iter()
+            .map(
+                |file| json!({ "name": file.name, "diffs": file.diffs, "severity": file.severity }),
+            )
+            .collect();
+        let pairs: Vec<Value> = self
+            .pairs
+            .iter()
+            .map(|(name, status)| json!({ "name": name, "status": status.name() }))
+            .collect();
+        let collisions: Vec<Value> = self
+            .collisions
+            .iter()
+            .map(|(source, name, renamed)| {
+                json!({ "source": source, "name": name, "renamed_to": renamed })
+            })
+            .collect();
+        let omitted: Vec<Value> = self
+            .omitted
+            .iter()
+            .map(|(source, lines_range)| {
+                json!({
+                    "source": source,
+                    "start_line": lines_range.start_line + 1,
+                    "end_line": lines_range.end_line,
+                })
+            })
+            .collect();
+        json!({
+            "total_diffs": self.total_diffs(),
+            "files_with_diffs": self.files.len(),
+            "metrics": metrics_json(&self.metrics),
+            "languages": languages,
+            "top_files": top_files,
+            "corrupt_inputs": self.corrupt,
+            "partial_reports": self.partial,
+            "stale_sources": self.stale,
+            "report_collisions": collisions,
+            "omitted_snippets": omitted,
+            "pairs": pairs,
+            "grammar_diff": self.grammar_diff.as_ref().map(GrammarDiff::to_json),
+        })
+    }
+}
@@ -0,0 +1,156 @@
+use std::io::Write;
+
+use serde_json::{Map, Value};
+
+use crate::json_report::JsonReport;
+use crate::report::{Report, ReportWriter};
+use crate::{LinesRange, SnippetDiff};
+
+/// The report as a TOML document, with the fields of the JSON report.
+/// TOML has no null, so the fields without a value are left out.
+#[derive(Default)]
+pub(crate) struct TomlReport {
+    json: JsonReport,
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn escape(text: &str, multiline: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' if multiline => escaped.push('\n'),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push('\t'),
+            '\r' => escaped.push_str("\\r"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn string(text: &str) -> String {
+    if text.contains('\n') {
+        // The newline after the opening quotes is not part of the string
+        format!("\"\"\"\n{}\"\"\"", escape(text, true))
+    } else {
+        format!("\"{}\"", escape(text, false))
+    }
+}
+
+fn key(key: &str) -> String {
+    if is_bare_key(key) {
+        key.to_owned()
+    } else {
+        string(key)
+    }
+}
+
+// A value written after its key, the tables being inline ones
+fn inline_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(value) => value.to_string(),
+        // serde_json writes the floats with a decimal point or an exponent,
+        // as TOML requires
+        Value::Number(number) => number.to_string(),
+        Value::String(text) => string(text),
+        Value::Array(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .filter(|item| !item.is_null())
+                .map(inline_value)
+                .collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Object(map) => {
+            let entries: Vec<String> = map
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(name, value)| format!("{} = {}", key(name), inline_value(value)))
+                .collect();
+            if entries.is_empty() {
+                "{}".to_owned()
+            } else {
+                format!("{{ {} }}", entries.join(", "))
+            }
+        }
+    }
+}
+
+fn is_array_of_tables(value: &Value) -> bool {
+    matches!(value, Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object))
+}
+
+// Writes the entries of a table: its values first, then its tables and
+// its arrays of tables under their own headers
+pub(crate) fn write_table(
+    writer: &mut dyn Write,
+    path: &[String],
+    map: &Map<String, Value>,
+) -> std::io::Result<()> {
+    for (name, value) in map {
+        if !value.is_null() && !value.is_object() && !is_array_of_tables(value) {
+            writeln!(writer, "{} = {}", key(name), inline_value(value))?;
+        }
+    }
+    for (name, value) in map {
+        let mut path = path.to_vec();
+        path.push(key(name));
+        match value {
+            Value::Object(table) => {
+                writeln!(writer, "\n[{}]", path.join("."))?;
+                write_table(writer, &path, table)?;
+            }
+            Value::Array(items) if is_array_of_tables(value) => {
+                for item in items.iter().filter_map(Value::as_object) {
+                    writeln!(writer, "\n[[{}]]", path.join("."))?;
+                    write_table(writer, &path, item)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+impl ReportWriter for TomlReport {
+    fn write_header(&mut self, writer: &mut dyn Write, report: &Report) -> std::io::Result<()> {
+        self.json.write_header(writer, report)
+    }
+
+    fn write_global_metrics(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+    ) -> std::io::Result<()> {
+        self.json.write_global_metrics(writer, report)
+    }
+
+    fn write_snippet(
+        &mut self,
+        writer: &mut dyn Write,
+        report: &Report,
+        lines_range: &LinesRange,
+        diffs: &[SnippetDiff],
+    ) -> std::io::Result<()> {
+        self.json.write_snippet(writer, report, lines_range, diffs)
+    }
+
+    /// Writes the whole document, since its tables are only known once the
+    /// JSON report is complete.
+    fn finish(&mut self, writer: &mut dyn Write, report: &Report) -> std::io::Result<()> {
+        match self.json.take_report(report) {
+            Value::Object(map) => write_table(writer, &[], &map),
+            _ => Ok(()),
+        }
+    }
+}
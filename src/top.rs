@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::filter::{FilterRule, FilteredDiff};
+use crate::{CodeSnippets, LinesRange, SnippetDiff, SortBy};
+
+/// The snippets of a source file, kept until the ones of the whole run
+/// are known to report only the top ones.
+pub(crate) struct DeferredReport {
+    pub(crate) snippets: CodeSnippets,
+    pub(crate) source_file: String,
+    // The new metric file, naming the pair in the JUnit report
+    pub(crate) metric_path: PathBuf,
+}
+
+// How much a snippet stands out: its largest absolute change,
+// or its number of diffs
+pub(crate) fn score(diffs: &[SnippetDiff], sort_by: SortBy) -> f64 {
+    match sort_by {
+        SortBy::Delta => diffs
+            .iter()
+            .filter_map(|diff| diff.delta())
+            .map(f64::abs)
+            .fold(0.0, f64::max),
+        SortBy::Count => diffs.len() as f64,
+    }
+}
+
+/// Keeps the `top` snippets of the run with the largest score, moving
+/// the diffs of the others, and the ones outside of any snippet, to the
+/// filtered diffs. The reports left without snippets are dropped.
+pub(crate) fn keep_top(reports: &mut Vec<DeferredReport>, top: usize, sort_by: SortBy) {
+    // The ties are broken by source file, then by line
+    reports.sort_by(|a, b| a.snippets.source_filename.cmp(&b.snippets.source_filename));
+    let mut candidates: Vec<(f64, usize, &LinesRange)> = reports
+        .iter()
+        .enumerate()
+        .flat_map(|(i, report)| {
+            report
+                .snippets
+                .snippets_data
+                .iter()
+                .map(move |(lines_range, diffs)| (score(diffs, sort_by), i, lines_range))
+        })
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.0.total_cmp(&a.0)
+            .then(a.1.cmp(&b.1))
+            .then(a.2.start_line.cmp(&b.2.start_line))
+            .then(a.2.end_line.cmp(&b.2.end_line))
+    });
+    let kept: HashSet<(usize, LinesRange)> = candidates
+        .into_iter()
+        .take(top)
+        .map(|(_, i, lines_range)| (i, lines_range.clone()))
+        .collect();
+
+    for (i, report) in reports.iter_mut().enumerate() {
+        let snippets = &mut report.snippets;
+        let mut left_out: Vec<SnippetDiff> = snippets
+            .global_metrics
+            .drain(..)
+            .chain(snippets.space_changes.drain(..))
+            .chain(
+                std::mem::take(&mut snippets.positions)
+                    .into_values()
+                    .flatten(),
+            )
+            .collect();
+        let ranges: Vec<LinesRange> = snippets.snippets_data.keys().cloned().collect();
+        for lines_range in ranges {
+            if !kept.contains(&(i, lines_range.clone())) {
+                left_out.extend(
+                    snippets
+                        .snippets_data
+                        .remove(&lines_range)
+                        .unwrap_or_default(),
+                );
+            }
+        }
+        snippets
+            .filtered
+            .extend(left_out.into_iter().map(|diff| FilteredDiff {
+                diff,
+                rule: FilterRule::Top,
+            }));
+        snippets
+            .filtered
+            .sort_by(|a, b| a.diff.path.cmp(&b.diff.path));
+    }
+    reports.retain(|report| !report.snippets.snippets_data.is_empty());
+}
+
+/// Keeps the `max_snippets` snippets of a source file with the largest
+/// change, moving the diffs of the others to the filtered diffs, and
+/// returns the number of snippets left out.
+pub(crate) fn keep_largest_snippets(snippets: &mut CodeSnippets, max_snippets: usize) -> usize {
+    if snippets.snippets_data.len() <= max_snippets {
+        return 0;
+    }
+    let mut ranges: Vec<(f64, LinesRange)> = snippets
+        .snippets_data
+        .iter()
+        .map(|(lines_range, diffs)| (score(diffs, SortBy::Delta), lines_range.clone()))
+        .collect();
+    // The ties are broken by line
+    ranges.sort_by(|a, b| {
+        b.0.total_cmp(&a.0)
+            .then(a.1.start_line.cmp(&b.1.start_line))
+            .then(a.1.end_line.cmp(&b.1.end_line))
+    });
+    let left_out = ranges.split_off(max_snippets);
+    for (_, lines_range) in &left_out {
+        let diffs = snippets
+            .snippets_data
+            .remove(lines_range)
+            .unwrap_or_default();
+        snippets
+            .filtered
+            .extend(diffs.into_iter().map(|diff| FilteredDiff {
+                diff,
+                rule: FilterRule::PerFile,
+            }));
+    }
+    left_out.len()
+}
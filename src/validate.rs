@@ -0,0 +1,134 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::schema::Schema;
+
+#[derive(Debug)]
+pub(crate) enum SchemaErrorKind {
+    NotAnObject,
+    MissingField(String),
+    WrongType {
+        field: String,
+        expected: &'static str,
+    },
+    InvalidLineRange {
+        start_line: u64,
+        end_line: u64,
+    },
+}
+
+#[derive(Debug)]
+pub(crate) struct SchemaError {
+    // JSON pointer to the space containing the error, e.g. /spaces/0/spaces/3
+    pub(crate) pointer: String,
+    pub(crate) kind: SchemaErrorKind,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "(root)"
+        } else {
+            &self.pointer
+        };
+        match &self.kind {
+            SchemaErrorKind::NotAnObject => write!(f, "{pointer}: a space should be an object"),
+            SchemaErrorKind::MissingField(field) => {
+                write!(f, "{pointer}: missing field `{field}`")
+            }
+            SchemaErrorKind::WrongType { field, expected } => {
+                write!(f, "{pointer}: field `{field}` should be {expected}")
+            }
+            SchemaErrorKind::InvalidLineRange {
+                start_line,
+                end_line,
+            } => write!(
+                f,
+                "{pointer}: invalid line range ({start_line}, {end_line})"
+            ),
+        }
+    }
+}
+
+/// Checks that a JSON value has the layout of the schema,
+/// returning every problem found instead of stopping at the first one.
+pub(crate) fn validate(json: &Value, schema: &Schema) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    validate_space(json, String::new(), schema, &mut errors);
+    errors
+}
+
+fn validate_space(space: &Value, pointer: String, schema: &Schema, errors: &mut Vec<SchemaError>) {
+    let mut push = |kind| {
+        errors.push(SchemaError {
+            pointer: pointer.clone(),
+            kind,
+        })
+    };
+
+    if !space.is_object() {
+        push(SchemaErrorKind::NotAnObject);
+        return;
+    }
+
+    match space.get(&schema.name) {
+        None => push(SchemaErrorKind::MissingField(schema.name.clone())),
+        // Anonymous spaces have a null name
+        Some(name) if !(name.is_string() || name.is_null()) => push(SchemaErrorKind::WrongType {
+            field: schema.name.clone(),
+            expected: "a string",
+        }),
+        _ => {}
+    }
+
+    let mut line = |field: &String| match space.get(field) {
+        None => {
+            push(SchemaErrorKind::MissingField(field.clone()));
+            None
+        }
+        Some(value) => match value.as_u64() {
+            Some(line) if line > 0 => Some(line),
+            _ => {
+                push(SchemaErrorKind::WrongType {
+                    field: field.clone(),
+                    expected: "a positive integer",
+                });
+                None
+            }
+        },
+    };
+    let start_line = line(&schema.start_line);
+    let end_line = line(&schema.end_line);
+    if let (Some(start_line), Some(end_line)) = (start_line, end_line) {
+        if start_line > end_line {
+            push(SchemaErrorKind::InvalidLineRange {
+                start_line,
+                end_line,
+            });
+        }
+    }
+
+    match space.get(&schema.metrics) {
+        None => push(SchemaErrorKind::MissingField(schema.metrics.clone())),
+        Some(metrics) if !metrics.is_object() => push(SchemaErrorKind::WrongType {
+            field: schema.metrics.clone(),
+            expected: "an object",
+        }),
+        _ => {}
+    }
+
+    match space.get(&schema.spaces) {
+        None => push(SchemaErrorKind::MissingField(schema.spaces.clone())),
+        Some(Value::Array(spaces)) => {
+            for (i, child) in spaces.iter().enumerate() {
+                let pointer = format!("{pointer}/{}/{i}", schema.spaces);
+                validate_space(child, pointer, schema, errors);
+            }
+        }
+        Some(_) => push(SchemaErrorKind::WrongType {
+            field: schema.spaces.clone(),
+            expected: "an array",
+        }),
+    }
+}
@@ -0,0 +1,173 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::channel::unbounded;
+
+use crate::gitignore::Ignores;
+use crate::glob::Glob;
+use crate::log::{log, Event};
+
+fn is_hidden(name: &OsStr) -> bool {
+    name.to_str().map(|s| s.starts_with('.')).unwrap_or(false)
+}
+
+/// What the walks of the compared directories skip.
+#[derive(Debug, Default)]
+pub(crate) struct WalkOptions {
+    // Extensions of the metric files, without the leading dot,
+    // e.g. json or json.gz
+    pub(crate) extensions: Vec<String>,
+    // Walk the hidden files and directories too, except .git
+    pub(crate) hidden: bool,
+    // Files in the format of .gitignore applied to the walked directories
+    pub(crate) ignore_files: Vec<PathBuf>,
+    // Depth of the deepest files walked, 1 for the files of the directory
+    pub(crate) max_depth: Option<usize>,
+    // Globs matched against the paths of the directories not descended,
+    // relative to the walked directory
+    pub(crate) prune: Vec<Glob>,
+}
+
+impl WalkOptions {
+    /// Checks whether a file is a metric file from its extension, the
+    /// other files of the walked directories being skipped.
+    pub(crate) fn is_metric_file(&self, path: &Path) -> bool {
+        path.file_name()
+            .and_then(OsStr::to_str)
+            .is_some_and(|name| {
+                self.extensions.iter().any(|extension| {
+                    name.strip_suffix(extension.as_str())
+                        .and_then(|stem| stem.strip_suffix('.'))
+                        .is_some_and(|stem| !stem.is_empty())
+                })
+            })
+    }
+
+    // Why an entry of the walked directory at a path relative to it is
+    // skipped, if it is
+    fn skip_reason(
+        &self,
+        relative: &Path,
+        is_dir: bool,
+        ignores: &Ignores,
+    ) -> Option<&'static str> {
+        let depth = relative.components().count();
+        if self
+            .max_depth
+            .is_some_and(|max_depth| depth > max_depth || (is_dir && depth == max_depth))
+        {
+            Some("deeper than --max-depth")
+        } else if is_dir
+            && self
+                .prune
+                .iter()
+                .any(|glob| glob.is_match(&relative.to_string_lossy()))
+        {
+            Some("pruned with --prune")
+        } else if ignores.is_ignored(relative, is_dir) {
+            Some("ignored by an ignore file")
+        } else {
+            None
+        }
+    }
+}
+
+/// Walks a directory on `num_threads` threads and calls `on_file` for each
+/// file, with its path relative to the directory. Hidden files and
+/// directories are skipped unless the walk options include them, together
+/// with the ones ignored by the `.gitignore` and `.ignore` files and by
+/// the walk options.
+pub(crate) fn walk_files<F: Fn(PathBuf) + Sync>(
+    root: &Path,
+    num_threads: usize,
+    walk_options: &WalkOptions,
+    on_file: F,
+) {
+    // Directories still to be read, relative to the root, with the
+    // ignore files which apply to them
+    let (dir_sender, dir_receiver) = unbounded::<(PathBuf, Ignores)>();
+    // Directories queued or being read
+    let pending = AtomicUsize::new(1);
+    dir_sender
+        .send((
+            PathBuf::new(),
+            Ignores::new(root, &walk_options.ignore_files),
+        ))
+        .unwrap();
+
+    thread::scope(|scope| {
+        for _ in 0..num_threads.max(1) {
+            let dir_sender = dir_sender.clone();
+            let dir_receiver = dir_receiver.clone();
+            let pending = &pending;
+            let on_file = &on_file;
+            scope.spawn(move || loop {
+                let (dir, ignores) = match dir_receiver.recv_timeout(Duration::from_millis(10)) {
+                    Ok(dir) => dir,
+                    Err(_) if pending.load(Ordering::SeqCst) == 0 => break,
+                    Err(_) => continue,
+                };
+                if let Ok(entries) = std::fs::read_dir(root.join(&dir)) {
+                    for entry in entries.flatten() {
+                        let name = entry.file_name();
+                        if is_hidden(&name) && (!walk_options.hidden || name == ".git") {
+                            continue;
+                        }
+                        let relative = dir.join(&name);
+                        let Ok(file_type) = entry.file_type() else {
+                            continue;
+                        };
+                        if let Some(reason) =
+                            walk_options.skip_reason(&relative, file_type.is_dir(), &ignores)
+                        {
+                            log(Event::Skipped {
+                                name: &root.join(&relative).display().to_string(),
+                                reason,
+                            });
+                            continue;
+                        }
+                        match file_type {
+                            file_type if file_type.is_dir() => {
+                                let ignores = ignores.enter(root, &relative);
+                                pending.fetch_add(1, Ordering::SeqCst);
+                                dir_sender.send((relative, ignores)).unwrap();
+                            }
+                            file_type if file_type.is_file() => on_file(relative),
+                            _ => {}
+                        }
+                    }
+                }
+                pending.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+}
+
+/// Walks the first directory on `num_threads` threads and calls `on_pair`
+/// for each JSON file which also exists at the same relative path inside
+/// the second directory, skipping the same files as [`walk_files`].
+pub(crate) fn parallel_walk<F: Fn(PathBuf, PathBuf) + Sync>(
+    root1: &Path,
+    root2: &Path,
+    num_threads: usize,
+    walk_options: &WalkOptions,
+    on_pair: F,
+) {
+    walk_files(root1, num_threads, walk_options, |relative| {
+        let path2 = root2.join(&relative);
+        if !walk_options.is_metric_file(&relative) {
+            return;
+        }
+        if path2.is_file() {
+            on_pair(root1.join(relative), path2);
+        } else {
+            log(Event::Skipped {
+                name: &root1.join(&relative).display().to_string(),
+                reason: &format!("not in {}", root2.display()),
+            });
+        }
+    });
+}
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Time between two scans of the watched directories.
+pub(crate) const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+type Pair = (PathBuf, PathBuf);
+
+fn pair_modified((path1, path2): &Pair) -> Option<SystemTime> {
+    let modified1 = path1.metadata().and_then(|metadata| metadata.modified());
+    let modified2 = path2.metadata().and_then(|metadata| metadata.modified());
+    modified1.ok().max(modified2.ok())
+}
+
+/// Tracks the modification times of the pairs of files, by polling them,
+/// to find the ones added or modified since the previous scan.
+pub(crate) struct Watcher {
+    modified: HashMap<Pair, Option<SystemTime>>,
+}
+
+impl Watcher {
+    pub(crate) fn new(pairs: Vec<Pair>) -> Self {
+        let modified = pairs
+            .into_iter()
+            .map(|pair| {
+                let modified = pair_modified(&pair);
+                (pair, modified)
+            })
+            .collect();
+        Self { modified }
+    }
+
+    /// Returns the pairs added or modified since the previous call,
+    /// given the pairs found now.
+    pub(crate) fn changed(&mut self, pairs: Vec<Pair>) -> Vec<Pair> {
+        let current = Self::new(pairs).modified;
+        let mut changed: Vec<Pair> = current
+            .iter()
+            .filter(|(pair, modified)| self.modified.get(*pair) != Some(*modified))
+            .map(|(pair, _)| pair.clone())
+            .collect();
+        changed.sort();
+        self.modified = current;
+        changed
+    }
+}
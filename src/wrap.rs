@@ -0,0 +1,75 @@
+// Removes the indentation common to the lines which are not blank
+fn dedent<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let indentation = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indentation..).unwrap_or_else(|| line.trim_start()))
+        .collect()
+}
+
+fn push_indented(code: &mut String, lines: &[&str], indentation: &str) {
+    for line in lines {
+        if !line.is_empty() {
+            code.push_str(indentation);
+            code.push_str(line);
+        }
+        code.push('\n');
+    }
+}
+
+/// A Rust snippet in a module of its own, in the `impl` of a type when it
+/// is a method, e.g. `fn area(&self) -> f64`.
+fn wrap_rust(lines: &[&str]) -> String {
+    let is_method = lines
+        .iter()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| line.contains("fn ") && line.contains("self"));
+    let mut code = String::from("#[allow(dead_code, unused)]\nmod minimal_test {\n");
+    if is_method {
+        code.push_str("    struct MinimalTest;\n\n    impl MinimalTest {\n");
+        push_indented(&mut code, lines, "        ");
+        code.push_str("    }\n");
+    } else {
+        push_indented(&mut code, lines, "    ");
+    }
+    code.push_str("}\n");
+    code
+}
+
+/// A C or C++ snippet after the `#include` lines of its source file.
+fn wrap_c(lines: &[&str], source_file: &str) -> String {
+    let mut code = String::new();
+    let includes: Vec<&str> = source_file
+        .lines()
+        .filter(|line| line.trim_start().starts_with("#include"))
+        .filter(|include| !lines.contains(include))
+        .collect();
+    if !includes.is_empty() {
+        push_indented(&mut code, &includes, "");
+        code.push('\n');
+    }
+    push_indented(&mut code, lines, "");
+    code
+}
+
+/// Returns the code of a minimal test in a scaffold which compiles on its
+/// own in the language of its source file, for the fixtures of the tests
+/// of rust-code-analysis: a Rust snippet in a module, a C one with the
+/// includes of its file, a Python one at module level.
+pub(crate) fn wrap(lines: &[&str], source_file: &str, language: &str) -> String {
+    let lines = dedent(lines);
+    match language {
+        "Rust" => wrap_rust(&lines),
+        "C" | "C++" => wrap_c(&lines, source_file),
+        _ => {
+            let mut code = String::new();
+            push_indented(&mut code, &lines, "");
+            code
+        }
+    }
+}
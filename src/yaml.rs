@@ -0,0 +1,531 @@
+use std::fmt;
+
+use serde_json::{Map, Number, Value};
+
+/// An error of a YAML document, at a line counted from 1.
+#[derive(Debug)]
+pub(crate) struct YamlError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for YamlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+// Cuts a comment at the end of a line, outside of the quoted strings
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q && previous != '\\' => quote = None,
+            (None, '#') if previous == ' ' => return &text[..i],
+            _ => {}
+        }
+        previous = c;
+    }
+    text
+}
+
+// Finds the `: ` separating the key of a mapping entry from its value,
+// outside of the quoted strings and of the flow collections
+fn key_separator(text: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut depth = 0;
+    let bytes = text.as_bytes();
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if i == 0 => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            (None, ':') if depth == 0 && matches!(bytes.get(i + 1), None | Some(b' ')) => {
+                return Some(i)
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn is_sequence_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+fn unescape_double(text: &str) -> Result<String, String> {
+    let mut string = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            string.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => string.push('\n'),
+            Some('t') => string.push('\t'),
+            Some('r') => string.push('\r'),
+            Some('0') => string.push('\0'),
+            Some(c @ ('"' | '\\' | '/' | ' ')) => string.push(c),
+            Some(c @ ('x' | 'u' | 'U')) => {
+                let len = match c {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                let hex: String = chars.by_ref().take(len).collect();
+                // A short escape at the end of the string is not valid
+                let c = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == len)
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("invalid escape `\\{c}{hex}`"))?;
+                string.push(c);
+            }
+            c => return Err(format!("invalid escape `\\{}`", c.unwrap_or(' '))),
+        }
+    }
+    Ok(string)
+}
+
+// Parses a plain scalar: a null, a boolean, a number or else a string
+fn plain_scalar(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        // As serde_json writes them
+        ".nan" | ".NaN" | ".NAN" | ".inf" | ".Inf" | ".INF" | "-.inf" | "-.Inf" | "-.INF" => {
+            return Value::Null
+        }
+        _ => {}
+    }
+    let is_number = text
+        .trim_start_matches(['-', '+'])
+        .starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'));
+    if is_number {
+        if let Ok(number) = text.parse::<i64>() {
+            return Value::Number(number.into());
+        }
+        if let Ok(number) = text.parse::<u64>() {
+            return Value::Number(number.into());
+        }
+        if let Some(number) = text.parse::<f64>().ok().and_then(Number::from_f64) {
+            return Value::Number(number);
+        }
+    }
+    Value::String(text.to_owned())
+}
+
+fn scalar(text: &str) -> Result<Value, String> {
+    let text = text.trim();
+    if let Some(quoted) = text.strip_prefix('"') {
+        let quoted = quoted
+            .strip_suffix('"')
+            .ok_or("unterminated double-quoted string")?;
+        Ok(Value::String(unescape_double(quoted)?))
+    } else if let Some(quoted) = text.strip_prefix('\'') {
+        let quoted = quoted
+            .strip_suffix('\'')
+            .ok_or("unterminated single-quoted string")?;
+        Ok(Value::String(quoted.replace("''", "'")))
+    } else if text.starts_with(['|', '>']) {
+        Err("block scalars are not supported".to_owned())
+    } else if text.starts_with(['&', '*', '!']) {
+        Err("anchors, aliases and tags are not supported".to_owned())
+    } else {
+        Ok(plain_scalar(text))
+    }
+}
+
+// A flow collection, e.g. `[1, 2]` or `{a: 1}`, or a scalar inside one
+struct Flow<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Flow<'_> {
+    fn skip_spaces(&mut self) {
+        while self.text[self.pos..].starts_with(' ') {
+            self.pos += 1;
+        }
+    }
+
+    fn scalar(&mut self, in_key: bool) -> Result<Value, String> {
+        let rest = &self.text[self.pos..];
+        let end = if rest.starts_with(['"', '\'']) {
+            let quote = rest.as_bytes()[0];
+            let mut end = 1;
+            let bytes = rest.as_bytes();
+            while end < bytes.len() && (bytes[end] != quote || bytes[end - 1] == b'\\') {
+                end += 1;
+            }
+            end + 1
+        } else {
+            rest.find(|c| matches!(c, ',' | ']' | '}') || (in_key && c == ':'))
+                .unwrap_or(rest.len())
+        };
+        let end = end.min(rest.len());
+        self.pos += end;
+        scalar(&rest[..end])
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_spaces();
+        if self.text[self.pos..].starts_with(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected `{c}` in a flow collection"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_spaces();
+        match self.text[self.pos..].chars().next() {
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_spaces();
+                    if self.text[self.pos..].starts_with(']') {
+                        self.pos += 1;
+                        return Ok(Value::Array(items));
+                    }
+                    items.push(self.value()?);
+                    self.skip_spaces();
+                    if !self.text[self.pos..].starts_with(']') {
+                        self.expect(',')?;
+                    }
+                }
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut map = Map::new();
+                loop {
+                    self.skip_spaces();
+                    if self.text[self.pos..].starts_with('}') {
+                        self.pos += 1;
+                        return Ok(Value::Object(map));
+                    }
+                    let key = match self.scalar(true)? {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    self.expect(':')?;
+                    map.insert(key, self.value()?);
+                    self.skip_spaces();
+                    if !self.text[self.pos..].starts_with('}') {
+                        self.expect(',')?;
+                    }
+                }
+            }
+            _ => self.scalar(false),
+        }
+    }
+}
+
+// A value written on the line of its key or of its sequence item
+fn inline_value(text: &str) -> Result<Value, String> {
+    if text.starts_with(['[', '{']) {
+        let mut flow = Flow { text, pos: 0 };
+        let value = flow.value()?;
+        flow.skip_spaces();
+        if flow.pos < text.len() {
+            return Err("unexpected text after a flow collection".to_owned());
+        }
+        Ok(value)
+    } else {
+        scalar(text)
+    }
+}
+
+struct Parser<'a> {
+    lines: Vec<Line<'a>>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> YamlError {
+        YamlError {
+            line: self
+                .lines
+                .get(self.pos)
+                .or(self.lines.last())
+                .map_or(1, |line| line.number),
+            message: message.into(),
+        }
+    }
+
+    // Parses the node starting at the current line
+    fn node(&mut self) -> Result<Value, YamlError> {
+        let line = &self.lines[self.pos];
+        if is_sequence_item(line.text) {
+            self.sequence(line.indent)
+        } else if key_separator(line.text).is_some() {
+            self.mapping(line.indent)
+        } else {
+            let value = inline_value(line.text).map_err(|message| self.error(message))?;
+            self.pos += 1;
+            Ok(value)
+        }
+    }
+
+    // Parses the node below a key or an item without an inline value,
+    // null when there is none
+    fn nested(&mut self, indent: usize, in_mapping: bool) -> Result<Value, YamlError> {
+        match self.lines.get(self.pos) {
+            Some(line) if line.indent > indent => self.node(),
+            // The items of a sequence can be as indented as its key
+            Some(line) if in_mapping && line.indent == indent && is_sequence_item(line.text) => {
+                self.sequence(indent)
+            }
+            _ => Ok(Value::Null),
+        }
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Value, YamlError> {
+        let mut map = Map::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.indent < indent || is_sequence_item(line.text) {
+                break;
+            }
+            if line.indent > indent {
+                return Err(self.error("unexpected indentation"));
+            }
+            let separator = key_separator(line.text)
+                .ok_or_else(|| self.error("expected a `key: value` entry"))?;
+            let key = match scalar(&line.text[..separator]).map_err(|m| self.error(m))? {
+                Value::String(key) => key,
+                key => key.to_string(),
+            };
+            let value = line.text[separator + 1..].trim();
+            let value = if value.is_empty() {
+                self.pos += 1;
+                self.nested(indent, true)?
+            } else {
+                let value = inline_value(value).map_err(|message| self.error(message))?;
+                self.pos += 1;
+                value
+            };
+            map.insert(key, value);
+        }
+        Ok(Value::Object(map))
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Value, YamlError> {
+        let mut items = Vec::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.indent != indent || !is_sequence_item(line.text) {
+                if line.indent > indent {
+                    return Err(self.error("unexpected indentation"));
+                }
+                break;
+            }
+            let item = line.text[1..].trim_start();
+            if item.is_empty() {
+                self.pos += 1;
+                items.push(self.nested(indent, false)?);
+            } else if is_sequence_item(item) || key_separator(item).is_some() {
+                // A nested node starting on the line of the item, parsed
+                // as if it started on a line of its own
+                let offset = line.text.len() - item.len();
+                let line = &mut self.lines[self.pos];
+                line.indent += offset;
+                line.text = item;
+                items.push(self.node()?);
+            } else {
+                items.push(inline_value(item).map_err(|message| self.error(message))?);
+                self.pos += 1;
+            }
+        }
+        Ok(Value::Array(items))
+    }
+}
+
+/// Parses a YAML document into the JSON value with the same structure.
+///
+/// Only the subset of YAML written by the serializers of the metric tools
+/// is supported: block mappings and sequences, flow collections on a
+/// single line, plain and quoted scalars and comments. Anchors, aliases,
+/// tags and block scalars are errors, and multiple documents are not
+/// supported.
+pub(crate) fn parse_yaml(text: &str) -> Result<Value, YamlError> {
+    let mut lines = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let content = raw.trim_start_matches(' ');
+        if content.starts_with('\t') {
+            return Err(YamlError {
+                line: i + 1,
+                message: "tabs cannot indent YAML".to_owned(),
+            });
+        }
+        let content = strip_comment(content).trim_end();
+        if content.is_empty() || content == "---" || content == "..." {
+            continue;
+        }
+        lines.push(Line {
+            number: i + 1,
+            indent: raw.len() - raw.trim_start_matches(' ').len(),
+            text: content,
+        });
+    }
+    let mut parser = Parser { lines, pos: 0 };
+    if parser.lines.is_empty() {
+        return Ok(Value::Null);
+    }
+    let value = parser.node()?;
+    if parser.pos < parser.lines.len() {
+        return Err(parser.error("unexpected content after the document"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn error(text: &str) -> String {
+        parse_yaml(text).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn block_collections() {
+        let text = "\
+name: a.rs
+spaces:
+- name: f
+  metrics:
+    nargs: [1, 2]
+-
+  name: g
+nested:
+  - - 1
+    - 2
+empty:
+";
+        assert_eq!(
+            parse_yaml(text).unwrap(),
+            json!({
+                "name": "a.rs",
+                "spaces": [{ "name": "f", "metrics": { "nargs": [1, 2] } }, { "name": "g" }],
+                "nested": [[1, 2]],
+                "empty": null,
+            })
+        );
+    }
+
+    #[test]
+    fn flow_collections() {
+        assert_eq!(
+            parse_yaml("a: {b: [1, {c: x}], 'd': \"e, f\"}").unwrap(),
+            json!({ "a": { "b": [1, { "c": "x" }], "d": "e, f" } })
+        );
+        assert_eq!(parse_yaml("[]").unwrap(), json!([]));
+        assert_eq!(parse_yaml("- {}").unwrap(), json!([{}]));
+    }
+
+    #[test]
+    fn scalars() {
+        let text = "\
+double: \"tab\\t \\\"quote\\\" \\u00e8 \\x41\"
+single: 'it''s # not a comment'
+plain: some text # a comment
+int: -3
+float: 1.5e3
+nan: .nan
+bools: [true, FALSE]
+nulls: [~, null, Null]
+version: 1.2.3
+";
+        assert_eq!(
+            parse_yaml(text).unwrap(),
+            json!({
+                "double": "tab\t \"quote\" è A",
+                "single": "it's # not a comment",
+                "plain": "some text",
+                "int": -3,
+                "float": 1500.0,
+                "nan": null,
+                "bools": [true, false],
+                "nulls": [null, null, null],
+                "version": "1.2.3",
+            })
+        );
+    }
+
+    #[test]
+    fn comments_and_documents() {
+        let text = "# metrics\n---\na: 1 # one\n\n# end\nb: '#'\n...\n";
+        assert_eq!(parse_yaml(text).unwrap(), json!({ "a": 1, "b": "#" }));
+        assert_eq!(parse_yaml("# nothing\n").unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(
+            error("a: &anchor 1\nb: *anchor"),
+            "line 1: anchors, aliases and tags are not supported"
+        );
+        assert_eq!(
+            error("a: 1\nb: *anchor"),
+            "line 2: anchors, aliases and tags are not supported"
+        );
+        assert_eq!(
+            error("a: !!str 1"),
+            "line 1: anchors, aliases and tags are not supported"
+        );
+        assert_eq!(
+            error("a: |\n  text"),
+            "line 1: block scalars are not supported"
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(error("a: 1\n\tb: 2"), "line 2: tabs cannot indent YAML");
+        assert_eq!(error("a: 1\n   b: 2"), "line 2: unexpected indentation");
+        assert_eq!(error("a: 1\nb"), "line 2: expected a `key: value` entry");
+        assert_eq!(
+            error("a: \"open"),
+            "line 1: unterminated double-quoted string"
+        );
+        assert_eq!(error("a: \"\\q\""), "line 1: invalid escape `\\q`");
+        assert_eq!(error("a: \"\\u12\""), "line 1: invalid escape `\\u12`");
+        assert_eq!(
+            error("a: [1, 2"),
+            "line 1: expected `,` in a flow collection"
+        );
+        assert_eq!(
+            error("a: {b: 1"),
+            "line 1: expected `,` in a flow collection"
+        );
+        assert_eq!(error("a: {b}"), "line 1: expected `:` in a flow collection");
+        assert_eq!(
+            error("a: [1] x"),
+            "line 1: unexpected text after a flow collection"
+        );
+        assert_eq!(
+            error("- 1\nb: 2"),
+            "line 2: unexpected content after the document"
+        );
+        // Cut anywhere, a document is an error or a value, never a panic
+        let text = "a: {b: [1, \"c\\u00e8\"], 'd': 'e'}\nf:\n  - g: 1\n";
+        for (end, _) in text.char_indices() {
+            let _ = parse_yaml(&text[..end]);
+        }
+    }
+}
@@ -0,0 +1,16 @@
+// header
+fn foo(a: i32) -> i32 {
+    if a > 0 {
+        a
+    } else {
+        -a
+    }
+}
+
+struct S;
+impl S {
+    fn bar(&self) {
+        let x = 1;
+        println!("{}", x);
+    }
+}
@@ -0,0 +1,5 @@
+import functools
+
+@functools.cache
+def f(x):
+    return x + 1
@@ -0,0 +1,6 @@
+#include <stdio.h>
+#ifdef DEBUG
+void trace(int x) {
+    printf("%d\n", x);
+}
+#endif
@@ -0,0 +1,304 @@
+/* Trac du caf */
+/* rpt 0 */
+/* rpt 1 */
+/* rpt 2 */
+/* rpt 3 */
+/* rpt 4 */
+/* rpt 5 */
+/* rpt 6 */
+/* rpt 7 */
+/* rpt 8 */
+/* rpt 9 */
+/* rpt 10 */
+/* rpt 11 */
+/* rpt 12 */
+/* rpt 13 */
+/* rpt 14 */
+/* rpt 15 */
+/* rpt 16 */
+/* rpt 17 */
+/* rpt 18 */
+/* rpt 19 */
+/* rpt 20 */
+/* rpt 21 */
+/* rpt 22 */
+/* rpt 23 */
+/* rpt 24 */
+/* rpt 25 */
+/* rpt 26 */
+/* rpt 27 */
+/* rpt 28 */
+/* rpt 29 */
+/* rpt 30 */
+/* rpt 31 */
+/* rpt 32 */
+/* rpt 33 */
+/* rpt 34 */
+/* rpt 35 */
+/* rpt 36 */
+/* rpt 37 */
+/* rpt 38 */
+/* rpt 39 */
+/* rpt 40 */
+/* rpt 41 */
+/* rpt 42 */
+/* rpt 43 */
+/* rpt 44 */
+/* rpt 45 */
+/* rpt 46 */
+/* rpt 47 */
+/* rpt 48 */
+/* rpt 49 */
+/* rpt 50 */
+/* rpt 51 */
+/* rpt 52 */
+/* rpt 53 */
+/* rpt 54 */
+/* rpt 55 */
+/* rpt 56 */
+/* rpt 57 */
+/* rpt 58 */
+/* rpt 59 */
+/* rpt 60 */
+/* rpt 61 */
+/* rpt 62 */
+/* rpt 63 */
+/* rpt 64 */
+/* rpt 65 */
+/* rpt 66 */
+/* rpt 67 */
+/* rpt 68 */
+/* rpt 69 */
+/* rpt 70 */
+/* rpt 71 */
+/* rpt 72 */
+/* rpt 73 */
+/* rpt 74 */
+/* rpt 75 */
+/* rpt 76 */
+/* rpt 77 */
+/* rpt 78 */
+/* rpt 79 */
+/* rpt 80 */
+/* rpt 81 */
+/* rpt 82 */
+/* rpt 83 */
+/* rpt 84 */
+/* rpt 85 */
+/* rpt 86 */
+/* rpt 87 */
+/* rpt 88 */
+/* rpt 89 */
+/* rpt 90 */
+/* rpt 91 */
+/* rpt 92 */
+/* rpt 93 */
+/* rpt 94 */
+/* rpt 95 */
+/* rpt 96 */
+/* rpt 97 */
+/* rpt 98 */
+/* rpt 99 */
+/* rpt 100 */
+/* rpt 101 */
+/* rpt 102 */
+/* rpt 103 */
+/* rpt 104 */
+/* rpt 105 */
+/* rpt 106 */
+/* rpt 107 */
+/* rpt 108 */
+/* rpt 109 */
+/* rpt 110 */
+/* rpt 111 */
+/* rpt 112 */
+/* rpt 113 */
+/* rpt 114 */
+/* rpt 115 */
+/* rpt 116 */
+/* rpt 117 */
+/* rpt 118 */
+/* rpt 119 */
+/* rpt 120 */
+/* rpt 121 */
+/* rpt 122 */
+/* rpt 123 */
+/* rpt 124 */
+/* rpt 125 */
+/* rpt 126 */
+/* rpt 127 */
+/* rpt 128 */
+/* rpt 129 */
+/* rpt 130 */
+/* rpt 131 */
+/* rpt 132 */
+/* rpt 133 */
+/* rpt 134 */
+/* rpt 135 */
+/* rpt 136 */
+/* rpt 137 */
+/* rpt 138 */
+/* rpt 139 */
+/* rpt 140 */
+/* rpt 141 */
+/* rpt 142 */
+/* rpt 143 */
+/* rpt 144 */
+/* rpt 145 */
+/* rpt 146 */
+/* rpt 147 */
+/* rpt 148 */
+/* rpt 149 */
+/* rpt 150 */
+/* rpt 151 */
+/* rpt 152 */
+/* rpt 153 */
+/* rpt 154 */
+/* rpt 155 */
+/* rpt 156 */
+/* rpt 157 */
+/* rpt 158 */
+/* rpt 159 */
+/* rpt 160 */
+/* rpt 161 */
+/* rpt 162 */
+/* rpt 163 */
+/* rpt 164 */
+/* rpt 165 */
+/* rpt 166 */
+/* rpt 167 */
+/* rpt 168 */
+/* rpt 169 */
+/* rpt 170 */
+/* rpt 171 */
+/* rpt 172 */
+/* rpt 173 */
+/* rpt 174 */
+/* rpt 175 */
+/* rpt 176 */
+/* rpt 177 */
+/* rpt 178 */
+/* rpt 179 */
+/* rpt 180 */
+/* rpt 181 */
+/* rpt 182 */
+/* rpt 183 */
+/* rpt 184 */
+/* rpt 185 */
+/* rpt 186 */
+/* rpt 187 */
+/* rpt 188 */
+/* rpt 189 */
+/* rpt 190 */
+/* rpt 191 */
+/* rpt 192 */
+/* rpt 193 */
+/* rpt 194 */
+/* rpt 195 */
+/* rpt 196 */
+/* rpt 197 */
+/* rpt 198 */
+/* rpt 199 */
+/* rpt 200 */
+/* rpt 201 */
+/* rpt 202 */
+/* rpt 203 */
+/* rpt 204 */
+/* rpt 205 */
+/* rpt 206 */
+/* rpt 207 */
+/* rpt 208 */
+/* rpt 209 */
+/* rpt 210 */
+/* rpt 211 */
+/* rpt 212 */
+/* rpt 213 */
+/* rpt 214 */
+/* rpt 215 */
+/* rpt 216 */
+/* rpt 217 */
+/* rpt 218 */
+/* rpt 219 */
+/* rpt 220 */
+/* rpt 221 */
+/* rpt 222 */
+/* rpt 223 */
+/* rpt 224 */
+/* rpt 225 */
+/* rpt 226 */
+/* rpt 227 */
+/* rpt 228 */
+/* rpt 229 */
+/* rpt 230 */
+/* rpt 231 */
+/* rpt 232 */
+/* rpt 233 */
+/* rpt 234 */
+/* rpt 235 */
+/* rpt 236 */
+/* rpt 237 */
+/* rpt 238 */
+/* rpt 239 */
+/* rpt 240 */
+/* rpt 241 */
+/* rpt 242 */
+/* rpt 243 */
+/* rpt 244 */
+/* rpt 245 */
+/* rpt 246 */
+/* rpt 247 */
+/* rpt 248 */
+/* rpt 249 */
+/* rpt 250 */
+/* rpt 251 */
+/* rpt 252 */
+/* rpt 253 */
+/* rpt 254 */
+/* rpt 255 */
+/* rpt 256 */
+/* rpt 257 */
+/* rpt 258 */
+/* rpt 259 */
+/* rpt 260 */
+/* rpt 261 */
+/* rpt 262 */
+/* rpt 263 */
+/* rpt 264 */
+/* rpt 265 */
+/* rpt 266 */
+/* rpt 267 */
+/* rpt 268 */
+/* rpt 269 */
+/* rpt 270 */
+/* rpt 271 */
+/* rpt 272 */
+/* rpt 273 */
+/* rpt 274 */
+/* rpt 275 */
+/* rpt 276 */
+/* rpt 277 */
+/* rpt 278 */
+/* rpt 279 */
+/* rpt 280 */
+/* rpt 281 */
+/* rpt 282 */
+/* rpt 283 */
+/* rpt 284 */
+/* rpt 285 */
+/* rpt 286 */
+/* rpt 287 */
+/* rpt 288 */
+/* rpt 289 */
+/* rpt 290 */
+/* rpt 291 */
+/* rpt 292 */
+/* rpt 293 */
+/* rpt 294 */
+/* rpt 295 */
+/* rpt 296 */
+/* rpt 297 */
+/* rpt 298 */
+/* rpt 299 */
+int trace(int a) {
+    return a > 0 ? a : -a; /* t */
+}
@@ -0,0 +1,484 @@
+//! Runs the command on the pairs of metric files in `tests/fixtures` and
+//! compares the reports with the ones in the `expected` directory of each
+//! pair. `UPDATE_SNAPSHOTS=1 cargo test` writes them again after a change
+//! of the reports, to be reviewed in the diff.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const FORMATS: &[&str] = &["html", "json"];
+
+fn fixture(case: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(case)
+}
+
+// Runs the command from the directory of a pair, where its source file is,
+// writing the reports in a new output directory
+fn run(case: &str, format: &str) -> (Output, PathBuf) {
+    run_with(case, format, format, &[])
+}
+
+// Runs the command with some more arguments, writing the reports in
+// an output directory of its own, since the tests run in parallel
+fn run_with(case: &str, output: &str, format: &str, args: &[&str]) -> (Output, PathBuf) {
+    let output_path = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("reports")
+        .join(case)
+        .join(output);
+    let _ = fs::remove_dir_all(&output_path);
+    fs::create_dir_all(&output_path).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_json-minimal-tests"))
+        .current_dir(fixture(case))
+        .args(["old", "new", "--format", format])
+        .args(args)
+        .arg("-o")
+        .arg(&output_path)
+        .output()
+        .unwrap();
+    (output, output_path)
+}
+
+// The files of a directory, but the manifest of the run, which records
+// when and where it ran
+fn read_dir(path: &Path) -> Vec<(String, String)> {
+    let mut files: Vec<_> = fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .map(|entry| entry.unwrap())
+                .filter(|entry| entry.file_name() != "manifest.json")
+                .map(|entry| {
+                    (
+                        entry.file_name().to_string_lossy().into_owned(),
+                        fs::read_to_string(entry.path()).unwrap(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+// Compares the reports of a pair in a format with its snapshots
+fn assert_snapshots(case: &str, format: &str) -> Output {
+    let (output, output_path) = run(case, format);
+    let expected_path = fixture(case).join("expected").join(format);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        let _ = fs::remove_dir_all(&expected_path);
+        fs::create_dir_all(&expected_path).unwrap();
+        for (name, content) in read_dir(&output_path) {
+            fs::write(expected_path.join(name), content).unwrap();
+        }
+    }
+    let reports = read_dir(&output_path);
+    let expected = read_dir(&expected_path);
+    assert_eq!(
+        reports.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        expected.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        "{case}: the {format} reports are not the expected ones",
+    );
+    for ((name, report), (_, expected)) in reports.iter().zip(&expected) {
+        assert!(
+            report == expected,
+            "{case}: {name} differs from {}",
+            expected_path.join(name).display(),
+        );
+    }
+    output
+}
+
+#[test]
+fn identical() {
+    for format in FORMATS {
+        let output = assert_snapshots("identical", format);
+        assert!(output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+}
+
+#[test]
+fn metric_change() {
+    for format in FORMATS {
+        let output = assert_snapshots("metric_change", format);
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a.rs\n");
+    }
+    let (_, output_path) = run("metric_change", "json");
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&fs::read(output_path.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["pairs"][0]["status"], "reported");
+    assert_eq!(manifest["total_diffs"], 1);
+    assert_eq!(manifest["reports"][0]["report"], "a.rs.json");
+    assert_eq!(manifest["reports"][0]["metrics"][0], "cyclomatic.sum");
+}
+
+#[test]
+fn toml() {
+    let output = assert_snapshots("metric_change", "toml");
+    assert!(output.status.success());
+}
+
+#[test]
+fn space_added() {
+    for format in FORMATS {
+        let output = assert_snapshots("space_added", format);
+        assert!(output.status.success());
+    }
+}
+
+#[test]
+fn streamed() {
+    // The new files streamed against the old ones, the default, are
+    // reported as when both are parsed as a whole, which --validate needs
+    for case in ["metric_change", "space_added"] {
+        let (_, streamed) = run_with(case, "streamed", "json", &[]);
+        let (_, whole) = run_with(case, "whole", "json", &["--validate"]);
+        assert_eq!(read_dir(&streamed), read_dir(&whole));
+    }
+}
+
+#[test]
+fn malformed() {
+    for format in FORMATS {
+        let output = assert_snapshots("malformed", format);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("cannot parse new/a.rs.json"));
+    }
+}
+
+#[test]
+fn empty() {
+    // The identical blank files are truncated inputs, not pairs without
+    // diffs, so the prescan leaves them to the comparison
+    let (output, _) = run_with("empty", "count", "json", &["--count"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "2 pairs: 0 identical, 2 different\n"
+    );
+    let (output, _) = run_with("empty", "strict", "json", &["--prescan", "--strict"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 corrupt input files found"));
+}
+
+#[test]
+fn non_utf8_source() {
+    for format in FORMATS {
+        assert_snapshots("non_utf8", format);
+    }
+    let (_, output_path) = run("non_utf8", "json");
+    let report = fs::read_to_string(output_path.join("a.rs.json")).unwrap();
+    assert!(report.contains("let x = 1; // 変数"));
+}
+
+// A path which is not UTF-8 is compared like any other one
+#[cfg(unix)]
+#[test]
+fn non_utf8_path() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let new_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(OsStr::from_bytes(b"new\xff"));
+    let _ = fs::remove_dir_all(&new_path);
+    fs::create_dir_all(&new_path).unwrap();
+    for (name, _) in read_dir(&fixture("identical").join("new")) {
+        fs::copy(
+            fixture("identical").join("new").join(&name),
+            new_path.join(&name),
+        )
+        .unwrap();
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_json-minimal-tests"))
+        .current_dir(fixture("identical"))
+        .arg("old")
+        .arg(&new_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn ignore_files() {
+    // old/.ignore leaves out the metric files in build, which differ
+    for format in FORMATS {
+        let output = assert_snapshots("ignore_files", format);
+        assert!(output.status.success());
+        assert!(output.stdout.is_empty());
+    }
+}
+
+#[test]
+fn yaml() {
+    // The same metrics as metric_change, in YAML
+    for format in FORMATS {
+        let output = assert_snapshots("yaml", format);
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a.rs\n");
+    }
+}
+
+#[test]
+fn binary() {
+    // The same metrics as metric_change, in MessagePack and CBOR
+    for case in ["msgpack", "cbor"] {
+        for format in FORMATS {
+            let output = assert_snapshots(case, format);
+            assert!(output.status.success());
+            assert_eq!(String::from_utf8_lossy(&output.stdout), "a.rs\n");
+        }
+    }
+}
+
+#[test]
+fn suppressions() {
+    // The fingerprint of the only diff of metric_change, with a note
+    let suppressions = Path::new(env!("CARGO_TARGET_TMPDIR")).join("suppressions");
+    fs::write(&suppressions, "# Accepted\nbe6d7de1b3694ada loop\n").unwrap();
+    let (output, output_path) = run_with(
+        "metric_change",
+        "suppressions",
+        "json",
+        &["--suppressions", suppressions.to_str().unwrap()],
+    );
+    assert!(output.status.success());
+    assert!(!output_path.join("a.rs.json").exists());
+}
+
+#[test]
+fn group_by_metric() {
+    let (output, output_path) = run_with(
+        "metric_change",
+        "by-metric",
+        "json",
+        &["--group-by", "metric"],
+    );
+    assert!(output.status.success());
+    assert!(!output_path.join("a.rs.json").exists());
+    let report = fs::read_to_string(output_path.join("by-metric.json")).unwrap();
+    assert!(report.contains("\"source\": \"a.rs\""));
+    assert!(report.contains("\"fingerprint\": \"be6d7de1b3694ada\""));
+}
+
+#[test]
+fn top() {
+    // The minimal test of bar has the largest change, the one of foo
+    // the most diffs
+    for (sort_by, kept, left_out) in [("delta", "12,15", "2,8"), ("count", "2,8", "12,15")] {
+        let (output, output_path) =
+            run_with("top", sort_by, "csv", &["--top", "1", "--sort-by", sort_by]);
+        assert!(output.status.success());
+        let report = fs::read_to_string(output_path.join("a.rs.csv")).unwrap();
+        assert!(report.contains(&format!("snippet,{kept},")));
+        assert!(!report.contains(&format!("snippet,{left_out},")));
+    }
+}
+
+#[test]
+fn max_snippets_per_file() {
+    // The minimal test of bar has the largest change
+    let (output, output_path) =
+        run_with("top", "per-file", "json", &["--max-snippets-per-file", "1"]);
+    assert!(output.status.success());
+    let report = fs::read_to_string(output_path.join("a.rs.json")).unwrap();
+    let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert_eq!(report["omitted_snippets"], 1);
+    assert_eq!(report["snippets"].as_array().unwrap().len(), 1);
+    assert_eq!(report["snippets"][0]["start_line"], 12);
+}
+
+#[test]
+fn max_output() {
+    // No budget is small enough for the snippets, all of them are left out
+    let (output, output_path) = run_with("top", "budget", "json", &["--max-output-mb", "0"]);
+    assert!(output.status.success());
+    let report = fs::read_to_string(output_path.join("a.rs.json")).unwrap();
+    let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert_eq!(report["omitted_snippets"], 2);
+    assert!(report["snippets"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn on_existing() {
+    let (_, output_path) = run_with("top", "on-existing", "csv", &[]);
+    let run_again = |policy: &str| {
+        Command::new(env!("CARGO_BIN_EXE_json-minimal-tests"))
+            .current_dir(fixture("top"))
+            .args(["old", "new", "--format", "csv", "--on-existing", policy])
+            .arg("-o")
+            .arg(&output_path)
+            .output()
+            .unwrap()
+    };
+    run_again("version");
+    assert!(output_path.join("a.rs-2.csv").is_file());
+    let output = run_again("error");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("a.rs.csv exists"));
+}
+
+#[test]
+fn clean() {
+    // Only the files of the run go, the one of the user stays
+    let (_, output_path) = run_with("top", "clean", "csv", &[]);
+    let user_file = output_path.join("notes.txt");
+    fs::write(&user_file, "mine").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_json-minimal-tests"))
+        .arg("clean")
+        .arg("-o")
+        .arg(&output_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!output_path.join("a.rs.csv").exists());
+    assert!(!output_path.join("manifest.sha256").exists());
+    assert!(user_file.is_file());
+}
+
+#[test]
+fn config() {
+    // The format of the command line wins over the one of the file,
+    // the sort of the command line needs the top of the file
+    let config_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config.toml");
+    fs::write(&config_path, "format = \"json\"\ntop = 1\n").unwrap();
+    let config_arg = config_path.to_str().unwrap();
+    let (output, output_path) = run_with(
+        "top",
+        "config",
+        "csv",
+        &["--config", config_arg, "--sort-by", "count"],
+    );
+    assert!(output.status.success());
+    let report = fs::read_to_string(output_path.join("a.rs.csv")).unwrap();
+    assert!(report.contains("snippet,2,8,"));
+    assert!(!report.contains("snippet,12,15,"));
+}
+
+#[test]
+fn profile() {
+    // The strict profile stops at the malformed pair, unless the command
+    // line keeps going
+    let (output, _) = run_with("malformed", "strict", "json", &["--profile", "strict"]);
+    assert!(!output.status.success());
+    let (output, _) = run_with(
+        "malformed",
+        "strict-keep-going",
+        "json",
+        &["--profile", "strict", "--keep-going"],
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn enclose() {
+    // The decorator of the Python function and the conditional around
+    // the C one are part of their minimal tests
+    let (output, output_path) = run("enclose", "csv");
+    assert!(output.status.success());
+    let report = fs::read_to_string(output_path.join("a.py.csv")).unwrap();
+    assert!(report.contains("snippet,3,5,"));
+    let report = fs::read_to_string(output_path.join("b.c.csv")).unwrap();
+    assert!(report.contains("snippet,2,6,"));
+}
+
+#[test]
+fn encoding() {
+    // The Windows-1252 source, longer than 4 KB, is decoded up to the
+    // minimal test at its end
+    let (output, output_path) = run("encoding", "markdown");
+    assert!(output.status.success());
+    let report = fs::read_to_string(output_path.join("a.c.md")).unwrap();
+    assert!(report.contains("return a > 0 ? a : -a; /* été */"));
+}
+
+#[test]
+fn emit_fixtures() {
+    let fixtures_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fixtures");
+    let _ = fs::remove_dir_all(&fixtures_path);
+    let (output, _) = run_with(
+        "top",
+        "fixtures",
+        "csv",
+        &["--emit-fixtures", fixtures_path.to_str().unwrap()],
+    );
+    assert!(output.status.success());
+    let code = fs::read_to_string(fixtures_path.join("a_12-15.rs")).unwrap();
+    assert!(code.starts_with("    fn bar(&self) {"));
+    // The lines of the space start from the one of the minimal test
+    let expected = fs::read_to_string(fixtures_path.join("a_12-15.rs.expected.json")).unwrap();
+    let expected: serde_json::Value = serde_json::from_str(&expected).unwrap();
+    assert_eq!(expected["spaces"][0]["name"], "bar");
+    assert_eq!(expected["spaces"][0]["start_line"], 1);
+    assert_eq!(expected["spaces"][0]["end_line"], 4);
+}
+
+#[test]
+fn diff_results() {
+    // The top pair has two diffs more than metric_change
+    let (_, output_path) = run("top", "json");
+    let output = Command::new(env!("CARGO_BIN_EXE_json-minimal-tests"))
+        .arg("diff-results")
+        .arg(fixture("metric_change").join("expected").join("json"))
+        .arg(&output_path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with(
+        "New: 2
+  a.rs .spaces[0].metrics.loc.lloc: 3.0 -> 4.0 [b6583773c8e70dbf]
+  a.rs .spaces[1].spaces[0].metrics.loc.sloc: 8.0 -> 20.0 [a048359189b2b69b]
+Fixed: 0
+Unchanged: 1
+"
+    ));
+}
+
+// The trend reads back the runs recorded with --db, when the sqlite3
+// shell is installed
+#[test]
+fn results_database() {
+    if Command::new("sqlite3").arg("-version").output().is_err() {
+        return;
+    }
+    let db_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("results.sqlite");
+    let _ = fs::remove_file(&db_path);
+    let db = db_path.to_str().unwrap();
+    for output in ["db1", "db2"] {
+        let (output, _) = run_with("metric_change", output, "json", &["--db", db]);
+        assert!(output.status.success(), "{output:?}");
+    }
+    let trend = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_json-minimal-tests"))
+            .args(["trend", db, "--csv"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            // Leave out the start times
+            .map(|line| {
+                let mut fields: Vec<&str> = line.split(',').collect();
+                fields.remove(1);
+                fields.join(",")
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(trend(&[]), ["run,cyclomatic.sum", "1,1", "2,1"]);
+    assert_eq!(trend(&["--last", "1"]), ["run,cyclomatic.sum", "2,1"]);
+    assert_eq!(trend(&["--metric", "halstead"]), ["run", "1", "2"]);
+}
+
+#[test]
+fn fail_fast() {
+    // The failed pair is still in the reports and in the count
+    let (output, output_path) = run_with("malformed", "fail-fast", "junit", &["--fail-fast"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Stopped at the first failed pair"));
+    assert!(stderr.contains("1 pairs could not be compared"));
+    let junit = fs::read_to_string(output_path.join("junit.xml")).unwrap();
+    assert!(junit.contains("a.rs.json"));
+}